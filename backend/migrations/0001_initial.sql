@@ -0,0 +1,25 @@
+CREATE TABLE projects (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL
+);
+
+CREATE TABLE categories (
+    id TEXT PRIMARY KEY NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    weekly_target_hours REAL
+);
+
+-- project_id and category_id are deliberately not foreign keys: the frontend
+-- can delete a category while entries still reference it.
+CREATE TABLE time_entries (
+    id TEXT PRIMARY KEY NOT NULL,
+    description TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER,
+    project_id TEXT,
+    category_id TEXT
+);
+
+CREATE INDEX time_entries_start_time ON time_entries (start_time);
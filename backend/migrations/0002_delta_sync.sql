@@ -0,0 +1,19 @@
+-- Server time (ms since epoch) at which each record last changed, so a sync
+-- only has to ship what is newer than the client's cursor.
+ALTER TABLE time_entries ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE projects ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN updated_at INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX time_entries_updated_at ON time_entries (updated_at);
+CREATE INDEX projects_updated_at ON projects (updated_at);
+CREATE INDEX categories_updated_at ON categories (updated_at);
+
+-- One row per deleted record; `kind` is the name of the table it lived in.
+CREATE TABLE tombstones (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, id)
+);
+
+CREATE INDEX tombstones_deleted_at ON tombstones (deleted_at);
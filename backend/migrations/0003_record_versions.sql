@@ -0,0 +1,15 @@
+-- Server-assigned version of each record, bumped on every change. Clients
+-- send back the version they last saw so concurrent edits can be detected.
+ALTER TABLE time_entries ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE projects ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+ALTER TABLE categories ADD COLUMN version INTEGER NOT NULL DEFAULT 1;
+
+-- Serialized content of every version, used as the base of three-way merges.
+CREATE TABLE record_history (
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    PRIMARY KEY (kind, id, version)
+);
@@ -0,0 +1,7 @@
+-- Small key/value store for sync bookkeeping. `tombstones_purged_before` is the
+-- newest cutoff used to garbage-collect tombstones: a client whose cursor is
+-- older may have missed deletions and has to resync from scratch.
+CREATE TABLE sync_meta (
+    key TEXT PRIMARY KEY NOT NULL,
+    value INTEGER NOT NULL
+);
@@ -0,0 +1,107 @@
+CREATE TABLE users (
+    id INTEGER PRIMARY KEY,
+    username TEXT NOT NULL UNIQUE COLLATE NOCASE,
+    password_hash TEXT NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+-- Bearer tokens are stored as SHA-256 hashes, never in the clear.
+CREATE TABLE auth_tokens (
+    token_hash TEXT PRIMARY KEY NOT NULL,
+    user_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL,
+    expires_at INTEGER NOT NULL,
+    revoked_at INTEGER
+);
+
+CREATE INDEX auth_tokens_user_id ON auth_tokens (user_id);
+
+-- Every record now belongs to a user, and ids only have to be unique per
+-- user (every store starts out with project p1). Rows stored before accounts
+-- existed get user 0 and are handed to the first account that registers.
+ALTER TABLE time_entries RENAME TO time_entries_old;
+ALTER TABLE projects RENAME TO projects_old;
+ALTER TABLE categories RENAME TO categories_old;
+ALTER TABLE tombstones RENAME TO tombstones_old;
+ALTER TABLE record_history RENAME TO record_history_old;
+
+CREATE TABLE time_entries (
+    user_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    end_time INTEGER,
+    project_id TEXT,
+    category_id TEXT,
+    updated_at INTEGER NOT NULL DEFAULT 0,
+    version INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, id)
+);
+
+CREATE TABLE projects (
+    user_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    updated_at INTEGER NOT NULL DEFAULT 0,
+    version INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, id)
+);
+
+CREATE TABLE categories (
+    user_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    color TEXT NOT NULL,
+    weekly_target_hours REAL,
+    updated_at INTEGER NOT NULL DEFAULT 0,
+    version INTEGER NOT NULL DEFAULT 1,
+    PRIMARY KEY (user_id, id)
+);
+
+CREATE TABLE tombstones (
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, kind, id)
+);
+
+CREATE TABLE record_history (
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    recorded_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, kind, id, version)
+);
+
+INSERT INTO time_entries
+    (user_id, id, description, start_time, end_time, project_id, category_id, updated_at, version)
+SELECT 0, id, description, start_time, end_time, project_id, category_id, updated_at, version
+FROM time_entries_old;
+
+INSERT INTO projects (user_id, id, name, color, updated_at, version)
+SELECT 0, id, name, color, updated_at, version FROM projects_old;
+
+INSERT INTO categories (user_id, id, name, color, weekly_target_hours, updated_at, version)
+SELECT 0, id, name, color, weekly_target_hours, updated_at, version FROM categories_old;
+
+INSERT INTO tombstones (user_id, kind, id, deleted_at)
+SELECT 0, kind, id, deleted_at FROM tombstones_old;
+
+INSERT INTO record_history (user_id, kind, id, version, data, recorded_at)
+SELECT 0, kind, id, version, data, recorded_at FROM record_history_old;
+
+DROP TABLE time_entries_old;
+DROP TABLE projects_old;
+DROP TABLE categories_old;
+DROP TABLE tombstones_old;
+DROP TABLE record_history_old;
+
+CREATE INDEX time_entries_start_time ON time_entries (user_id, start_time);
+CREATE INDEX time_entries_updated_at ON time_entries (user_id, updated_at);
+CREATE INDEX projects_updated_at ON projects (user_id, updated_at);
+CREATE INDEX categories_updated_at ON categories (user_id, updated_at);
+CREATE INDEX tombstones_deleted_at ON tombstones (deleted_at);
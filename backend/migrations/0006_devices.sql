@@ -0,0 +1,17 @@
+-- Each client a user logs in from. The server keeps the device's sync cursor
+-- so clients no longer have to track `last_synced_at` themselves.
+CREATE TABLE devices (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    last_synced_at INTEGER,
+    sync_cursor INTEGER NOT NULL DEFAULT 0,
+    revoked_at INTEGER
+);
+
+CREATE INDEX devices_user_id ON devices (user_id);
+
+-- Tokens issued to a device are revoked along with it. Tokens from before
+-- devices existed have none.
+ALTER TABLE auth_tokens ADD COLUMN device_id INTEGER;
@@ -0,0 +1,3 @@
+-- Archived projects are hidden from pickers but keep their entries and still
+-- show up in reports.
+ALTER TABLE projects ADD COLUMN archived INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,7 @@
+-- One subscribable calendar feed per user. Calendar apps cannot send bearer
+-- tokens, so the feed has its own token, carried in its URL and stored hashed.
+CREATE TABLE calendar_feeds (
+    user_id INTEGER PRIMARY KEY,
+    token_hash TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL
+);
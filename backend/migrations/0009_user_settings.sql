@@ -0,0 +1,4 @@
+-- Where each user is, so days and weeks are reported the way they see them.
+ALTER TABLE users ADD COLUMN timezone TEXT NOT NULL DEFAULT 'UTC';
+-- The day weeks start on, counted from Monday: 0 for Monday, 6 for Sunday.
+ALTER TABLE users ADD COLUMN week_start INTEGER NOT NULL DEFAULT 0;
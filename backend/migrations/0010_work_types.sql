@@ -0,0 +1,17 @@
+-- Each user's work-type rules as JSON. Users without a row use the defaults.
+CREATE TABLE work_type_rules (
+    user_id INTEGER PRIMARY KEY,
+    rules TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+-- The work type each entry was classified as, with the description it was
+-- classified from so an edited description is classified again.
+CREATE TABLE entry_work_types (
+    user_id INTEGER NOT NULL,
+    entry_id TEXT NOT NULL,
+    description TEXT NOT NULL,
+    work_type TEXT NOT NULL,
+    classified_at INTEGER NOT NULL,
+    PRIMARY KEY (user_id, entry_id)
+);
@@ -0,0 +1,36 @@
+-- Entries older than `older_than_days` are archived or deleted. A policy
+-- without a project covers entries whose project has no policy of its own.
+CREATE TABLE retention_policies (
+    user_id INTEGER NOT NULL,
+    project_id TEXT,
+    older_than_days INTEGER NOT NULL,
+    action TEXT NOT NULL,
+    updated_at INTEGER NOT NULL
+);
+
+CREATE UNIQUE INDEX retention_policies_scope
+    ON retention_policies (user_id, IFNULL(project_id, ''));
+
+-- Entries a policy took out of the live data, as they were then.
+CREATE TABLE archived_entries (
+    user_id INTEGER NOT NULL,
+    id TEXT NOT NULL,
+    entry TEXT NOT NULL,
+    start_time INTEGER NOT NULL,
+    archived_at INTEGER NOT NULL,
+    run_id INTEGER NOT NULL,
+    PRIMARY KEY (user_id, id)
+);
+
+-- Every policy run that removed entries, and the entries it removed.
+CREATE TABLE retention_runs (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    ran_at INTEGER NOT NULL,
+    trigger TEXT NOT NULL,
+    archived INTEGER NOT NULL,
+    deleted INTEGER NOT NULL,
+    entries TEXT NOT NULL
+);
+
+CREATE INDEX retention_runs_user_id ON retention_runs (user_id, ran_at);
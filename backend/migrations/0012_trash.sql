@@ -0,0 +1,16 @@
+-- Deleted records as they were before deletion, so they can be restored.
+-- Records deleted in the same transaction share `deleted_at`, which is how
+-- one operation is undone as a whole. `undone_at` is set when an undo took
+-- that operation, so records that could not be restored then do not hold up
+-- undoing older deletions.
+CREATE TABLE trash (
+    user_id INTEGER NOT NULL,
+    kind TEXT NOT NULL,
+    id TEXT NOT NULL,
+    data TEXT NOT NULL,
+    deleted_at INTEGER NOT NULL,
+    undone_at INTEGER,
+    PRIMARY KEY (user_id, kind, id)
+);
+
+CREATE INDEX trash_deleted_at ON trash (user_id, deleted_at);
@@ -0,0 +1,29 @@
+-- Every create, update and delete of an entry, project or category. `actor_id`
+-- and `device_id` are NULL for changes the server made on its own; `changes`
+-- maps each changed field to its value before and after.
+CREATE TABLE audit_log (
+    id INTEGER PRIMARY KEY,
+    user_id INTEGER NOT NULL,
+    actor_id INTEGER,
+    device_id INTEGER,
+    kind TEXT NOT NULL,
+    record_id TEXT NOT NULL,
+    action TEXT NOT NULL,
+    version INTEGER NOT NULL,
+    changes TEXT NOT NULL,
+    changed_at INTEGER NOT NULL
+);
+
+CREATE INDEX audit_log_changed_at ON audit_log (user_id, changed_at);
+CREATE INDEX audit_log_record ON audit_log (user_id, kind, record_id);
+
+-- The log is append-only.
+CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append-only');
+END;
+
+CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log
+BEGIN
+    SELECT RAISE(ABORT, 'the audit log is append-only');
+END;
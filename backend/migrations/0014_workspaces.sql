@@ -0,0 +1,29 @@
+-- Workspaces share projects and categories between accounts. Their records
+-- live in the same tables as an account's, under an owner id that is the
+-- negated id of a row in `stores` so it never clashes with a user id: one
+-- store per workspace for the shared records, and one per member for the
+-- entries they log in it.
+CREATE TABLE stores (
+    id INTEGER PRIMARY KEY
+);
+
+CREATE TABLE workspaces (
+    id INTEGER PRIMARY KEY,
+    name TEXT NOT NULL,
+    store_id INTEGER NOT NULL,
+    created_at INTEGER NOT NULL
+);
+
+-- Members who leave keep their row, and their entries keep counting in team
+-- totals; rejoining clears `left_at` and brings the entries back.
+CREATE TABLE workspace_members (
+    workspace_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role TEXT NOT NULL,
+    store_id INTEGER NOT NULL,
+    joined_at INTEGER NOT NULL,
+    left_at INTEGER,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX workspace_members_user_id ON workspace_members (user_id);
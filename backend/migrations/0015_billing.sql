@@ -0,0 +1,14 @@
+-- Billing: entries and projects can be billable and carry an hourly rate in
+-- a currency. An entry's own flag and rate win over its project's, and a
+-- project's rate over its workspace's default.
+ALTER TABLE time_entries ADD COLUMN billable INTEGER;
+ALTER TABLE time_entries ADD COLUMN hourly_rate REAL;
+ALTER TABLE time_entries ADD COLUMN currency TEXT;
+
+ALTER TABLE projects ADD COLUMN billable INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE projects ADD COLUMN client TEXT;
+ALTER TABLE projects ADD COLUMN hourly_rate REAL;
+ALTER TABLE projects ADD COLUMN currency TEXT;
+
+ALTER TABLE workspaces ADD COLUMN hourly_rate REAL;
+ALTER TABLE workspaces ADD COLUMN currency TEXT;
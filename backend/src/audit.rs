@@ -0,0 +1,187 @@
+// backend/src/audit.rs
+//! REST endpoints for reading the audit log of changes to entries, projects
+//! and categories.
+//!
+//! Changes to a workspace's shared projects and categories are read by its
+//! owners and admins under `/workspaces/{id}/audit`, and changes to a
+//! member's entries in it under `/workspaces/{id}/members/{user_id}/audit`,
+//! by that member and by owners and admins.
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::auth::AuthUser;
+use crate::db::{AuditEvent, AuditQuery, DeviceId, RecordKind, UserId, WorkspaceId};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::Validate;
+use crate::workspaces;
+use crate::AppState;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Filters for the log. `from` and `to` are epoch milliseconds and match
+/// changes made within `[from, to)`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ListAudit {
+    pub kind: Option<RecordKind>,
+    pub record_id: Option<String>,
+    pub device: Option<DeviceId>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for ListAudit {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            record_id: None,
+            device: None,
+            from: None,
+            to: None,
+            limit: DEFAULT_PAGE_SIZE,
+            offset: 0,
+        }
+    }
+}
+
+impl Validate for ListAudit {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if to < from {
+                errors.push(FieldError::new(
+                    join_path(path, "to"),
+                    "must not be before from",
+                ));
+            }
+        }
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit) {
+            errors.push(FieldError::new(
+                join_path(path, "limit"),
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if self.offset < 0 {
+            errors.push(FieldError::new(
+                join_path(path, "offset"),
+                "must not be negative",
+            ));
+        }
+    }
+}
+
+/// List changes to the user's records, newest first
+pub async fn list_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ListAudit>,
+) -> Result<Json<Vec<AuditEvent>>, ApiError> {
+    Ok(Json(events_in(&state, auth.user.id, query)?))
+}
+
+/// List changes to a workspace's shared projects and categories, newest
+/// first
+pub async fn list_workspace_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace): Path<WorkspaceId>,
+    ValidQuery(query): ValidQuery<ListAudit>,
+) -> Result<Json<Vec<AuditEvent>>, ApiError> {
+    let membership = workspaces::manager(
+        &state,
+        &auth,
+        workspace,
+        "only owners and admins can read the workspace's audit log",
+    )?;
+    Ok(Json(events_in(&state, membership.shared_store, query)?))
+}
+
+/// List changes to a member's entries in a workspace, newest first
+pub async fn list_member_events(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member)): Path<(WorkspaceId, UserId)>,
+    ValidQuery(query): ValidQuery<ListAudit>,
+) -> Result<Json<Vec<AuditEvent>>, ApiError> {
+    let (_, store) = workspaces::member_entries(
+        &state,
+        &auth,
+        workspace,
+        member,
+        "only owners and admins can read other members' audit log",
+    )?;
+    Ok(Json(events_in(&state, store, query)?))
+}
+
+fn events_in(
+    state: &AppState,
+    store: UserId,
+    query: ListAudit,
+) -> Result<Vec<AuditEvent>, ApiError> {
+    Ok(state.db.list_audit_events(
+        store,
+        &AuditQuery {
+            kind: query.kind,
+            record_id: query.record_id,
+            device_id: query.device,
+            from: query.from,
+            to: query.to,
+            limit: query.limit,
+            offset: query.offset,
+        },
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entries::{create_entry, delete_entry};
+    use crate::error::ValidJson;
+    use crate::model::TimeEntry;
+
+    #[tokio::test]
+    async fn test_handlers_log_their_device() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let device = state.db.create_device(ada.user.id, "Laptop").unwrap();
+        let auth = AuthUser {
+            device: Some(device.id),
+            ..ada
+        };
+        let entry = TimeEntry {
+            id: "e1".to_string(),
+            description: "Review".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            ..Default::default()
+        };
+        let _ = create_entry(State(state.clone()), auth.clone(), ValidJson(entry))
+            .await
+            .unwrap();
+        delete_entry(State(state.clone()), auth.clone(), Path("e1".to_string()))
+            .await
+            .unwrap();
+
+        let query = ListAudit {
+            record_id: Some("e1".to_string()),
+            ..ListAudit::default()
+        };
+        let events = list_events(State(state), auth.clone(), ValidQuery(query))
+            .await
+            .unwrap();
+        assert_eq!(events.len(), 2);
+        // Newest first: the deletion clears every field.
+        assert_eq!(
+            events[0].changes["description"]["after"],
+            serde_json::Value::Null
+        );
+        assert!(events.iter().all(
+            |event| event.actor_id == Some(auth.user.id) && event.device_id == Some(device.id)
+        ));
+    }
+}
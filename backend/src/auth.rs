@@ -0,0 +1,366 @@
+// backend/src/auth.rs
+//! Accounts and bearer-token authentication.
+//!
+//! Passwords are hashed with Argon2id. Tokens are 32 random bytes handed to
+//! the client once and stored only as a SHA-256 hash, so a leaked database
+//! cannot be used to impersonate anyone.
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::{FromRequestParts, State},
+    http::{header, request::Parts, HeaderMap, StatusCode},
+    Json,
+};
+use chrono::Weekday;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::Write;
+use std::sync::LazyLock;
+
+use crate::db::{Actor, Db, Device, DeviceId, User};
+use crate::devices;
+use crate::error::{join_path, ApiError, FieldError, ValidJson};
+use crate::model::Validate;
+use crate::periods;
+use crate::AppState;
+
+const MIN_PASSWORD_LENGTH: usize = 8;
+const MAX_PASSWORD_LENGTH: usize = 1024;
+const MAX_USERNAME_LENGTH: usize = 64;
+
+/// Verified against when a username does not exist, so failed logins take
+/// the same time whether or not the account is real.
+static DUMMY_HASH: LazyLock<String> = LazyLock::new(|| hash_password("not a real password"));
+
+#[derive(Debug, Deserialize)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+    /// Registers a device and ties the issued token to it.
+    #[serde(default)]
+    pub device_name: Option<String>,
+}
+
+impl Validate for Credentials {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let username = self.username.trim();
+        if username.is_empty() || username.len() > MAX_USERNAME_LENGTH {
+            errors.push(FieldError::new(
+                join_path(path, "username"),
+                format!("must be between 1 and {MAX_USERNAME_LENGTH} characters"),
+            ));
+        }
+        if self.password.is_empty() || self.password.len() > MAX_PASSWORD_LENGTH {
+            errors.push(FieldError::new(
+                join_path(path, "password"),
+                format!("must be between 1 and {MAX_PASSWORD_LENGTH} characters"),
+            ));
+        }
+        if let Some(name) = &self.device_name {
+            devices::validate_name(name, &join_path(path, "device_name"), errors);
+        }
+    }
+}
+
+/// Changes to an account's settings; fields left out stay as they are.
+#[derive(Debug, Default, Deserialize)]
+pub struct SettingsPatch {
+    /// IANA time zone, e.g. `Europe/Berlin`.
+    pub timezone: Option<String>,
+    /// `Mon` through `Sun`, or the full name.
+    pub week_start: Option<Weekday>,
+}
+
+impl Validate for SettingsPatch {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let Some(timezone) = &self.timezone {
+            periods::validate_timezone(timezone, &join_path(path, "timezone"), errors);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TokenResponse {
+    pub token: String,
+    pub expires_at: i64,
+    pub user: User,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device: Option<Device>,
+}
+
+/// The account a request was made with, taken from its bearer token, and
+/// the device the token was issued to.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user: User,
+    pub device: Option<DeviceId>,
+}
+
+impl AuthUser {
+    /// A database handle whose changes are logged as made by this user and
+    /// device.
+    pub fn db(&self, state: &AppState) -> Db {
+        state.db.acting_as(Actor {
+            user: self.user.id,
+            device: self.device,
+        })
+    }
+}
+
+impl FromRequestParts<AppState> for AuthUser {
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, ApiError> {
+        let token = bearer_token(&parts.headers).ok_or(ApiError::Unauthorized)?;
+        let (user, device) = state
+            .db
+            .session_for_token(&hash_token(token))?
+            .ok_or(ApiError::Unauthorized)?;
+        Ok(AuthUser { user, device })
+    }
+}
+
+/// Create an account and log it in
+pub async fn register(
+    State(state): State<AppState>,
+    ValidJson(credentials): ValidJson<Credentials>,
+) -> Result<(StatusCode, Json<TokenResponse>), ApiError> {
+    if credentials.password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(ApiError::Validation(vec![FieldError::new(
+            "password",
+            format!("must be at least {MIN_PASSWORD_LENGTH} characters"),
+        )]));
+    }
+
+    let password = credentials.password;
+    let password_hash = tokio::task::spawn_blocking(move || hash_password(&password)).await?;
+    let user = state
+        .db
+        .create_user(credentials.username.trim(), &password_hash)?
+        .ok_or_else(|| ApiError::Conflict("username is already taken".to_string()))?;
+
+    let response = issue_token(&state, user, credentials.device_name.as_deref())?;
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Exchange a username and password for a bearer token
+pub async fn login(
+    State(state): State<AppState>,
+    ValidJson(credentials): ValidJson<Credentials>,
+) -> Result<Json<TokenResponse>, ApiError> {
+    let account = state.db.find_credentials(credentials.username.trim())?;
+    let (user, stored_hash) = match account {
+        Some((user, hash)) => (Some(user), hash),
+        None => (None, DUMMY_HASH.clone()),
+    };
+
+    let password = credentials.password;
+    let verified =
+        tokio::task::spawn_blocking(move || verify_password(&password, &stored_hash)).await?;
+    match user {
+        Some(user) if verified => Ok(Json(issue_token(
+            &state,
+            user,
+            credentials.device_name.as_deref(),
+        )?)),
+        _ => Err(ApiError::Unauthorized),
+    }
+}
+
+/// Revoke the token the request was made with
+pub async fn logout(
+    State(state): State<AppState>,
+    _user: AuthUser,
+    headers: HeaderMap,
+) -> Result<StatusCode, ApiError> {
+    if let Some(token) = bearer_token(&headers) {
+        state.db.revoke_token(&hash_token(token))?;
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The account the token belongs to
+pub async fn me(auth: AuthUser) -> Json<User> {
+    Json(auth.user)
+}
+
+/// Change the account's time zone or week start
+pub async fn update_me(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(patch): ValidJson<SettingsPatch>,
+) -> Result<Json<User>, ApiError> {
+    let timezone = patch.timezone.unwrap_or(auth.user.timezone);
+    let week_start = patch.week_start.unwrap_or(auth.user.week_start);
+    let user = state
+        .db
+        .update_user_settings(auth.user.id, &timezone, week_start)?;
+    Ok(Json(user))
+}
+
+fn issue_token(
+    state: &AppState,
+    user: User,
+    device_name: Option<&str>,
+) -> Result<TokenResponse, ApiError> {
+    let device = device_name
+        .map(|name| state.db.create_device(user.id, name.trim()))
+        .transpose()?;
+    let token = generate_token();
+    let ttl = chrono::Duration::days(state.config.auth.token_ttl_days);
+    let expires_at = (chrono::Utc::now() + ttl).timestamp_millis();
+    state.db.create_token(
+        user.id,
+        device.as_ref().map(|device| device.id),
+        &hash_token(&token),
+        expires_at,
+    )?;
+    Ok(TokenResponse {
+        token,
+        expires_at,
+        user,
+        device,
+    })
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, token) = value.split_once(' ')?;
+    let token = token.trim();
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+fn generate_token() -> String {
+    random_hex(32)
+}
+
+/// `len` random bytes from the OS, hex-encoded.
+pub fn random_hex(len: usize) -> String {
+    let mut bytes = vec![0u8; len];
+    OsRng.fill_bytes(&mut bytes);
+    to_hex(&bytes)
+}
+
+/// A token as it is stored: its SHA-256, hex-encoded.
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{byte:02x}");
+        hex
+    })
+}
+
+fn hash_password(password: &str) -> String {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .expect("argon2 accepts any password and a generated salt")
+        .to_string()
+}
+
+fn verify_password(password: &str, stored_hash: &str) -> bool {
+    PasswordHash::new(stored_hash)
+        .and_then(|hash| Argon2::default().verify_password(password.as_bytes(), &hash))
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::Request;
+
+    fn parts(authorization: &str) -> Parts {
+        let request = Request::builder()
+            .header(header::AUTHORIZATION, authorization)
+            .body(())
+            .unwrap();
+        request.into_parts().0
+    }
+
+    fn headers(authorization: &str) -> HeaderMap {
+        parts(authorization).headers
+    }
+
+    #[test]
+    fn test_password_round_trip() {
+        let hash = hash_password("correct horse");
+        assert!(verify_password("correct horse", &hash));
+        assert!(!verify_password("wrong horse", &hash));
+        assert!(!verify_password("correct horse", "not a phc string"));
+    }
+
+    #[test]
+    fn test_bearer_token_parsing() {
+        assert_eq!(bearer_token(&headers("Bearer abc")), Some("abc"));
+        assert_eq!(bearer_token(&headers("bearer  abc ")), Some("abc"));
+        assert_eq!(bearer_token(&headers("Basic abc")), None);
+        assert_eq!(bearer_token(&headers("Bearer ")), None);
+    }
+
+    #[tokio::test]
+    async fn test_settings_patch_keeps_left_out_fields() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let patch = SettingsPatch {
+            timezone: Some("America/New_York".to_string()),
+            ..SettingsPatch::default()
+        };
+        let user = update_me(State(state.clone()), auth.clone(), ValidJson(patch))
+            .await
+            .unwrap();
+        assert_eq!(user.timezone, "America/New_York");
+        assert_eq!(user.week_start, Weekday::Mon);
+
+        let mut errors = Vec::new();
+        let invalid = SettingsPatch {
+            timezone: Some("Mars/Olympus".to_string()),
+            ..SettingsPatch::default()
+        };
+        invalid.validate("", &mut errors);
+        assert_eq!(errors[0].field, "timezone");
+    }
+
+    #[tokio::test]
+    async fn test_register_login_and_logout() {
+        let state = AppState::for_tests();
+        let credentials = || Credentials {
+            username: "ada".to_string(),
+            password: "correct horse".to_string(),
+            device_name: None,
+        };
+
+        let (status, registered) = register(State(state.clone()), ValidJson(credentials()))
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+
+        let duplicate = register(State(state.clone()), ValidJson(credentials())).await;
+        assert!(matches!(duplicate, Err(ApiError::Conflict(_))));
+
+        let wrong = Credentials {
+            password: "wrong horse".to_string(),
+            ..credentials()
+        };
+        let rejected = login(State(state.clone()), ValidJson(wrong)).await;
+        assert!(matches!(rejected, Err(ApiError::Unauthorized)));
+
+        let logged_in = login(State(state.clone()), ValidJson(credentials()))
+            .await
+            .unwrap();
+        assert_eq!(logged_in.user, registered.user);
+
+        let mut request = parts(&format!("Bearer {}", logged_in.token));
+        let user = AuthUser::from_request_parts(&mut request, &state)
+            .await
+            .unwrap();
+        logout(State(state.clone()), user, request.headers.clone())
+            .await
+            .unwrap();
+        let revoked = AuthUser::from_request_parts(&mut request, &state).await;
+        assert!(matches!(revoked, Err(ApiError::Unauthorized)));
+    }
+}
@@ -0,0 +1,410 @@
+// backend/src/billing.rs
+//! Billing report: billable time and what it is worth, per client and
+//! project.
+//!
+//! An entry is billable if it says so, or else if its project is. Its rate
+//! is its own, or else its project's, or else the workspace's default.
+//! Each entry's time is clipped to the period and then rounded on its own,
+//! the way it would appear as a line on a timesheet. Running entries are
+//! left out until they stop.
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::db::WorkspaceId;
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::{Project, TimeEntry, Validate};
+use crate::workspaces;
+use crate::AppState;
+
+const MINUTE: i64 = 60 * 1000;
+const HOUR: f64 = 60.0 * 60.0 * 1000.0;
+
+/// Longest rounding step, in minutes.
+const MAX_ROUND_TO_MINUTES: i64 = 60;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rounding {
+    #[default]
+    Nearest,
+    Up,
+    Down,
+}
+
+impl Rounding {
+    fn apply(self, ms: i64, step: i64) -> i64 {
+        let steps = match self {
+            Rounding::Nearest => (ms + step / 2) / step,
+            Rounding::Up => (ms + step - 1) / step,
+            Rounding::Down => ms / step,
+        };
+        steps * step
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BillingQuery {
+    pub from: i64,
+    pub to: i64,
+    /// Round each entry to a multiple of this many minutes, e.g. 6 or 15.
+    #[serde(default)]
+    pub round_to_minutes: Option<i64>,
+    #[serde(default)]
+    pub rounding: Rounding,
+    /// Bill a workspace's time instead of the caller's own.
+    #[serde(default)]
+    pub workspace: Option<WorkspaceId>,
+}
+
+impl Validate for BillingQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.to < self.from {
+            errors.push(FieldError::new(
+                join_path(path, "to"),
+                "must not be before from",
+            ));
+        }
+        if self
+            .round_to_minutes
+            .is_some_and(|minutes| !(1..=MAX_ROUND_TO_MINUTES).contains(&minutes))
+        {
+            errors.push(FieldError::new(
+                join_path(path, "round_to_minutes"),
+                format!("must be between 1 and {MAX_ROUND_TO_MINUTES}"),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BillingReport {
+    pub from: i64,
+    pub to: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub round_to_minutes: Option<i64>,
+    pub rounding: Rounding,
+    pub lines: Vec<BillingLine>,
+    /// What the rated lines add up to, per currency.
+    pub totals: Vec<CurrencyTotal>,
+    /// Billable time that has no rate, also listed among the lines.
+    pub unrated_ms: i64,
+    /// Time in the period that is not billable, rounded like the rest.
+    pub non_billable_ms: i64,
+}
+
+/// Billable time of one project at one rate. Lines come by client, then
+/// project name.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BillingLine {
+    pub client: Option<String>,
+    /// `None` collects entries without a project.
+    pub project_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_name: Option<String>,
+    /// `None` for time without a rate, which has no amount either.
+    pub hourly_rate: Option<f64>,
+    pub currency: Option<String>,
+    pub billed_ms: i64,
+    pub entry_count: usize,
+    pub amount: Option<f64>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CurrencyTotal {
+    pub currency: String,
+    pub billed_ms: i64,
+    pub amount: f64,
+}
+
+/// An hourly rate and its currency.
+#[derive(Debug, Clone, PartialEq)]
+struct Rate {
+    hourly_rate: f64,
+    currency: String,
+}
+
+impl Rate {
+    fn of(hourly_rate: Option<f64>, currency: Option<&String>) -> Option<Self> {
+        Some(Rate {
+            hourly_rate: hourly_rate?,
+            currency: currency?.clone(),
+        })
+    }
+}
+
+/// Billable time and amounts for the period, per client and project
+pub async fn billing(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<BillingQuery>,
+) -> Result<Json<BillingReport>, ApiError> {
+    let (from, to) = (Some(query.from), Some(query.to));
+    let (entries, projects, default_rate) = match query.workspace {
+        Some(workspace) => {
+            let membership = workspaces::membership(&state, &auth, workspace)?;
+            let mut entries = Vec::new();
+            for member in workspaces::report_stores(&state, &auth, workspace, &membership)? {
+                entries.extend(state.db.overlapping_entries(member.store, from, to)?);
+            }
+            let default_rate = state
+                .db
+                .workspace(workspace, auth.user.id)?
+                .and_then(|workspace| Rate::of(workspace.hourly_rate, workspace.currency.as_ref()));
+            let projects = state.db.list_projects(membership.shared_store, true)?;
+            (entries, projects, default_rate)
+        }
+        None => {
+            let user = auth.user.id;
+            let entries = state.db.overlapping_entries(user, from, to)?;
+            (entries, state.db.list_projects(user, true)?, None)
+        }
+    };
+    Ok(Json(bill(
+        &entries,
+        &projects,
+        default_rate.as_ref(),
+        &query,
+    )))
+}
+
+/// Totals `entries` as `query` asks, with `default_rate` for billable time
+/// that has no rate of its own or from its project.
+fn bill(
+    entries: &[TimeEntry],
+    projects: &[Project],
+    default_rate: Option<&Rate>,
+    query: &BillingQuery,
+) -> BillingReport {
+    let projects: HashMap<&str, &Project> = projects
+        .iter()
+        .map(|project| (project.id.as_str(), project))
+        .collect();
+    let step = query.round_to_minutes.map(|minutes| minutes * MINUTE);
+
+    // (project, rate as bits and currency) -> (billed, entries, rate)
+    type Key<'a> = (Option<&'a str>, Option<(u64, String)>);
+    let mut lines: HashMap<Key, (i64, usize, Option<Rate>)> = HashMap::new();
+    let mut non_billable_ms = 0;
+    for entry in entries {
+        let Some(end) = entry.end_time else {
+            continue;
+        };
+        let ms = end.min(query.to) - entry.start_time.max(query.from);
+        if ms <= 0 {
+            continue;
+        }
+        let ms = step.map_or(ms, |step| query.rounding.apply(ms, step));
+
+        let project = entry
+            .project_id
+            .as_deref()
+            .and_then(|id| projects.get(id).copied());
+        let billable = entry
+            .billable
+            .unwrap_or_else(|| project.is_some_and(|project| project.billable));
+        if !billable {
+            non_billable_ms += ms;
+            continue;
+        }
+        let rate = Rate::of(entry.hourly_rate, entry.currency.as_ref())
+            .or_else(|| {
+                project.and_then(|project| Rate::of(project.hourly_rate, project.currency.as_ref()))
+            })
+            .or_else(|| default_rate.cloned());
+
+        let key = (
+            project.map(|project| project.id.as_str()),
+            rate.as_ref()
+                .map(|rate| (rate.hourly_rate.to_bits(), rate.currency.clone())),
+        );
+        let line = lines.entry(key).or_insert((0, 0, rate));
+        line.0 += ms;
+        line.1 += 1;
+    }
+
+    let mut lines: Vec<BillingLine> = lines
+        .into_iter()
+        .map(|((project_id, _), (billed_ms, entry_count, rate))| {
+            let project = project_id.and_then(|id| projects.get(id));
+            BillingLine {
+                client: project.and_then(|project| project.client.clone()),
+                project_id: project_id.map(str::to_string),
+                project_name: project.map(|project| project.name.clone()),
+                amount: rate
+                    .as_ref()
+                    .map(|rate| amount(billed_ms, rate.hourly_rate)),
+                hourly_rate: rate.as_ref().map(|rate| rate.hourly_rate),
+                currency: rate.map(|rate| rate.currency),
+                billed_ms,
+                entry_count,
+            }
+        })
+        .collect();
+    lines.sort_by(|a, b| {
+        (&a.client, &a.project_name, &a.currency)
+            .cmp(&(&b.client, &b.project_name, &b.currency))
+            .then_with(|| {
+                a.hourly_rate
+                    .partial_cmp(&b.hourly_rate)
+                    .unwrap_or(Ordering::Equal)
+            })
+    });
+
+    let mut totals: Vec<CurrencyTotal> = Vec::new();
+    let mut unrated_ms = 0;
+    for line in &lines {
+        let (Some(currency), Some(amount)) = (&line.currency, line.amount) else {
+            unrated_ms += line.billed_ms;
+            continue;
+        };
+        match totals.iter_mut().find(|total| &total.currency == currency) {
+            Some(total) => {
+                total.billed_ms += line.billed_ms;
+                total.amount = round_cents(total.amount + amount);
+            }
+            None => totals.push(CurrencyTotal {
+                currency: currency.clone(),
+                billed_ms: line.billed_ms,
+                amount,
+            }),
+        }
+    }
+    totals.sort_by(|a, b| a.currency.cmp(&b.currency));
+
+    BillingReport {
+        from: query.from,
+        to: query.to,
+        round_to_minutes: query.round_to_minutes,
+        rounding: query.rounding,
+        lines,
+        totals,
+        unrated_ms,
+        non_billable_ms,
+    }
+}
+
+fn amount(billed_ms: i64, hourly_rate: f64) -> f64 {
+    round_cents(billed_ms as f64 / HOUR * hourly_rate)
+}
+
+fn round_cents(amount: f64) -> f64 {
+    (amount * 100.0).round() / 100.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 60 * MINUTE;
+
+    fn entry(id: &str, start_time: i64, end_time: i64, project_id: Option<&str>) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            start_time,
+            end_time: Some(end_time),
+            project_id: project_id.map(str::to_string),
+            version: 1,
+            ..Default::default()
+        }
+    }
+
+    fn project(id: &str, client: &str, hourly_rate: Option<f64>) -> Project {
+        Project {
+            id: id.to_string(),
+            name: id.to_string(),
+            color: "#3b82f6".to_string(),
+            billable: true,
+            client: Some(client.to_string()),
+            hourly_rate,
+            currency: hourly_rate.map(|_| "EUR".to_string()),
+            version: 1,
+            ..Default::default()
+        }
+    }
+
+    fn query(round_to_minutes: Option<i64>, rounding: Rounding) -> BillingQuery {
+        BillingQuery {
+            from: 0,
+            to: 24 * HOUR_MS,
+            round_to_minutes,
+            rounding,
+            workspace: None,
+        }
+    }
+
+    #[test]
+    fn test_rounding_steps() {
+        let step = 15 * MINUTE;
+        assert_eq!(Rounding::Nearest.apply(7 * MINUTE, step), 0);
+        assert_eq!(Rounding::Nearest.apply(8 * MINUTE, step), step);
+        assert_eq!(Rounding::Up.apply(MINUTE, step), step);
+        assert_eq!(Rounding::Up.apply(step, step), step);
+        assert_eq!(Rounding::Down.apply(29 * MINUTE, step), step);
+    }
+
+    #[test]
+    fn test_rates_fall_back_from_entry_to_project_to_default() {
+        let projects = [
+            project("site", "Acme", Some(100.0)),
+            project("audit", "Globex", None),
+        ];
+        let mut own_rate = entry("e2", HOUR_MS, 2 * HOUR_MS, Some("site"));
+        own_rate.hourly_rate = Some(150.0);
+        own_rate.currency = Some("EUR".to_string());
+        let mut not_billable = entry("e4", 0, HOUR_MS, Some("site"));
+        not_billable.billable = Some(false);
+        let entries = [
+            entry("e1", 0, HOUR_MS, Some("site")),
+            own_rate,
+            entry("e3", 0, HOUR_MS / 2, Some("audit")),
+            not_billable,
+            entry("e5", 0, HOUR_MS, None),
+        ];
+        let default_rate = Rate {
+            hourly_rate: 80.0,
+            currency: "EUR".to_string(),
+        };
+
+        let report = bill(
+            &entries,
+            &projects,
+            Some(&default_rate),
+            &query(None, Rounding::Nearest),
+        );
+        let amounts: Vec<_> = report
+            .lines
+            .iter()
+            .map(|line| (line.project_id.as_deref(), line.amount))
+            .collect();
+        assert_eq!(
+            amounts,
+            [
+                (Some("site"), Some(100.0)),
+                (Some("site"), Some(150.0)),
+                (Some("audit"), Some(40.0)),
+            ]
+        );
+        assert_eq!(report.totals[0].amount, 290.0);
+        assert_eq!(report.non_billable_ms, 2 * HOUR_MS);
+        assert_eq!(report.unrated_ms, 0);
+    }
+
+    #[test]
+    fn test_entries_are_clipped_and_rounded_one_by_one() {
+        let projects = [project("site", "Acme", None)];
+        let entries = [
+            entry("e1", -HOUR_MS, 10 * MINUTE, Some("site")),
+            entry("e2", HOUR_MS, HOUR_MS + 10 * MINUTE, Some("site")),
+        ];
+
+        let report = bill(&entries, &projects, None, &query(Some(6), Rounding::Up));
+        assert_eq!(report.lines.len(), 1);
+        assert_eq!(report.lines[0].billed_ms, 24 * MINUTE);
+        assert_eq!(report.lines[0].amount, None);
+        assert_eq!(report.unrated_ms, 24 * MINUTE);
+        assert!(report.totals.is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+// backend/src/calendar.rs
+//! Subscribable calendar feed of a user's time entries.
+//!
+//! Calendar apps fetch feeds without custom headers, so instead of a bearer
+//! token the feed URL carries its own token. It only opens the feed and can
+//! be rotated or revoked without logging anyone out.
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{hash_token, random_hex, AuthUser};
+use crate::db::EntryQuery;
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::export::{entries_body, ExportFormat, PAGE_SIZE};
+use crate::model::Validate;
+use crate::periods::Calendar;
+use crate::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct CalendarFeed {
+    pub token: String,
+    /// Path of the feed, token included, to append to the server's origin.
+    pub path: String,
+    pub created_at: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    pub token: String,
+}
+
+impl Validate for FeedQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.token.is_empty() {
+            errors.push(FieldError::new(
+                join_path(path, "token"),
+                "must not be empty",
+            ));
+        }
+    }
+}
+
+/// Turn on the calendar feed, or give it a new URL
+///
+/// Any earlier feed URL stops working.
+pub async fn create_feed(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<(StatusCode, Json<CalendarFeed>), ApiError> {
+    let token = random_hex(32);
+    let created_at = state
+        .db
+        .set_calendar_token(auth.user.id, &hash_token(&token))?;
+    Ok((
+        StatusCode::CREATED,
+        Json(CalendarFeed {
+            path: format!("/calendar/feed.ics?token={token}"),
+            token,
+            created_at,
+        }),
+    ))
+}
+
+/// Turn off the calendar feed
+pub async fn delete_feed(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    if state.db.revoke_calendar_token(auth.user.id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound("calendar feed"))
+    }
+}
+
+/// The feed's entries from the configured number of days back, as iCalendar
+pub async fn feed(
+    State(state): State<AppState>,
+    ValidQuery(query): ValidQuery<FeedQuery>,
+) -> Result<Response, ApiError> {
+    let user = state
+        .db
+        .user_for_calendar_token(&hash_token(&query.token))?
+        .ok_or(ApiError::NotFound("calendar feed"))?;
+
+    let window = chrono::Duration::days(state.config.calendar.feed_days);
+    let entries = EntryQuery {
+        from: Some((chrono::Utc::now() - window).timestamp_millis()),
+        to: None,
+        project_id: None,
+        category_id: None,
+        after: None,
+        limit: PAGE_SIZE,
+        offset: 0,
+    };
+    // Events carry UTC times, so the calendar apps place them in their own zone.
+    let body = entries_body(
+        &state.db,
+        user,
+        Calendar::default(),
+        ExportFormat::Ics,
+        entries,
+    )?;
+    Ok((
+        [(header::CONTENT_TYPE, "text/calendar; charset=utf-8")],
+        body,
+    )
+        .into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimeEntry;
+
+    async fn fetch(state: &AppState, token: &str) -> Result<String, ApiError> {
+        let query = FeedQuery {
+            token: token.to_string(),
+        };
+        let response = feed(State(state.clone()), ValidQuery(query)).await?;
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        Ok(String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_feed_covers_the_rolling_window() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let now = chrono::Utc::now().timestamp_millis();
+        let day = 24 * 60 * 60 * 1000;
+        for (id, start_time) in [("recent", now - day), ("old", now - 100 * day)] {
+            let entry = TimeEntry {
+                id: id.to_string(),
+                description: id.to_string(),
+                start_time,
+                end_time: Some(start_time + 60_000),
+                ..Default::default()
+            };
+            state.db.create_entry(auth.user.id, entry).unwrap().unwrap();
+        }
+
+        let (status, created) = create_feed(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(status, StatusCode::CREATED);
+        let calendar = fetch(&state, &created.token).await.unwrap();
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\n"));
+        assert!(calendar.contains("SUMMARY:recent\r\n"));
+        assert!(!calendar.contains("SUMMARY:old\r\n"));
+    }
+
+    #[tokio::test]
+    async fn test_rotated_and_revoked_tokens_stop_working() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let (_, first) = create_feed(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        let (_, second) = create_feed(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+
+        let stale = fetch(&state, &first.token).await;
+        assert!(matches!(stale, Err(ApiError::NotFound(_))));
+        assert!(fetch(&state, &second.token).await.is_ok());
+
+        delete_feed(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        let revoked = fetch(&state, &second.token).await;
+        assert!(matches!(revoked, Err(ApiError::NotFound(_))));
+        let again = delete_feed(State(state), auth).await;
+        assert!(matches!(again, Err(ApiError::NotFound(_))));
+    }
+}
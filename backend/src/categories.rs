@@ -0,0 +1,214 @@
+// backend/src/categories.rs
+//! REST endpoints for categories and their weekly targets.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::db::{CategoryEntries, Rejection};
+use crate::error::{join_path, ApiError, FieldError, ValidJson, ValidQuery};
+use crate::model::{nullable, Category, Validate};
+use crate::AppState;
+
+/// What to do with the entries in a category being deleted. Without either
+/// option the delete is refused while the category has entries.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteCategory {
+    /// Category to move the entries to.
+    pub reassign_to: Option<String>,
+    /// Delete the entries too.
+    pub cascade: bool,
+}
+
+impl Validate for DeleteCategory {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.cascade && self.reassign_to.is_some() {
+            errors.push(FieldError::new(
+                join_path(path, "cascade"),
+                "cannot be combined with reassign_to",
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedCategory {
+    /// How many entries were moved, or deleted with `cascade`.
+    pub affected_entries: usize,
+}
+
+/// Fields to change on a category; absent fields are kept and a null
+/// `weeklyTargetHours` removes the target. If `version` is given the patch
+/// only applies to that version.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct CategoryPatch {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    #[serde(deserialize_with = "nullable")]
+    pub weekly_target_hours: Option<Option<f64>>,
+    pub version: Option<i64>,
+}
+
+impl Validate for CategoryPatch {
+    // Checked once applied, against the whole category.
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+impl CategoryPatch {
+    fn apply(self, category: &mut Category) {
+        if let Some(name) = self.name {
+            category.name = name;
+        }
+        if let Some(color) = self.color {
+            category.color = color;
+        }
+        if let Some(hours) = self.weekly_target_hours {
+            category.weekly_target_hours = hours;
+        }
+    }
+}
+
+/// List the user's categories by name
+pub async fn list_categories(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<Category>>, ApiError> {
+    Ok(Json(state.db.list_categories(auth.user.id)?))
+}
+
+/// Get a category by id
+pub async fn get_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Category>, ApiError> {
+    let category = state
+        .db
+        .find_category(auth.user.id, &id)?
+        .ok_or(ApiError::NotFound("category"))?;
+    Ok(Json(category))
+}
+
+/// Create a category
+pub async fn create_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(category): ValidJson<Category>,
+) -> Result<(StatusCode, Json<Category>), ApiError> {
+    let category = auth
+        .db(&state)
+        .create_category(auth.user.id, category)?
+        .map_err(|rejection| ApiError::rejected("category", rejection))?;
+    Ok((StatusCode::CREATED, Json(category)))
+}
+
+/// Change the name, color or weekly target of a category
+pub async fn patch_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidJson(patch): ValidJson<CategoryPatch>,
+) -> Result<Json<Category>, ApiError> {
+    let mut category = state
+        .db
+        .find_category(auth.user.id, &id)?
+        .ok_or(ApiError::NotFound("category"))?;
+    if patch
+        .version
+        .is_some_and(|version| version != category.version)
+    {
+        return Err(ApiError::rejected(
+            "category",
+            Rejection::StaleVersion(category.version),
+        ));
+    }
+    patch.apply(&mut category);
+
+    let mut errors = Vec::new();
+    category.validate("", &mut errors);
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let category = auth
+        .db(&state)
+        .update_category(auth.user.id, category)?
+        .map_err(|rejection| ApiError::rejected("category", rejection))?;
+    Ok(Json(category))
+}
+
+/// Delete a category, moving its entries to `reassign_to` or deleting them
+/// with `cascade`
+pub async fn delete_category(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidQuery(query): ValidQuery<DeleteCategory>,
+) -> Result<Json<DeletedCategory>, ApiError> {
+    let entries = match (&query.reassign_to, query.cascade) {
+        (Some(target), _) => CategoryEntries::ReassignTo(target),
+        (None, true) => CategoryEntries::Delete,
+        (None, false) => CategoryEntries::Refuse,
+    };
+    let affected_entries = auth
+        .db(&state)
+        .delete_category(auth.user.id, &id, entries)?
+        .map_err(|rejection| ApiError::rejected("category", rejection))?;
+    Ok(Json(DeletedCategory { affected_entries }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_patch_clears_weekly_target() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let category = Category {
+            id: "c1".to_string(),
+            name: "Work".to_string(),
+            color: "#10b981".to_string(),
+            weekly_target_hours: Some(40.0),
+            ..Default::default()
+        };
+        let _ = create_category(State(state.clone()), auth.clone(), ValidJson(category))
+            .await
+            .unwrap();
+
+        let patch: CategoryPatch = serde_json::from_str(r#"{"weeklyTargetHours": null}"#).unwrap();
+        let patched = patch_category(
+            State(state.clone()),
+            auth.clone(),
+            Path("c1".into()),
+            ValidJson(patch),
+        )
+        .await
+        .unwrap();
+        assert_eq!(patched.weekly_target_hours, None);
+
+        let too_much = CategoryPatch {
+            weekly_target_hours: Some(Some(200.0)),
+            ..CategoryPatch::default()
+        };
+        let result =
+            patch_category(State(state), auth, Path("c1".into()), ValidJson(too_much)).await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+    }
+
+    #[test]
+    fn test_cascade_and_reassign_are_exclusive() {
+        let query = DeleteCategory {
+            reassign_to: Some("c2".to_string()),
+            cascade: true,
+        };
+        let mut errors = Vec::new();
+        query.validate("", &mut errors);
+        assert_eq!(errors[0].field, "cascade");
+    }
+}
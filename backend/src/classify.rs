@@ -0,0 +1,436 @@
+// backend/src/classify.rs
+//! Classification of entries into work types (meeting, coding, ...) from
+//! their descriptions, the way `getWorkTypeFromDescription` in the frontend's
+//! analytics does it.
+//!
+//! A description is matched against each work type in order:
+//!
+//! 1. a keyword it contains, or a regular expression it matches, picks the
+//!    first such type;
+//! 2. otherwise each word of three or more letters is compared to the
+//!    keywords of types that allow fuzzy matching, and the closest keyword
+//!    within the rule set's threshold picks its type;
+//! 3. otherwise the entry is `other`, or `unspecified` without a description.
+//!
+//! Matching ignores case.
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{join_path, FieldError};
+use crate::model::{require_color, require_id, require_name, Validate};
+
+/// Work type of entries without a description.
+pub const UNSPECIFIED: &str = "unspecified";
+
+/// Work type of entries no rule matches.
+pub const OTHER: &str = "other";
+
+/// Longest regular expression a rule may use, in bytes.
+const MAX_PATTERN_LENGTH: usize = 1000;
+
+/// A user's work types, tried in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RuleSet {
+    pub work_types: Vec<WorkType>,
+    /// How far a word may be from a keyword and still match it: the edit
+    /// distance divided by the longer length, from 0 (exact) to 1 (anything).
+    #[serde(default = "default_fuzzy_threshold")]
+    pub fuzzy_threshold: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorkType {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Regular expressions, matched anywhere in the description.
+    #[serde(default)]
+    pub patterns: Vec<String>,
+    /// Whether words close to a keyword count as a match.
+    #[serde(default = "default_fuzzy")]
+    pub fuzzy: bool,
+}
+
+fn default_fuzzy_threshold() -> f64 {
+    0.4
+}
+
+fn default_fuzzy() -> bool {
+    true
+}
+
+impl Validate for RuleSet {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if !(0.0..=1.0).contains(&self.fuzzy_threshold) {
+            errors.push(FieldError::new(
+                join_path(path, "fuzzy_threshold"),
+                "must be between 0 and 1",
+            ));
+        }
+        for (index, work_type) in self.work_types.iter().enumerate() {
+            let type_path = join_path(path, &format!("work_types[{index}]"));
+            work_type.validate(&type_path, errors);
+            let reserved = [UNSPECIFIED, OTHER].contains(&work_type.id.as_str());
+            let duplicate = self.work_types[..index]
+                .iter()
+                .any(|earlier| earlier.id == work_type.id);
+            if reserved || duplicate {
+                errors.push(FieldError::new(
+                    join_path(&type_path, "id"),
+                    if reserved {
+                        "is reserved"
+                    } else {
+                        "is used by an earlier work type"
+                    },
+                ));
+            }
+        }
+    }
+}
+
+impl Validate for WorkType {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        require_id(&self.id, path, errors);
+        require_name(&self.name, path, errors);
+        require_color(&self.color, path, errors);
+        for (index, keyword) in self.keywords.iter().enumerate() {
+            if keyword.trim().is_empty() {
+                errors.push(FieldError::new(
+                    join_path(path, &format!("keywords[{index}]")),
+                    "must not be empty",
+                ));
+            }
+        }
+        for (index, pattern) in self.patterns.iter().enumerate() {
+            if let Err(err) = compile(pattern) {
+                errors.push(FieldError::new(
+                    join_path(path, &format!("patterns[{index}]")),
+                    format!("is not a valid regular expression: {err}"),
+                ));
+            }
+        }
+    }
+}
+
+impl Default for RuleSet {
+    /// The frontend's `WORK_TYPE_DEFINITIONS`.
+    fn default() -> Self {
+        let work_type = |id: &str, name: &str, color: &str, keywords: &[&str]| WorkType {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: color.to_string(),
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            patterns: Vec::new(),
+            fuzzy: true,
+        };
+        Self {
+            work_types: vec![
+                work_type(
+                    "meeting",
+                    "Meeting",
+                    "#4E79A7",
+                    &[
+                        "meet",
+                        "call",
+                        "zoom",
+                        "conference",
+                        "discussion",
+                        "sync",
+                        "standup",
+                        "1on1",
+                        "chat",
+                        "huddle",
+                        "interview",
+                    ],
+                ),
+                work_type(
+                    "coding",
+                    "Coding",
+                    "#F28E2B",
+                    &[
+                        "code",
+                        "programming",
+                        "dev",
+                        "develop",
+                        "implementation",
+                        "debug",
+                        "fix",
+                        "bug",
+                        "feature",
+                        "refactor",
+                        "test",
+                    ],
+                ),
+                work_type(
+                    "content",
+                    "Content",
+                    "#E15759",
+                    &[
+                        "write",
+                        "blog",
+                        "article",
+                        "copy",
+                        "content",
+                        "documentation",
+                        "doc",
+                        "post",
+                        "draft",
+                        "edit",
+                        "proofread",
+                    ],
+                ),
+                work_type(
+                    "communication",
+                    "Communication",
+                    "#76B7B2",
+                    &[
+                        "email",
+                        "correspondence",
+                        "message",
+                        "reply",
+                        "slack",
+                        "dm",
+                        "chat",
+                        "contact",
+                        "respond",
+                    ],
+                ),
+                work_type(
+                    "design",
+                    "Design",
+                    "#59A14F",
+                    &[
+                        "design",
+                        "ui",
+                        "ux",
+                        "interface",
+                        "prototype",
+                        "mockup",
+                        "wireframe",
+                        "sketch",
+                        "figma",
+                    ],
+                ),
+                work_type(
+                    "research",
+                    "Research",
+                    "#EDC948",
+                    &[
+                        "research",
+                        "study",
+                        "learn",
+                        "explore",
+                        "investigate",
+                        "review",
+                        "analyze",
+                        "evaluate",
+                        "read",
+                    ],
+                ),
+                work_type(
+                    "planning",
+                    "Planning",
+                    "#B07AA1",
+                    &[
+                        "plan",
+                        "strategy",
+                        "roadmap",
+                        "backlog",
+                        "prioritize",
+                        "organize",
+                        "schedule",
+                        "outline",
+                    ],
+                ),
+                work_type(
+                    "admin",
+                    "Administration",
+                    "#FF9DA7",
+                    &[
+                        "admin",
+                        "manage",
+                        "organize",
+                        "coordinate",
+                        "setup",
+                        "configure",
+                        "maintenance",
+                    ],
+                ),
+            ],
+            fuzzy_threshold: default_fuzzy_threshold(),
+        }
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex, String> {
+    if pattern.len() > MAX_PATTERN_LENGTH {
+        return Err(format!("longer than {MAX_PATTERN_LENGTH} bytes"));
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|err| err.to_string())
+}
+
+/// A rule set ready to classify descriptions.
+pub struct Classifier {
+    work_types: Vec<CompiledType>,
+    fuzzy_threshold: f64,
+}
+
+struct CompiledType {
+    id: String,
+    keywords: Vec<String>,
+    patterns: Vec<Regex>,
+    fuzzy: bool,
+}
+
+impl Classifier {
+    /// Compiles a validated rule set. Patterns that do not compile are
+    /// skipped.
+    pub fn new(rules: &RuleSet) -> Self {
+        Self {
+            work_types: rules
+                .work_types
+                .iter()
+                .map(|work_type| CompiledType {
+                    id: work_type.id.clone(),
+                    keywords: work_type
+                        .keywords
+                        .iter()
+                        .map(|keyword| keyword.trim().to_lowercase())
+                        .collect(),
+                    patterns: work_type
+                        .patterns
+                        .iter()
+                        .filter_map(|pattern| compile(pattern).ok())
+                        .collect(),
+                    fuzzy: work_type.fuzzy,
+                })
+                .collect(),
+            fuzzy_threshold: rules.fuzzy_threshold,
+        }
+    }
+
+    /// The id of the work type `description` belongs to.
+    pub fn classify(&self, description: &str) -> String {
+        let description = description.trim().to_lowercase();
+        if description.is_empty() {
+            return UNSPECIFIED.to_string();
+        }
+
+        let exact = self.work_types.iter().find(|work_type| {
+            work_type
+                .keywords
+                .iter()
+                .any(|keyword| description.contains(keyword.as_str()))
+                || work_type
+                    .patterns
+                    .iter()
+                    .any(|pattern| pattern.is_match(&description))
+        });
+        if let Some(work_type) = exact {
+            return work_type.id.clone();
+        }
+
+        let mut best: Option<(f64, &str)> = None;
+        let words = description
+            .split_whitespace()
+            .filter(|word| word.chars().count() > 2);
+        for word in words {
+            for work_type in self.work_types.iter().filter(|work_type| work_type.fuzzy) {
+                for keyword in &work_type.keywords {
+                    let score = distance(word, keyword);
+                    if score <= self.fuzzy_threshold && best.is_none_or(|(best, _)| score < best) {
+                        best = Some((score, &work_type.id));
+                    }
+                }
+            }
+        }
+        best.map_or(OTHER, |(_, id)| id).to_string()
+    }
+}
+
+/// Levenshtein distance between `a` and `b` divided by the longer length.
+fn distance(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 0.0;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a_char) in a.iter().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()] as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_defaults_match_the_frontend() {
+        let classifier = Classifier::new(&RuleSet::default());
+        assert_eq!(classifier.classify("Weekly standup"), "meeting");
+        assert_eq!(classifier.classify("Fix login BUG"), "coding");
+        assert_eq!(classifier.classify("  "), UNSPECIFIED);
+        // No keyword is contained, but "wirefame" is close to "wireframe".
+        assert_eq!(classifier.classify("Wirefame onboarding"), "design");
+        assert_eq!(classifier.classify("Lunch"), OTHER);
+    }
+
+    #[test]
+    fn test_patterns_and_order() {
+        let mut rules = RuleSet::default();
+        rules.work_types.insert(
+            0,
+            WorkType {
+                id: "support".to_string(),
+                name: "Support".to_string(),
+                color: "#000000".to_string(),
+                keywords: Vec::new(),
+                patterns: vec![r"^(ticket|case) #?\d+".to_string()],
+                fuzzy: false,
+            },
+        );
+        let classifier = Classifier::new(&rules);
+        assert_eq!(classifier.classify("Ticket #4521: fix export"), "support");
+        assert_eq!(classifier.classify("Fix export"), "coding");
+    }
+
+    #[test]
+    fn test_invalid_rules_are_reported() {
+        let mut rules = RuleSet::default();
+        rules.work_types[1].id = "meeting".to_string();
+        rules.work_types[2].patterns = vec!["(unclosed".to_string()];
+        rules.work_types[3].id = OTHER.to_string();
+
+        let mut errors = Vec::new();
+        rules.validate("", &mut errors);
+        let fields: Vec<_> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            [
+                "work_types[1].id",
+                "work_types[2].patterns[0]",
+                "work_types[3].id"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("design", "design"), 0.0);
+        assert_eq!(distance("desgin", "design"), 2.0 / 6.0);
+        assert_eq!(distance("", "abc"), 1.0);
+    }
+}
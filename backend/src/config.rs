@@ -0,0 +1,321 @@
+// backend/src/config.rs
+//! Server configuration.
+//!
+//! Settings are layered, each source overriding the previous one: built-in
+//! defaults, a TOML file, `TIME_TRACKER_*` environment variables, then
+//! command-line flags. See `time_tracker.example.toml` for the file format.
+use clap::Parser;
+use serde::Deserialize;
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use tracing_subscriber::EnvFilter;
+
+/// Config file read from the working directory when `--config` is not given.
+const DEFAULT_CONFIG_PATH: &str = "time_tracker.toml";
+
+/// Origin of the Vite dev server, the only client allowed by default.
+const DEFAULT_CORS_ORIGIN: &str = "http://localhost:5173";
+
+/// Command-line flags; each one can also be set through the environment.
+#[derive(Debug, Parser)]
+#[command(version, about = "Time tracker sync server")]
+pub struct Cli {
+    /// TOML config file [default: time_tracker.toml, if it exists]
+    #[arg(long, env = "TIME_TRACKER_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[arg(long, env = "TIME_TRACKER_BIND_ADDRESS")]
+    pub bind_address: Option<IpAddr>,
+    #[arg(long, env = "TIME_TRACKER_PORT")]
+    pub port: Option<u16>,
+    #[arg(long, env = "TIME_TRACKER_DATABASE_PATH")]
+    pub database_path: Option<PathBuf>,
+    /// Comma-separated origins allowed to call the API, or `*` for any
+    #[arg(long, env = "TIME_TRACKER_CORS_ALLOWED_ORIGINS", value_delimiter = ',')]
+    pub cors_allowed_origins: Option<Vec<String>>,
+    /// Log filter, e.g. `info` or `backend=debug,tower_http=info`
+    #[arg(long, env = "TIME_TRACKER_LOG_LEVEL")]
+    pub log_level: Option<String>,
+    #[arg(long, env = "TIME_TRACKER_TOMBSTONE_RETENTION_DAYS")]
+    pub tombstone_retention_days: Option<i64>,
+    #[arg(long, env = "TIME_TRACKER_TRASH_RETENTION_DAYS")]
+    pub trash_retention_days: Option<i64>,
+    #[arg(long, env = "TIME_TRACKER_TOKEN_TTL_DAYS")]
+    pub token_ttl_days: Option<i64>,
+    #[arg(long, env = "TIME_TRACKER_CALENDAR_FEED_DAYS")]
+    pub calendar_feed_days: Option<i64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    pub database_path: PathBuf,
+    pub cors_allowed_origins: Vec<String>,
+    pub log_level: String,
+    pub retention: RetentionConfig,
+    pub auth: AuthConfig,
+    pub calendar: CalendarConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetentionConfig {
+    /// How long deletions are remembered for devices that have not synced.
+    /// A device that stays offline for longer has to resync from scratch.
+    pub tombstone_days: i64,
+    /// How long deleted records can be restored before they are purged.
+    pub trash_days: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// How long a login token stays valid.
+    pub token_ttl_days: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CalendarConfig {
+    /// How many days back the subscribable calendar feed reaches.
+    pub feed_days: i64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_address: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3000,
+            database_path: PathBuf::from("time_tracker.db"),
+            cors_allowed_origins: vec![DEFAULT_CORS_ORIGIN.to_string()],
+            log_level: "info".to_string(),
+            retention: RetentionConfig::default(),
+            auth: AuthConfig::default(),
+            calendar: CalendarConfig::default(),
+        }
+    }
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self {
+            tombstone_days: 90,
+            trash_days: 30,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self { token_ttl_days: 30 }
+    }
+}
+
+impl Default for CalendarConfig {
+    fn default() -> Self {
+        Self { feed_days: 90 }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Read(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Read(path, err) => write!(f, "cannot read {}: {}", path.display(), err),
+            ConfigError::Parse(path, err) => write!(f, "cannot parse {}: {}", path.display(), err),
+            ConfigError::Invalid(problems) => {
+                write!(f, "invalid configuration: {}", problems.join("; "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Builds the effective configuration from every source and validates it.
+    pub fn load(cli: Cli) -> Result<Self, ConfigError> {
+        let mut config = match &cli.config {
+            Some(path) => Self::from_file(path)?,
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Self::from_file(Path::new(DEFAULT_CONFIG_PATH))?
+            }
+            None => Self::default(),
+        };
+        config.apply(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|err| ConfigError::Read(path.into(), err))?;
+        toml::from_str(&contents).map_err(|err| ConfigError::Parse(path.into(), err))
+    }
+
+    fn apply(&mut self, cli: Cli) {
+        if let Some(bind_address) = cli.bind_address {
+            self.bind_address = bind_address;
+        }
+        if let Some(port) = cli.port {
+            self.port = port;
+        }
+        if let Some(database_path) = cli.database_path {
+            self.database_path = database_path;
+        }
+        if let Some(origins) = cli.cors_allowed_origins {
+            self.cors_allowed_origins = origins;
+        }
+        if let Some(log_level) = cli.log_level {
+            self.log_level = log_level;
+        }
+        if let Some(days) = cli.tombstone_retention_days {
+            self.retention.tombstone_days = days;
+        }
+        if let Some(days) = cli.trash_retention_days {
+            self.retention.trash_days = days;
+        }
+        if let Some(days) = cli.token_ttl_days {
+            self.auth.token_ttl_days = days;
+        }
+        if let Some(days) = cli.calendar_feed_days {
+            self.calendar.feed_days = days;
+        }
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        if self.port == 0 {
+            problems.push("port must not be 0".to_string());
+        }
+        if self.database_path.as_os_str().is_empty() {
+            problems.push("database_path must not be empty".to_string());
+        }
+        if self.cors_allowed_origins.is_empty() {
+            problems.push("cors_allowed_origins must list at least one origin".to_string());
+        }
+        for origin in &self.cors_allowed_origins {
+            let valid = origin == "*"
+                || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                    && !origin.ends_with('/')
+                    && origin.parse::<axum::http::HeaderValue>().is_ok());
+            if !valid {
+                problems.push(format!(
+                    "cors origin `{origin}` must be `*` or a scheme and host like {DEFAULT_CORS_ORIGIN}"
+                ));
+            }
+        }
+        if let Err(err) = EnvFilter::try_new(&self.log_level) {
+            problems.push(format!(
+                "log_level `{}` is invalid: {}",
+                self.log_level, err
+            ));
+        }
+        if self.retention.tombstone_days < 1 {
+            problems.push("retention.tombstone_days must be at least 1".to_string());
+        }
+        if self.retention.trash_days < 1 {
+            problems.push("retention.trash_days must be at least 1".to_string());
+        }
+        if self.auth.token_ttl_days < 1 {
+            problems.push("auth.token_ttl_days must be at least 1".to_string());
+        }
+        if self.calendar.feed_days < 1 {
+            problems.push("calendar.feed_days must be at least 1".to_string());
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+
+    /// Whether any origin may call the API.
+    pub fn allows_any_origin(&self) -> bool {
+        self.cors_allowed_origins.iter().any(|origin| origin == "*")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_values_override_defaults() {
+        let config: Config = toml::from_str(
+            r#"
+            port = 8080
+            cors_allowed_origins = ["https://tracker.example.com"]
+
+            [retention]
+            tombstone_days = 30
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.port, 8080);
+        assert_eq!(config.retention.tombstone_days, 30);
+        assert_eq!(config.database_path, Config::default().database_path);
+    }
+
+    #[test]
+    fn test_example_file_matches_defaults() {
+        let config: Config = toml::from_str(include_str!("../time_tracker.example.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_unknown_keys_are_rejected() {
+        let result: Result<Config, _> = toml::from_str("prot = 8080");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_flags_override_file() {
+        let mut config = Config {
+            port: 8080,
+            ..Config::default()
+        };
+        let cli = Cli::try_parse_from([
+            "backend",
+            "--port",
+            "9000",
+            "--cors-allowed-origins",
+            "https://a.example.com,https://b.example.com",
+        ])
+        .unwrap();
+
+        config.apply(cli);
+        assert_eq!(config.port, 9000);
+        assert_eq!(config.cors_allowed_origins.len(), 2);
+    }
+
+    #[test]
+    fn test_validate_reports_every_problem() {
+        let config = Config {
+            port: 0,
+            cors_allowed_origins: vec!["localhost:5173".to_string()],
+            log_level: "backend=loud".to_string(),
+            ..Config::default()
+        };
+
+        match config.validate() {
+            Err(ConfigError::Invalid(problems)) => assert_eq!(problems.len(), 3),
+            other => panic!("expected invalid config, got {other:?}"),
+        }
+        assert!(Config::default().validate().is_ok());
+    }
+}
@@ -0,0 +1,294 @@
+// backend/src/db/audit.rs
+//! Append-only log of every change to entries, projects and categories.
+//!
+//! Changes are recorded by the write helpers in `sync`, so nothing that
+//! stores or removes a record can skip the log. Who made them is set per
+//! transaction by [`begin_change`](super::sync::begin_change) in a temporary
+//! table, since the helpers only see the connection.
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::sync::{to_json, Table};
+use super::trash::RecordKind;
+use super::{Actor, Db, DeviceId, UserId};
+use crate::merge;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+}
+
+impl AuditAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            AuditAction::Create => "create",
+            AuditAction::Update => "update",
+            AuditAction::Delete => "delete",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "create" => Some(AuditAction::Create),
+            "update" => Some(AuditAction::Update),
+            "delete" => Some(AuditAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded change.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEvent {
+    pub id: i64,
+    pub changed_at: i64,
+    /// The account that made the change; `None` if the server made it, e.g.
+    /// a scheduled retention run.
+    pub actor_id: Option<UserId>,
+    pub device_id: Option<DeviceId>,
+    pub kind: RecordKind,
+    pub record_id: String,
+    pub action: AuditAction,
+    /// Version of the record after the change, or the last one it had if it
+    /// was deleted.
+    pub version: i64,
+    /// Each changed field with its value `before` and `after`; `null` where
+    /// the field was unset.
+    pub changes: Value,
+}
+
+impl AuditEvent {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let kind: String = row.get(4)?;
+        let action: String = row.get(6)?;
+        let changes: String = row.get(8)?;
+        Ok(Self {
+            id: row.get(0)?,
+            changed_at: row.get(1)?,
+            actor_id: row.get(2)?,
+            device_id: row.get(3)?,
+            kind: RecordKind::parse(&kind).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(4, Type::Text, kind.into())
+            })?,
+            record_id: row.get(5)?,
+            action: AuditAction::parse(&action).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(6, Type::Text, action.into())
+            })?,
+            version: row.get(7)?,
+            changes: serde_json::from_str(&changes).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(8, Type::Text, err.into())
+            })?,
+        })
+    }
+}
+
+/// Filters for reading the log; `from` and `to` bound `changed_at` to
+/// `[from, to)`.
+#[derive(Debug, Default, Clone)]
+pub struct AuditQuery {
+    pub kind: Option<RecordKind>,
+    pub record_id: Option<String>,
+    pub device_id: Option<DeviceId>,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Db {
+    /// Changes to `user`'s records matching `query`, newest first.
+    pub fn list_audit_events(
+        &self,
+        user: UserId,
+        query: &AuditQuery,
+    ) -> rusqlite::Result<Vec<AuditEvent>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, changed_at, actor_id, device_id, kind, record_id, action, version, changes
+             FROM audit_log
+             WHERE user_id = ?1
+               AND (?2 IS NULL OR kind = ?2)
+               AND (?3 IS NULL OR record_id = ?3)
+               AND (?4 IS NULL OR device_id = ?4)
+               AND (?5 IS NULL OR changed_at >= ?5)
+               AND (?6 IS NULL OR changed_at < ?6)
+             ORDER BY changed_at DESC, id DESC LIMIT ?7 OFFSET ?8",
+        )?;
+        let events = stmt
+            .query_map(
+                params![
+                    user,
+                    query.kind.map(RecordKind::as_str),
+                    query.record_id,
+                    query.device_id,
+                    query.from,
+                    query.to,
+                    query.limit,
+                    query.offset
+                ],
+                AuditEvent::from_row,
+            )?
+            .collect();
+        events
+    }
+}
+
+/// Creates the per-connection table holding who the current transaction's
+/// changes belong to.
+pub(super) fn init_context(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute_batch(
+        "CREATE TEMP TABLE audit_context (actor_id INTEGER, device_id INTEGER);
+         INSERT INTO audit_context VALUES (NULL, NULL);",
+    )
+}
+
+pub(super) fn set_context(conn: &Connection, actor: Option<Actor>) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE temp.audit_context SET actor_id = ?1, device_id = ?2",
+        params![
+            actor.map(|actor| actor.user),
+            actor.and_then(|actor| actor.device)
+        ],
+    )?;
+    Ok(())
+}
+
+/// Logs a change from `before` to `after`, either of which is `None` when
+/// the record did not exist. Updates that change no content are skipped.
+pub(super) fn record_change<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    before: Option<&T>,
+    after: Option<&T>,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let (action, record) = match (before, after) {
+        (None, Some(after)) => (AuditAction::Create, after),
+        (Some(_), Some(after)) => (AuditAction::Update, after),
+        (Some(before), None) => (AuditAction::Delete, before),
+        (None, None) => return Ok(()),
+    };
+    let changes = diff(
+        before.map(to_json).transpose()?.as_ref(),
+        after.map(to_json).transpose()?.as_ref(),
+    );
+    if action == AuditAction::Update && changes.is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT INTO audit_log
+             (user_id, actor_id, device_id, kind, record_id, action, version, changes, changed_at)
+         SELECT ?1, actor_id, device_id, ?2, ?3, ?4, ?5, ?6, ?7 FROM temp.audit_context",
+        params![
+            user,
+            T::NAME,
+            record.id(),
+            action.as_str(),
+            record.version(),
+            Value::Object(changes).to_string(),
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+fn diff(before: Option<&Value>, after: Option<&Value>) -> Map<String, Value> {
+    let before = before.map(merge::content).unwrap_or_default();
+    let after = after.map(merge::content).unwrap_or_default();
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    let mut changes = Map::new();
+    for field in fields {
+        let old = before.get(field).unwrap_or(&Value::Null);
+        let new = after.get(field).unwrap_or(&Value::Null);
+        if old != new {
+            changes.insert(
+                field.clone(),
+                serde_json::json!({ "before": old, "after": new }),
+            );
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimeEntry;
+
+    const USER: UserId = 1;
+
+    fn entry() -> TimeEntry {
+        TimeEntry {
+            id: "e1".to_string(),
+            description: "Review".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_changes_are_logged_with_their_actor() {
+        let db = Db::open_in_memory().unwrap();
+        let laptop = db.acting_as(Actor {
+            user: USER,
+            device: Some(7),
+        });
+        let created = laptop.create_entry(USER, entry()).unwrap().unwrap();
+        let renamed = TimeEntry {
+            description: "Code review".to_string(),
+            ..created
+        };
+        db.update_entry(USER, renamed).unwrap().unwrap();
+        laptop.delete_entry(USER, "e1").unwrap();
+
+        let events = db
+            .list_audit_events(
+                USER,
+                &AuditQuery {
+                    limit: 10,
+                    ..AuditQuery::default()
+                },
+            )
+            .unwrap();
+        let actions: Vec<_> = events.iter().map(|event| event.action).collect();
+        assert_eq!(
+            actions,
+            [
+                AuditAction::Delete,
+                AuditAction::Update,
+                AuditAction::Create
+            ]
+        );
+        assert_eq!(events[0].device_id, Some(7));
+        // The update went through a handle without an actor.
+        assert_eq!(events[1].actor_id, None);
+        assert_eq!(
+            events[1].changes,
+            serde_json::json!({
+                "description": { "before": "Review", "after": "Code review" }
+            })
+        );
+        assert_eq!(events[2].changes["endTime"]["before"], Value::Null);
+        assert_eq!(events[2].changes["endTime"]["after"], 2_000);
+    }
+
+    #[test]
+    fn test_log_is_append_only() {
+        let db = Db::open_in_memory().unwrap();
+        db.create_entry(USER, entry()).unwrap().unwrap();
+        let conn = db.conn();
+        assert!(conn.execute("DELETE FROM audit_log", []).is_err());
+        assert!(conn
+            .execute("UPDATE audit_log SET actor_id = 2", [])
+            .is_err());
+    }
+}
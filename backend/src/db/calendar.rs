@@ -0,0 +1,59 @@
+// backend/src/db/calendar.rs
+use rusqlite::{params, OptionalExtension};
+
+use super::{Db, UserId};
+
+impl Db {
+    /// Points the user's calendar feed at a new token, replacing any earlier
+    /// one. Returns when the feed was created.
+    pub fn set_calendar_token(&self, user: UserId, token_hash: &str) -> rusqlite::Result<i64> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn().execute(
+            "INSERT INTO calendar_feeds (user_id, token_hash, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET
+                 token_hash = excluded.token_hash, created_at = excluded.created_at",
+            params![user, token_hash, now],
+        )?;
+        Ok(now)
+    }
+
+    /// Turns the user's calendar feed off. Returns false if it was not on.
+    pub fn revoke_calendar_token(&self, user: UserId) -> rusqlite::Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM calendar_feeds WHERE user_id = ?1", [user])?;
+        Ok(deleted > 0)
+    }
+
+    /// The user whose calendar feed the token opens.
+    pub fn user_for_calendar_token(&self, token_hash: &str) -> rusqlite::Result<Option<UserId>> {
+        self.conn()
+            .query_row(
+                "SELECT user_id FROM calendar_feeds WHERE token_hash = ?1",
+                [token_hash],
+                |row| row.get(0),
+            )
+            .optional()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: UserId = 1;
+
+    #[test]
+    fn test_new_token_replaces_the_old_one() {
+        let db = Db::open_in_memory().unwrap();
+        db.set_calendar_token(USER, "first").unwrap();
+        db.set_calendar_token(USER, "second").unwrap();
+
+        assert_eq!(db.user_for_calendar_token("first").unwrap(), None);
+        assert_eq!(db.user_for_calendar_token("second").unwrap(), Some(USER));
+
+        assert!(db.revoke_calendar_token(USER).unwrap());
+        assert!(!db.revoke_calendar_token(USER).unwrap());
+        assert_eq!(db.user_for_calendar_token("second").unwrap(), None);
+    }
+}
@@ -0,0 +1,193 @@
+// backend/src/db/categories.rs
+//! Categories, read and written outside of a sync.
+use super::entries::{entries_referencing, rewrite_entries};
+use super::sync::{
+    begin_change, claim_id, delete_record, load_current, load_record, load_records, write_record,
+};
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::{Category, TimeEntry};
+
+/// What deleting a category does to the entries still in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CategoryEntries<'a> {
+    /// Keep the category and report the entries as [`Rejection::InUse`].
+    Refuse,
+    /// Move the entries to another category.
+    ReassignTo(&'a str),
+    /// Delete the entries along with the category.
+    Delete,
+}
+
+impl Db {
+    /// `user`'s categories by name.
+    pub fn list_categories(&self, user: UserId) -> rusqlite::Result<Vec<Category>> {
+        let mut categories: Vec<_> = load_records::<Category>(&self.conn(), "", [user])?
+            .into_values()
+            .collect();
+        categories.sort_by_key(|category| category.name.to_lowercase());
+        Ok(categories)
+    }
+
+    pub fn find_category(&self, user: UserId, id: &str) -> rusqlite::Result<Option<Category>> {
+        load_record(&self.conn(), user, id)
+    }
+
+    /// Stores a new category. An id that was deleted before may be reused.
+    pub fn create_category(&self, user: UserId, mut category: Category) -> WriteResult<Category> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if let Err(rejection) = claim_id::<Category>(&tx, user, &category.id)? {
+            return Ok(Err(rejection));
+        }
+        let now = begin_change(&tx, self.actor)?;
+        category.version = 1;
+        write_record(&tx, user, &category, now)?;
+        tx.commit()?;
+        Ok(Ok(category))
+    }
+
+    /// Replaces a category with `category`, whose version must be the one
+    /// stored.
+    pub fn update_category(&self, user: UserId, mut category: Category) -> WriteResult<Category> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let current = match load_current(&tx, user, &category)? {
+            Ok(current) => current,
+            Err(rejection) => return Ok(Err(rejection)),
+        };
+        if category == current {
+            return Ok(Ok(current));
+        }
+        let now = begin_change(&tx, self.actor)?;
+        category.version += 1;
+        write_record(&tx, user, &category, now)?;
+        tx.commit()?;
+        Ok(Ok(category))
+    }
+
+    /// Deletes a category and handles the entries in it as `entries` says.
+    /// Returns how many entries were moved or deleted.
+    pub fn delete_category(
+        &self,
+        user: UserId,
+        id: &str,
+        entries: CategoryEntries,
+    ) -> WriteResult<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if load_record::<Category>(&tx, user, id)?.is_none() {
+            return Ok(Err(Rejection::NotFound));
+        }
+        let affected = entries_referencing(&tx, user, "category_id", id)?;
+        let count = affected.len();
+
+        let now = begin_change(&tx, self.actor)?;
+        if count > 0 {
+            match entries {
+                CategoryEntries::Refuse => return Ok(Err(Rejection::InUse(count as i64))),
+                CategoryEntries::ReassignTo(target) => {
+                    if target == id || load_record::<Category>(&tx, user, target)?.is_none() {
+                        return Ok(Err(Rejection::MissingReference("reassign_to")));
+                    }
+                    rewrite_entries(&tx, user, affected, now, |entry| {
+                        entry.category_id = Some(target.to_string());
+                    })?;
+                }
+                CategoryEntries::Delete => {
+                    for entry in affected {
+                        delete_record::<TimeEntry>(&tx, user, &entry.id, now)?;
+                    }
+                }
+            }
+        }
+        delete_record::<Category>(&tx, user, id, now)?;
+        tx.commit()?;
+        Ok(Ok(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions};
+
+    const USER: UserId = 1;
+
+    fn category(id: &str, name: &str) -> Category {
+        Category {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: "#10b981".to_string(),
+            weekly_target_hours: Some(10.0),
+            ..Default::default()
+        }
+    }
+
+    fn db_with_entries() -> Db {
+        let db = Db::open_in_memory().unwrap();
+        db.create_category(USER, category("c1", "Work"))
+            .unwrap()
+            .unwrap();
+        db.create_category(USER, category("c2", "Learning"))
+            .unwrap()
+            .unwrap();
+        for id in ["e1", "e2"] {
+            let entry = TimeEntry {
+                id: id.to_string(),
+                start_time: 1_000,
+                end_time: Some(2_000),
+                category_id: Some("c1".to_string()),
+                ..Default::default()
+            };
+            db.create_entry(USER, entry).unwrap().unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_delete_reassigns_entries() {
+        let db = db_with_entries();
+        assert_eq!(
+            db.delete_category(USER, "c1", CategoryEntries::Refuse)
+                .unwrap(),
+            Err(Rejection::InUse(2))
+        );
+        assert_eq!(
+            db.delete_category(USER, "c1", CategoryEntries::ReassignTo("c1"))
+                .unwrap(),
+            Err(Rejection::MissingReference("reassign_to"))
+        );
+        assert_eq!(
+            db.delete_category(USER, "c1", CategoryEntries::ReassignTo("c2"))
+                .unwrap(),
+            Ok(2)
+        );
+
+        let entry = db.find_entry(USER, "e1").unwrap().unwrap();
+        assert_eq!(entry.category_id.as_deref(), Some("c2"));
+        let names: Vec<_> = db
+            .list_categories(USER)
+            .unwrap()
+            .into_iter()
+            .map(|category| category.name)
+            .collect();
+        assert_eq!(names, ["Learning"]);
+    }
+
+    #[test]
+    fn test_cascading_delete_tombstones_entries() {
+        let db = db_with_entries();
+        let cursor = db.snapshot(USER).unwrap().cursor;
+
+        assert_eq!(
+            db.delete_category(USER, "c1", CategoryEntries::Delete)
+                .unwrap(),
+            Ok(2)
+        );
+        let delta = db
+            .apply_sync(USER, &Dataset::default(), &Deletions::default(), cursor)
+            .unwrap();
+        assert_eq!(delta.deleted.time_entries, ["e1", "e2"]);
+        assert_eq!(delta.deleted.categories, ["c1"]);
+    }
+}
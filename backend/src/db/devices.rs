@@ -0,0 +1,155 @@
+// backend/src/db/devices.rs
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use super::{Db, DeviceId, UserId};
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Device {
+    pub id: DeviceId,
+    pub name: String,
+    pub created_at: i64,
+    /// Wall-clock time of the device's last sync, `None` if it never synced.
+    pub last_synced_at: Option<i64>,
+    /// Where the device's next delta sync starts.
+    pub sync_cursor: i64,
+}
+
+impl Device {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            created_at: row.get(2)?,
+            last_synced_at: row.get(3)?,
+            sync_cursor: row.get(4)?,
+        })
+    }
+}
+
+const DEVICE_COLUMNS: &str = "id, name, created_at, last_synced_at, sync_cursor";
+
+impl Db {
+    pub fn create_device(&self, user: UserId, name: &str) -> rusqlite::Result<Device> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let conn = self.conn();
+        conn.execute(
+            "INSERT INTO devices (user_id, name, created_at) VALUES (?1, ?2, ?3)",
+            params![user, name, now],
+        )?;
+        Ok(Device {
+            id: conn.last_insert_rowid(),
+            name: name.to_string(),
+            created_at: now,
+            last_synced_at: None,
+            sync_cursor: 0,
+        })
+    }
+
+    /// The devices `user` has not revoked, oldest first.
+    pub fn list_devices(&self, user: UserId) -> rusqlite::Result<Vec<Device>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {DEVICE_COLUMNS} FROM devices
+             WHERE user_id = ?1 AND revoked_at IS NULL ORDER BY id"
+        ))?;
+        let devices = stmt.query_map([user], Device::from_row)?.collect();
+        devices
+    }
+
+    pub fn find_device(&self, user: UserId, id: DeviceId) -> rusqlite::Result<Option<Device>> {
+        self.conn()
+            .query_row(
+                &format!(
+                    "SELECT {DEVICE_COLUMNS} FROM devices
+                     WHERE user_id = ?1 AND id = ?2 AND revoked_at IS NULL"
+                ),
+                [user, id],
+                Device::from_row,
+            )
+            .optional()
+    }
+
+    /// Revokes a device and every token issued to it. Returns false if `user`
+    /// has no such device.
+    pub fn revoke_device(&self, user: UserId, id: DeviceId) -> rusqlite::Result<bool> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let revoked = tx.execute(
+            "UPDATE devices SET revoked_at = ?3
+             WHERE user_id = ?1 AND id = ?2 AND revoked_at IS NULL",
+            params![user, id, now],
+        )?;
+        tx.execute(
+            "UPDATE auth_tokens SET revoked_at = ?3
+             WHERE user_id = ?1 AND device_id = ?2 AND revoked_at IS NULL",
+            params![user, id, now],
+        )?;
+        tx.commit()?;
+        Ok(revoked > 0)
+    }
+
+    /// Remembers where the device's next sync starts.
+    pub fn record_device_sync(&self, id: DeviceId, cursor: i64) -> rusqlite::Result<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn().execute(
+            "UPDATE devices SET sync_cursor = ?2, last_synced_at = ?3 WHERE id = ?1",
+            params![id, cursor, now],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_devices_are_listed_per_user() {
+        let db = Db::open_in_memory().unwrap();
+        let ada = db.create_user("ada", "hash").unwrap().unwrap();
+        let grace = db.create_user("grace", "hash").unwrap().unwrap();
+        let laptop = db.create_device(ada.id, "Laptop").unwrap();
+        db.create_device(ada.id, "Phone").unwrap();
+        db.create_device(grace.id, "Desktop").unwrap();
+
+        let names: Vec<_> = db
+            .list_devices(ada.id)
+            .unwrap()
+            .into_iter()
+            .map(|device| device.name)
+            .collect();
+        assert_eq!(names, ["Laptop", "Phone"]);
+        assert_eq!(db.find_device(grace.id, laptop.id).unwrap(), None);
+    }
+
+    #[test]
+    fn test_sync_advances_the_device_cursor() {
+        let db = Db::open_in_memory().unwrap();
+        let user = db.create_user("ada", "hash").unwrap().unwrap();
+        let device = db.create_device(user.id, "Laptop").unwrap();
+
+        db.record_device_sync(device.id, 42).unwrap();
+        let device = db.find_device(user.id, device.id).unwrap().unwrap();
+        assert_eq!(device.sync_cursor, 42);
+        assert!(device.last_synced_at.is_some());
+    }
+
+    #[test]
+    fn test_revoking_a_device_revokes_its_tokens() {
+        let db = Db::open_in_memory().unwrap();
+        let user = db.create_user("ada", "hash").unwrap().unwrap();
+        let device = db.create_device(user.id, "Phone").unwrap();
+        let later = chrono::Utc::now().timestamp_millis() + 60_000;
+        db.create_token(user.id, Some(device.id), "phone", later)
+            .unwrap();
+        db.create_token(user.id, None, "other", later).unwrap();
+
+        assert!(db.revoke_device(user.id, device.id).unwrap());
+        assert!(!db.revoke_device(user.id, device.id).unwrap());
+        assert_eq!(db.session_for_token("phone").unwrap(), None);
+        assert!(db.session_for_token("other").unwrap().is_some());
+        assert!(db.list_devices(user.id).unwrap().is_empty());
+    }
+}
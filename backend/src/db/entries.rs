@@ -0,0 +1,458 @@
+// backend/src/db/entries.rs
+//! Single time entries, read and written outside of a sync.
+//!
+//! Writes go through the same storage path as a sync, so they get a version,
+//! a change timestamp and history, and reach every device on its next sync.
+use rusqlite::{params, params_from_iter, Connection, ToSql};
+
+use super::sync::{
+    begin_change, claim_id, delete_record, load_current, load_record, load_records, write_record,
+};
+use super::timer::stop_superseded_timers;
+use super::work_types::classify_written;
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::{Category, Project, TimeEntry};
+
+/// Which entries to list, newest first.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EntryQuery {
+    /// Only entries starting at or after this time.
+    pub from: Option<i64>,
+    /// Only entries starting before this time.
+    pub to: Option<i64>,
+    pub project_id: Option<String>,
+    pub category_id: Option<String>,
+    /// Only entries after this `(startTime, id)` in list order, for paging
+    /// through a large result without an offset.
+    pub after: Option<(i64, String)>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// One page of entries and how many match in total.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryPage {
+    pub entries: Vec<TimeEntry>,
+    pub total: i64,
+}
+
+impl Db {
+    pub fn list_entries(&self, user: UserId, query: &EntryQuery) -> rusqlite::Result<EntryPage> {
+        let conn = self.conn();
+        let mut filter = String::new();
+        let mut values: Vec<&dyn ToSql> = vec![&user];
+        for (condition, value) in [
+            (
+                "start_time >= ?",
+                query.from.as_ref().map(|v| v as &dyn ToSql),
+            ),
+            ("start_time < ?", query.to.as_ref().map(|v| v as &dyn ToSql)),
+            (
+                "project_id = ?",
+                query.project_id.as_ref().map(|v| v as &dyn ToSql),
+            ),
+            (
+                "category_id = ?",
+                query.category_id.as_ref().map(|v| v as &dyn ToSql),
+            ),
+        ] {
+            if let Some(value) = value {
+                values.push(value);
+                filter.push_str(&format!(" AND {condition}{}", values.len()));
+            }
+        }
+        if let Some((start_time, id)) = &query.after {
+            values.push(start_time);
+            values.push(id);
+            let (start, id) = (values.len() - 1, values.len());
+            filter.push_str(&format!(
+                " AND (start_time < ?{start} OR (start_time = ?{start} AND id < ?{id}))"
+            ));
+        }
+
+        let total = conn.query_row(
+            &format!("SELECT COUNT(*) FROM time_entries WHERE user_id = ?1{filter}"),
+            params_from_iter(&values),
+            |row| row.get(0),
+        )?;
+
+        values.push(&query.limit);
+        values.push(&query.offset);
+        let page = format!(
+            "{filter} ORDER BY start_time DESC, id DESC LIMIT ?{} OFFSET ?{}",
+            values.len() - 1,
+            values.len()
+        );
+        let mut entries: Vec<_> =
+            load_records::<TimeEntry>(&conn, &page, params_from_iter(&values))?
+                .into_values()
+                .collect();
+        // Records come back keyed by id; restore the query's order.
+        entries.sort_by(|a, b| (b.start_time, &b.id).cmp(&(a.start_time, &a.id)));
+        Ok(EntryPage { entries, total })
+    }
+
+    /// Entries overlapping `[from, to)`, in no particular order. A running
+    /// entry overlaps everything after its start.
+    pub fn overlapping_entries(
+        &self,
+        user: UserId,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> rusqlite::Result<Vec<TimeEntry>> {
+        let entries = load_records::<TimeEntry>(
+            &self.conn(),
+            "AND (?2 IS NULL OR end_time IS NULL OR end_time > ?2)
+             AND (?3 IS NULL OR start_time < ?3)",
+            params![user, from, to],
+        )?;
+        Ok(entries.into_values().collect())
+    }
+
+    /// Start of `user`'s earliest entry in a category.
+    pub fn first_categorized_start(&self, user: UserId) -> rusqlite::Result<Option<i64>> {
+        self.conn().query_row(
+            "SELECT MIN(start_time) FROM time_entries
+             WHERE user_id = ?1 AND category_id IS NOT NULL",
+            [user],
+            |row| row.get(0),
+        )
+    }
+
+    /// Milliseconds logged per category in each `[start, end)` of `periods`,
+    /// as `(category id, index of the period, milliseconds)`. Running entries
+    /// count up to `now`, and an entry spanning several periods counts
+    /// towards each.
+    pub fn logged_per_category(
+        &self,
+        user: UserId,
+        periods: &[(i64, i64)],
+        now: i64,
+    ) -> rusqlite::Result<Vec<(String, usize, i64)>> {
+        let periods = serde_json::to_string(periods).expect("periods serialize");
+        let conn = self.conn();
+        let mut statement = conn.prepare(
+            "WITH periods AS (
+                 SELECT key AS period, value ->> 0 AS period_start, value ->> 1 AS period_end
+                 FROM json_each(?2)
+             )
+             SELECT category_id, period,
+                    SUM(MIN(COALESCE(end_time, ?3), ?3, period_end)
+                        - MAX(start_time, period_start))
+             FROM time_entries JOIN periods
+                 ON start_time < MIN(?3, period_end)
+                 AND COALESCE(end_time, ?3) > period_start
+             WHERE user_id = ?1 AND category_id IS NOT NULL
+             GROUP BY category_id, period",
+        )?;
+        let logged = statement
+            .query_map(params![user, periods, now], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+            })?
+            .collect();
+        logged
+    }
+
+    pub fn find_entry(&self, user: UserId, id: &str) -> rusqlite::Result<Option<TimeEntry>> {
+        load_record(&self.conn(), user, id)
+    }
+
+    /// Stores a new entry. An id that was deleted before may be reused.
+    pub fn create_entry(&self, user: UserId, mut entry: TimeEntry) -> WriteResult<TimeEntry> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if let Err(rejection) = check_references(&tx, user, &entry)? {
+            return Ok(Err(rejection));
+        }
+        if let Err(rejection) = claim_id::<TimeEntry>(&tx, user, &entry.id)? {
+            return Ok(Err(rejection));
+        }
+
+        let now = begin_change(&tx, self.actor)?;
+        entry.version = 1;
+        write_record(&tx, user, &entry, now)?;
+        stop_superseded_timers(&tx, user, now)?;
+        classify_written(&tx, user)?;
+        let stored = load_record(&tx, user, &entry.id)?.expect("entry was just written");
+        tx.commit()?;
+        Ok(Ok(stored))
+    }
+
+    /// Replaces an entry with `entry`, whose version must be the one stored.
+    /// Only references the update changes are checked: an entry can keep
+    /// pointing to a project or category that was deleted since.
+    pub fn update_entry(&self, user: UserId, mut entry: TimeEntry) -> WriteResult<TimeEntry> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let current = match load_current(&tx, user, &entry)? {
+            Ok(current) => current,
+            Err(rejection) => return Ok(Err(rejection)),
+        };
+        let changed = TimeEntry {
+            project_id: entry
+                .project_id
+                .clone()
+                .filter(|id| current.project_id.as_ref() != Some(id)),
+            category_id: entry
+                .category_id
+                .clone()
+                .filter(|id| current.category_id.as_ref() != Some(id)),
+            ..entry.clone()
+        };
+        if let Err(rejection) = check_references(&tx, user, &changed)? {
+            return Ok(Err(rejection));
+        }
+        if entry == current {
+            return Ok(Ok(current));
+        }
+
+        let now = begin_change(&tx, self.actor)?;
+        entry.version += 1;
+        write_record(&tx, user, &entry, now)?;
+        stop_superseded_timers(&tx, user, now)?;
+        classify_written(&tx, user)?;
+        let stored = load_record(&tx, user, &entry.id)?.expect("entry was just written");
+        tx.commit()?;
+        Ok(Ok(stored))
+    }
+
+    /// Deletes an entry, leaving a tombstone for other devices. Returns false
+    /// if there was no such entry.
+    pub fn delete_entry(&self, user: UserId, id: &str) -> rusqlite::Result<bool> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if load_record::<TimeEntry>(&tx, user, id)?.is_none() {
+            return Ok(false);
+        }
+        let now = begin_change(&tx, self.actor)?;
+        delete_record::<TimeEntry>(&tx, user, id, now)?;
+        tx.commit()?;
+        Ok(true)
+    }
+}
+
+/// `user`'s entries whose `column` (`project_id` or `category_id`) is `id`.
+pub(super) fn entries_referencing(
+    conn: &Connection,
+    user: UserId,
+    column: &str,
+    id: &str,
+) -> rusqlite::Result<Vec<TimeEntry>> {
+    Ok(load_records::<TimeEntry>(
+        conn,
+        &format!("AND {column} = ?2"),
+        rusqlite::params![user, id],
+    )?
+    .into_values()
+    .collect())
+}
+
+/// Applies `edit` to each entry and stores it as a new version.
+pub(super) fn rewrite_entries(
+    conn: &Connection,
+    user: UserId,
+    entries: Vec<TimeEntry>,
+    now: i64,
+    edit: impl Fn(&mut TimeEntry),
+) -> rusqlite::Result<()> {
+    for mut entry in entries {
+        edit(&mut entry);
+        entry.version += 1;
+        write_record(conn, user, &entry, now)?;
+    }
+    Ok(())
+}
+
+/// The project and category an entry names must exist.
+pub(super) fn check_references(
+    conn: &Connection,
+    user: UserId,
+    entry: &TimeEntry,
+) -> rusqlite::Result<Result<(), Rejection>> {
+    if let Some(project) = &entry.project_id {
+        if load_record::<Project>(conn, user, project)?.is_none() {
+            return Ok(Err(Rejection::MissingReference("projectId")));
+        }
+    }
+    if let Some(category) = &entry.category_id {
+        if load_record::<Category>(conn, user, category)?.is_none() {
+            return Ok(Err(Rejection::MissingReference("categoryId")));
+        }
+    }
+    Ok(Ok(()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions};
+
+    const USER: UserId = 1;
+
+    fn entry(id: &str, start_time: i64) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            description: "Review".to_string(),
+            start_time,
+            end_time: Some(start_time + 1_000),
+            project_id: Some("p1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn db_with_project() -> Db {
+        let db = Db::open_in_memory().unwrap();
+        let mut changes = Dataset::default();
+        changes.projects.insert(
+            "p1".to_string(),
+            Project {
+                id: "p1".to_string(),
+                name: "Work".to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+        db.apply_sync(USER, &changes, &Deletions::default(), 0)
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_overlapping_entries_include_those_crossing_the_range() {
+        let db = db_with_project();
+        for (id, start) in [("before", 1_000), ("crossing", 1_500), ("inside", 2_200)] {
+            db.create_entry(USER, entry(id, start)).unwrap().unwrap();
+        }
+
+        let mut ids: Vec<_> = db
+            .overlapping_entries(USER, Some(2_000), Some(3_000))
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, ["crossing", "inside"]);
+    }
+
+    #[test]
+    fn test_list_filters_and_pages_newest_first() {
+        let db = db_with_project();
+        for (id, start) in [("a", 1_000), ("b", 2_000), ("c", 3_000), ("d", 4_000)] {
+            db.create_entry(USER, entry(id, start)).unwrap().unwrap();
+        }
+        let query = EntryQuery {
+            from: Some(1_500),
+            limit: 2,
+            offset: 0,
+            ..EntryQuery::default()
+        };
+
+        let page = db.list_entries(USER, &query).unwrap();
+        let ids: Vec<_> = page.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["d", "c"]);
+        assert_eq!(page.total, 3);
+
+        let page = db
+            .list_entries(
+                USER,
+                &EntryQuery {
+                    offset: 2,
+                    ..query.clone()
+                },
+            )
+            .unwrap();
+        assert_eq!(page.entries[0].id, "b");
+
+        let after = EntryQuery {
+            after: Some((3_000, "c".to_string())),
+            ..query.clone()
+        };
+        let ids: Vec<_> = db
+            .list_entries(USER, &after)
+            .unwrap()
+            .entries
+            .into_iter()
+            .map(|e| e.id)
+            .collect();
+        assert_eq!(ids, ["b"]);
+
+        let other = EntryQuery {
+            project_id: Some("p2".to_string()),
+            ..query
+        };
+        assert_eq!(db.list_entries(USER, &other).unwrap().total, 0);
+    }
+
+    #[test]
+    fn test_writes_check_references_and_versions() {
+        let db = db_with_project();
+        let orphan = TimeEntry {
+            category_id: Some("missing".to_string()),
+            ..entry("e1", 1_000)
+        };
+        assert_eq!(
+            db.create_entry(USER, orphan).unwrap(),
+            Err(Rejection::MissingReference("categoryId"))
+        );
+
+        let created = db.create_entry(USER, entry("e1", 1_000)).unwrap().unwrap();
+        assert_eq!(created.version, 1);
+        assert_eq!(
+            db.create_entry(USER, entry("e1", 1_000)).unwrap(),
+            Err(Rejection::IdTaken)
+        );
+
+        let edited = TimeEntry {
+            description: "Code review".to_string(),
+            ..created.clone()
+        };
+        let updated = db.update_entry(USER, edited.clone()).unwrap().unwrap();
+        assert_eq!(updated.version, 2);
+        assert_eq!(
+            db.update_entry(USER, edited).unwrap(),
+            Err(Rejection::StaleVersion(2))
+        );
+    }
+
+    #[test]
+    fn test_updates_keep_references_to_deleted_records() {
+        let db = db_with_project();
+        let created = db.create_entry(USER, entry("e1", 1_000)).unwrap().unwrap();
+        let deletions = Deletions {
+            projects: vec!["p1".to_string()],
+            ..Deletions::default()
+        };
+        db.apply_sync(USER, &Dataset::default(), &deletions, 0)
+            .unwrap();
+
+        let edited = TimeEntry {
+            description: "Code review".to_string(),
+            ..created
+        };
+        let updated = db.update_entry(USER, edited).unwrap().unwrap();
+        assert_eq!(updated.project_id.as_deref(), Some("p1"));
+
+        let moved = TimeEntry {
+            category_id: Some("missing".to_string()),
+            ..updated
+        };
+        assert_eq!(
+            db.update_entry(USER, moved).unwrap(),
+            Err(Rejection::MissingReference("categoryId"))
+        );
+    }
+
+    #[test]
+    fn test_deleted_entries_reach_other_devices() {
+        let db = db_with_project();
+        db.create_entry(USER, entry("e1", 1_000)).unwrap().unwrap();
+        let cursor = db.snapshot(USER).unwrap().cursor;
+
+        assert!(db.delete_entry(USER, "e1").unwrap());
+        assert!(!db.delete_entry(USER, "e1").unwrap());
+        let delta = db
+            .apply_sync(USER, &Dataset::default(), &Deletions::default(), cursor)
+            .unwrap();
+        assert_eq!(delta.deleted.time_entries, ["e1"]);
+    }
+}
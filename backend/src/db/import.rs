@@ -0,0 +1,383 @@
+// backend/src/db/import.rs
+//! Bulk import of entries parsed from another tracker's export.
+use std::collections::{HashMap, HashSet};
+
+use rusqlite::Connection;
+
+use super::sync::{begin_change, claim_id, load_record, load_records, write_record, Table};
+use super::work_types::classify_written;
+use super::{Db, UserId};
+use crate::auth::random_hex;
+use crate::model::{Category, Project, TimeEntry};
+
+/// Colors handed to projects and categories created by an import, in turn.
+const PALETTE: [&str; 6] = [
+    "#3b82f6", "#10b981", "#f59e0b", "#ef4444", "#8b5cf6", "#ec4899",
+];
+
+/// A project or category an imported entry refers to, by id, by name or both.
+/// A name wins over an id, since ids rarely survive a move between trackers.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reference {
+    pub id: Option<String>,
+    pub name: Option<String>,
+}
+
+/// One entry read from an import file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedEntry {
+    /// Position in the file, for the report.
+    pub row: usize,
+    /// Kept if the file came from this app; generated otherwise.
+    pub id: Option<String>,
+    pub description: String,
+    pub start_time: i64,
+    pub end_time: i64,
+    pub project: Option<Reference>,
+    pub category: Option<Reference>,
+    pub billable: Option<bool>,
+    pub hourly_rate: Option<f64>,
+    pub currency: Option<String>,
+    /// How to bill the project, if the import has to create it. Projects
+    /// that exist already are left as they are.
+    pub project_billing: Option<ProjectBilling>,
+}
+
+/// The billing fields of a project an import creates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProjectBilling {
+    pub billable: bool,
+    pub client: Option<String>,
+    pub hourly_rate: Option<f64>,
+    pub currency: Option<String>,
+}
+
+/// A row that was not imported, and why.
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct SkippedRow {
+    pub row: usize,
+    pub reason: String,
+}
+
+/// What an import created, or would create in a dry run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportOutcome {
+    pub created_entries: usize,
+    pub created_projects: Vec<String>,
+    pub created_categories: Vec<String>,
+    pub duplicates: Vec<SkippedRow>,
+    pub rejected: Vec<SkippedRow>,
+}
+
+impl Db {
+    /// Stores `entries`, creating the projects and categories they name that
+    /// do not exist yet. Entries already stored, by id or by identical
+    /// description and times, are skipped as duplicates.
+    ///
+    /// A dry run works out the same outcome and then rolls everything back.
+    pub fn import_entries(
+        &self,
+        user: UserId,
+        entries: Vec<ImportedEntry>,
+        dry_run: bool,
+    ) -> rusqlite::Result<ImportOutcome> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = begin_change(&tx, self.actor)?;
+
+        let mut outcome = ImportOutcome::default();
+        let mut projects = Resolver::<Project>::load(&tx, user)?;
+        let mut categories = Resolver::<Category>::load(&tx, user)?;
+        let mut seen: HashSet<(String, i64, i64)> =
+            load_records::<TimeEntry>(&tx, "AND end_time IS NOT NULL", [user])?
+                .into_values()
+                .filter_map(|entry| Some((entry.description, entry.start_time, entry.end_time?)))
+                .collect();
+
+        for imported in entries {
+            let key = (
+                imported.description.clone(),
+                imported.start_time,
+                imported.end_time,
+            );
+            let id_taken = match &imported.id {
+                Some(id) => load_record::<TimeEntry>(&tx, user, id)?.is_some(),
+                None => false,
+            };
+            if id_taken || seen.contains(&key) {
+                outcome.duplicates.push(SkippedRow {
+                    row: imported.row,
+                    reason: if id_taken {
+                        "an entry with this id already exists".to_string()
+                    } else {
+                        "an entry with the same description and times already exists".to_string()
+                    },
+                });
+                continue;
+            }
+
+            // Both references are checked before anything is written, so a
+            // rejected row leaves no new project or category behind.
+            let project = match &imported.project {
+                Some(reference) => match projects.find(reference) {
+                    Ok(found) => Some(found),
+                    Err(reason) => {
+                        outcome.rejected.push(SkippedRow {
+                            row: imported.row,
+                            reason: format!("project {reason}"),
+                        });
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let category = match &imported.category {
+                Some(reference) => match categories.find(reference) {
+                    Ok(found) => Some(found),
+                    Err(reason) => {
+                        outcome.rejected.push(SkippedRow {
+                            row: imported.row,
+                            reason: format!("category {reason}"),
+                        });
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            let project_id = match project {
+                Some(found) => Some(projects.settle(&tx, user, found, now, |project| {
+                    if let Some(billing) = imported.project_billing {
+                        project.billable = billing.billable;
+                        project.client = billing.client;
+                        project.hourly_rate = billing.hourly_rate;
+                        project.currency = billing.currency;
+                    }
+                })?),
+                None => None,
+            };
+            let category_id = match category {
+                Some(found) => Some(categories.settle(&tx, user, found, now, |_| {})?),
+                None => None,
+            };
+
+            let entry = TimeEntry {
+                id: imported.id.unwrap_or_else(|| random_hex(16)),
+                description: imported.description,
+                start_time: imported.start_time,
+                end_time: Some(imported.end_time),
+                project_id,
+                category_id,
+                billable: imported.billable,
+                hourly_rate: imported.hourly_rate,
+                currency: imported.currency,
+                version: 1,
+            };
+            // Drops the tombstone if the file brings back a deleted entry.
+            claim_id::<TimeEntry>(&tx, user, &entry.id)?.expect("duplicate ids were skipped above");
+            write_record(&tx, user, &entry, now)?;
+            seen.insert(key);
+            outcome.created_entries += 1;
+        }
+
+        classify_written(&tx, user)?;
+        outcome.created_projects = projects.created;
+        outcome.created_categories = categories.created;
+        if !dry_run {
+            tx.commit()?;
+        }
+        Ok(outcome)
+    }
+}
+
+/// Names of the records a [`Resolver`] can create.
+trait Named: Table {
+    fn name(&self) -> &str;
+    fn new(id: String, name: String, color: &str) -> Self;
+}
+
+impl Named for Project {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new(id: String, name: String, color: &str) -> Self {
+        Project {
+            id,
+            name,
+            color: color.to_string(),
+            archived: false,
+            billable: false,
+            client: None,
+            hourly_rate: None,
+            currency: None,
+            version: 1,
+        }
+    }
+}
+
+impl Named for Category {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn new(id: String, name: String, color: &str) -> Self {
+        Category {
+            id,
+            name,
+            color: color.to_string(),
+            weekly_target_hours: None,
+            version: 1,
+        }
+    }
+}
+
+/// Finds projects or categories by name, case-insensitively, creating the
+/// ones that are missing.
+struct Resolver<T> {
+    by_name: HashMap<String, String>,
+    ids: HashSet<String>,
+    created: Vec<String>,
+    kind: std::marker::PhantomData<T>,
+}
+
+impl<T: Named> Resolver<T> {
+    fn load(conn: &Connection, user: UserId) -> rusqlite::Result<Self> {
+        let records = load_records::<T>(conn, "", [user])?;
+        Ok(Self {
+            by_name: records
+                .values()
+                .map(|record| (record.name().to_lowercase(), record.id().to_string()))
+                .collect(),
+            ids: records.into_keys().collect(),
+            created: Vec::new(),
+            kind: std::marker::PhantomData,
+        })
+    }
+
+    /// The record `reference` points to, or why there is none. Nothing is
+    /// created until the match is [settled](Self::settle).
+    fn find(&self, reference: &Reference) -> Result<Found, String> {
+        let name = reference.name.as_deref().map(str::trim).unwrap_or_default();
+        if name.is_empty() {
+            return match &reference.id {
+                Some(id) if self.ids.contains(id) => Ok(Found::Existing(id.clone())),
+                Some(id) => Err(format!(
+                    "`{id}` does not exist and has no name to create it by"
+                )),
+                None => Err("has neither an id nor a name".to_string()),
+            };
+        }
+        Ok(match self.by_name.get(&name.to_lowercase()) {
+            Some(id) => Found::Existing(id.clone()),
+            None => Found::New(name.to_string()),
+        })
+    }
+
+    /// The id of a record [`find`](Self::find) matched, creating it if it
+    /// did not exist yet. `fill` completes a record about to be created.
+    fn settle(
+        &mut self,
+        conn: &Connection,
+        user: UserId,
+        found: Found,
+        now: i64,
+        fill: impl FnOnce(&mut T),
+    ) -> rusqlite::Result<String> {
+        let name = match found {
+            Found::Existing(id) => return Ok(id),
+            Found::New(name) => name,
+        };
+
+        let id = random_hex(16);
+        let color = PALETTE[self.created.len() % PALETTE.len()];
+        let mut record = T::new(id.clone(), name.clone(), color);
+        fill(&mut record);
+        write_record(conn, user, &record, now)?;
+        self.by_name.insert(name.to_lowercase(), id.clone());
+        self.ids.insert(id.clone());
+        self.created.push(name);
+        Ok(id)
+    }
+}
+
+/// What a [`Resolver`] found for a reference.
+enum Found {
+    Existing(String),
+    /// No record has the name yet; it is created under it.
+    New(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: UserId = 1;
+
+    fn imported(row: usize, description: &str, project: &str) -> ImportedEntry {
+        ImportedEntry {
+            row,
+            id: None,
+            description: description.to_string(),
+            start_time: 1_000 * row as i64,
+            end_time: 1_000 * row as i64 + 500,
+            project: Some(Reference {
+                id: None,
+                name: Some(project.to_string()),
+            }),
+            category: None,
+            billable: None,
+            hourly_rate: None,
+            currency: None,
+            project_billing: None,
+        }
+    }
+
+    #[test]
+    fn test_import_creates_missing_projects_once() {
+        let db = Db::open_in_memory().unwrap();
+        let entries = vec![
+            imported(1, "Design", "Website"),
+            imported(2, "Build", "website"),
+            imported(3, "Ship", "Mobile"),
+        ];
+
+        let outcome = db.import_entries(USER, entries, false).unwrap();
+        assert_eq!(outcome.created_entries, 3);
+        assert_eq!(outcome.created_projects, ["Website", "Mobile"]);
+        assert_eq!(db.list_projects(USER, true).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_dry_run_reports_without_writing() {
+        let db = Db::open_in_memory().unwrap();
+        let entries = vec![imported(1, "Design", "Website")];
+
+        let outcome = db.import_entries(USER, entries.clone(), true).unwrap();
+        assert_eq!(outcome.created_entries, 1);
+        assert!(db.list_projects(USER, true).unwrap().is_empty());
+
+        db.import_entries(USER, entries.clone(), false).unwrap();
+        let again = db.import_entries(USER, entries, true).unwrap();
+        assert_eq!(again.created_entries, 0);
+        assert_eq!(again.duplicates.len(), 1);
+        assert!(again.created_projects.is_empty());
+    }
+
+    #[test]
+    fn test_unknown_ids_without_names_are_rejected() {
+        let db = Db::open_in_memory().unwrap();
+        let entry = ImportedEntry {
+            category: Some(Reference {
+                id: Some("c9".to_string()),
+                name: None,
+            }),
+            ..imported(1, "Design", "Website")
+        };
+
+        let outcome = db.import_entries(USER, vec![entry], false).unwrap();
+        assert_eq!(outcome.rejected.len(), 1);
+        assert!(outcome.rejected[0].reason.starts_with("category `c9`"));
+        assert!(outcome.created_projects.is_empty());
+        assert!(db.list_projects(USER, true).unwrap().is_empty());
+    }
+}
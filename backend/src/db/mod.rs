@@ -0,0 +1,168 @@
+// backend/src/db/mod.rs
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+mod audit;
+mod calendar;
+mod categories;
+mod devices;
+mod entries;
+mod import;
+mod projects;
+mod retention;
+mod sync;
+mod timer;
+mod trash;
+mod users;
+mod work_types;
+mod workspaces;
+
+pub use audit::{AuditEvent, AuditQuery};
+pub use categories::CategoryEntries;
+pub use devices::Device;
+pub use entries::EntryQuery;
+pub use import::{ImportedEntry, ProjectBilling, Reference, SkippedRow};
+pub use retention::{ArchivedEntry, RetentionAction, RetentionPolicy, RetentionRun, RunTrigger};
+pub use sync::SyncDelta;
+pub use timer::TimerChange;
+pub use trash::{RecordKind, Restored, TrashedRecord};
+pub use users::User;
+pub use workspaces::{Member, MemberStore, Membership, Role, Workspace, WorkspaceId};
+
+/// Row id of an account in the `users` table.
+pub type UserId = i64;
+
+/// Row id of a registered client in the `devices` table.
+pub type DeviceId = i64;
+
+/// Why a single-record write was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Rejection {
+    /// There is no record with this id.
+    NotFound,
+    /// A record with this id already exists.
+    IdTaken,
+    /// The record changed since the client read it; carries the stored
+    /// version.
+    StaleVersion(i64),
+    /// The named field refers to a record that does not exist.
+    MissingReference(&'static str),
+    /// Other records still refer to this one; carries how many.
+    InUse(i64),
+    /// The change would leave a workspace without an owner.
+    LastOwner,
+}
+
+/// Outcome of a single-record write: a storage failure, a refusal, or the
+/// record as stored.
+pub type WriteResult<T> = rusqlite::Result<Result<T, Rejection>>;
+
+/// Schema migrations, applied in order. `PRAGMA user_version` records how
+/// many have run, so only append to this list and never edit an entry.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/0001_initial.sql"),
+    include_str!("../../migrations/0002_delta_sync.sql"),
+    include_str!("../../migrations/0003_record_versions.sql"),
+    include_str!("../../migrations/0004_tombstone_gc.sql"),
+    include_str!("../../migrations/0005_users.sql"),
+    include_str!("../../migrations/0006_devices.sql"),
+    include_str!("../../migrations/0007_archived_projects.sql"),
+    include_str!("../../migrations/0008_calendar_feeds.sql"),
+    include_str!("../../migrations/0009_user_settings.sql"),
+    include_str!("../../migrations/0010_work_types.sql"),
+    include_str!("../../migrations/0011_retention.sql"),
+    include_str!("../../migrations/0012_trash.sql"),
+    include_str!("../../migrations/0013_audit_log.sql"),
+    include_str!("../../migrations/0014_workspaces.sql"),
+    include_str!("../../migrations/0015_billing.sql"),
+];
+
+/// Schema version of a fully migrated database.
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+/// Who makes the changes written through a [`Db`] handle, as recorded in
+/// the audit log.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Actor {
+    pub user: UserId,
+    pub device: Option<DeviceId>,
+}
+
+/// Handle to the embedded SQLite database, cheap to clone into handlers.
+#[derive(Clone)]
+pub struct Db {
+    conn: Arc<Mutex<Connection>>,
+    /// `None` for changes the server makes on its own, like scheduled
+    /// retention runs.
+    actor: Option<Actor>,
+}
+
+impl Db {
+    /// Opens (or creates) the database file and brings its schema up to date.
+    pub fn open(path: impl AsRef<Path>) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    #[cfg(test)]
+    pub fn open_in_memory() -> rusqlite::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> rusqlite::Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        migrate(&mut conn)?;
+        audit::init_context(&conn)?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+            actor: None,
+        })
+    }
+
+    /// A handle whose changes are attributed to `actor` in the audit log.
+    pub fn acting_as(&self, actor: Actor) -> Self {
+        Self {
+            conn: self.conn.clone(),
+            actor: Some(actor),
+        }
+    }
+
+    fn conn(&self) -> MutexGuard<'_, Connection> {
+        self.conn.lock().expect("database mutex poisoned")
+    }
+
+    /// How many migrations the database has applied. Doubles as a
+    /// connectivity check.
+    pub fn schema_version(&self) -> rusqlite::Result<usize> {
+        self.conn()
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+    }
+}
+
+fn migrate(conn: &mut Connection) -> rusqlite::Result<()> {
+    let applied: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrations_are_idempotent() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn).unwrap();
+        migrate(&mut conn).unwrap();
+
+        let version: usize = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, SCHEMA_VERSION);
+    }
+}
@@ -0,0 +1,191 @@
+// backend/src/db/projects.rs
+//! Projects, read and written outside of a sync.
+use super::entries::{entries_referencing, rewrite_entries};
+use super::sync::{
+    begin_change, claim_id, delete_record, load_current, load_record, load_records, write_record,
+};
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::Project;
+
+impl Db {
+    /// `user`'s projects by name, leaving out archived ones unless asked.
+    pub fn list_projects(
+        &self,
+        user: UserId,
+        include_archived: bool,
+    ) -> rusqlite::Result<Vec<Project>> {
+        let filter = if include_archived {
+            ""
+        } else {
+            "AND archived = 0"
+        };
+        let mut projects: Vec<_> = load_records::<Project>(&self.conn(), filter, [user])?
+            .into_values()
+            .collect();
+        projects.sort_by_key(|project| project.name.to_lowercase());
+        Ok(projects)
+    }
+
+    pub fn find_project(&self, user: UserId, id: &str) -> rusqlite::Result<Option<Project>> {
+        load_record(&self.conn(), user, id)
+    }
+
+    /// Stores a new project. An id that was deleted before may be reused.
+    pub fn create_project(&self, user: UserId, mut project: Project) -> WriteResult<Project> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if let Err(rejection) = claim_id::<Project>(&tx, user, &project.id)? {
+            return Ok(Err(rejection));
+        }
+        let now = begin_change(&tx, self.actor)?;
+        project.version = 1;
+        write_record(&tx, user, &project, now)?;
+        tx.commit()?;
+        Ok(Ok(project))
+    }
+
+    /// Replaces a project with `project`, whose version must be the one
+    /// stored.
+    pub fn update_project(&self, user: UserId, mut project: Project) -> WriteResult<Project> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let current = match load_current(&tx, user, &project)? {
+            Ok(current) => current,
+            Err(rejection) => return Ok(Err(rejection)),
+        };
+        if project == current {
+            return Ok(Ok(current));
+        }
+        let now = begin_change(&tx, self.actor)?;
+        project.version += 1;
+        write_record(&tx, user, &project, now)?;
+        tx.commit()?;
+        Ok(Ok(project))
+    }
+
+    /// Deletes a project. Entries still assigned to it are moved to
+    /// `reassign_to` if given, which must be another live project; otherwise
+    /// the project is kept and the number of such entries returned as
+    /// [`Rejection::InUse`]. Returns how many entries were moved.
+    pub fn delete_project(
+        &self,
+        user: UserId,
+        id: &str,
+        reassign_to: Option<&str>,
+    ) -> WriteResult<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if load_record::<Project>(&tx, user, id)?.is_none() {
+            return Ok(Err(Rejection::NotFound));
+        }
+        let entries = entries_referencing(&tx, user, "project_id", id)?;
+
+        let now = begin_change(&tx, self.actor)?;
+        let moved = entries.len();
+        if !entries.is_empty() {
+            let Some(target) = reassign_to else {
+                return Ok(Err(Rejection::InUse(moved as i64)));
+            };
+            if target == id || load_record::<Project>(&tx, user, target)?.is_none() {
+                return Ok(Err(Rejection::MissingReference("reassign_to")));
+            }
+            rewrite_entries(&tx, user, entries, now, |entry| {
+                entry.project_id = Some(target.to_string());
+            })?;
+        }
+        delete_record::<Project>(&tx, user, id, now)?;
+        tx.commit()?;
+        Ok(Ok(moved))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimeEntry;
+
+    const USER: UserId = 1;
+
+    fn project(id: &str, name: &str) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            color: "#3b82f6".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn entry(id: &str, project: &str) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            project_id: Some(project.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_archived_projects_are_listed_on_request() {
+        let db = Db::open_in_memory().unwrap();
+        db.create_project(USER, project("p1", "Work"))
+            .unwrap()
+            .unwrap();
+        let old = db
+            .create_project(USER, project("p2", "Archive me"))
+            .unwrap()
+            .unwrap();
+        db.update_project(
+            USER,
+            Project {
+                archived: true,
+                ..old
+            },
+        )
+        .unwrap()
+        .unwrap();
+
+        let names = |include_archived| {
+            db.list_projects(USER, include_archived)
+                .unwrap()
+                .into_iter()
+                .map(|project| project.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(false), ["Work"]);
+        assert_eq!(names(true), ["Archive me", "Work"]);
+    }
+
+    #[test]
+    fn test_delete_refuses_while_entries_use_the_project() {
+        let db = Db::open_in_memory().unwrap();
+        db.create_project(USER, project("p1", "Old"))
+            .unwrap()
+            .unwrap();
+        db.create_project(USER, project("p2", "New"))
+            .unwrap()
+            .unwrap();
+        db.create_entry(USER, entry("e1", "p1")).unwrap().unwrap();
+
+        assert_eq!(
+            db.delete_project(USER, "p1", None).unwrap(),
+            Err(Rejection::InUse(1))
+        );
+        for target in ["p3", "p1"] {
+            assert_eq!(
+                db.delete_project(USER, "p1", Some(target)).unwrap(),
+                Err(Rejection::MissingReference("reassign_to"))
+            );
+        }
+        assert_eq!(db.delete_project(USER, "p1", Some("p2")).unwrap(), Ok(1));
+
+        let moved = db.find_entry(USER, "e1").unwrap().unwrap();
+        assert_eq!(moved.project_id.as_deref(), Some("p2"));
+        assert_eq!(moved.version, 2);
+        assert_eq!(db.find_project(USER, "p1").unwrap(), None);
+        assert_eq!(
+            db.delete_project(USER, "p2", None).unwrap(),
+            Err(Rejection::InUse(1))
+        );
+    }
+}
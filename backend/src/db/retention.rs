@@ -0,0 +1,446 @@
+// backend/src/db/retention.rs
+//! Retention policies and the runs that apply them.
+//!
+//! Only finished entries are removed, by their start time. Archived entries
+//! leave the synced data like deleted ones, so devices drop them, but are
+//! kept on the server.
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::sync::{begin_change, load_record, load_records, remove_record};
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::{Project, TimeEntry};
+
+const DAY_MS: i64 = 24 * 60 * 60 * 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    /// Keep the entry on the server only.
+    Archive,
+    Delete,
+}
+
+impl RetentionAction {
+    fn as_str(self) -> &'static str {
+        match self {
+            RetentionAction::Archive => "archive",
+            RetentionAction::Delete => "delete",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "archive" => Some(RetentionAction::Archive),
+            "delete" => Some(RetentionAction::Delete),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionPolicy {
+    /// `None` for the policy covering entries without a project policy.
+    pub project_id: Option<String>,
+    pub older_than_days: i64,
+    pub action: RetentionAction,
+    pub updated_at: i64,
+}
+
+impl RetentionPolicy {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let action: String = row.get(2)?;
+        Ok(Self {
+            project_id: row.get(0)?,
+            older_than_days: row.get(1)?,
+            action: RetentionAction::parse(&action).ok_or_else(|| {
+                rusqlite::Error::FromSqlConversionFailure(2, Type::Text, action.into())
+            })?,
+            updated_at: row.get(3)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunTrigger {
+    /// The background task.
+    Scheduled,
+    /// A user's request.
+    Manual,
+}
+
+impl RunTrigger {
+    fn as_str(self) -> &'static str {
+        match self {
+            RunTrigger::Scheduled => "scheduled",
+            RunTrigger::Manual => "manual",
+        }
+    }
+}
+
+/// An entry a run removed, and how.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RemovedEntry {
+    pub action: RetentionAction,
+    pub entry: TimeEntry,
+}
+
+/// What a run removed, or would remove in a dry run.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RetentionRun {
+    /// `None` for dry runs and runs that removed nothing, which are not
+    /// recorded.
+    pub id: Option<i64>,
+    pub ran_at: i64,
+    pub trigger: RunTrigger,
+    pub dry_run: bool,
+    pub archived: usize,
+    pub deleted: usize,
+    pub entries: Vec<RemovedEntry>,
+}
+
+/// An entry taken out of the live data by a policy.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ArchivedEntry {
+    pub entry: TimeEntry,
+    pub archived_at: i64,
+    /// The run that archived it.
+    pub run_id: i64,
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, index: usize) -> rusqlite::Result<T> {
+    let json: String = row.get(index)?;
+    serde_json::from_str(&json)
+        .map_err(|err| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(err)))
+}
+
+impl Db {
+    /// The user's policies, the one for entries without a project policy
+    /// first.
+    pub fn list_retention_policies(&self, user: UserId) -> rusqlite::Result<Vec<RetentionPolicy>> {
+        list_policies(&self.conn(), user)
+    }
+
+    /// Creates or replaces the policy for `project`, or for entries without
+    /// a project policy when `None`. Refused if the project does not exist.
+    pub fn set_retention_policy(
+        &self,
+        user: UserId,
+        project: Option<&str>,
+        older_than_days: i64,
+        action: RetentionAction,
+    ) -> WriteResult<RetentionPolicy> {
+        let conn = self.conn();
+        if let Some(project) = project {
+            if load_record::<Project>(&conn, user, project)?.is_none() {
+                return Ok(Err(Rejection::NotFound));
+            }
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        conn.execute(
+            "DELETE FROM retention_policies WHERE user_id = ?1 AND project_id IS ?2",
+            params![user, project],
+        )?;
+        conn.execute(
+            "INSERT INTO retention_policies (user_id, project_id, older_than_days, action, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![user, project, older_than_days, action.as_str(), now],
+        )?;
+        Ok(Ok(RetentionPolicy {
+            project_id: project.map(str::to_string),
+            older_than_days,
+            action,
+            updated_at: now,
+        }))
+    }
+
+    /// Removes a policy. Returns false if there was none.
+    pub fn remove_retention_policy(
+        &self,
+        user: UserId,
+        project: Option<&str>,
+    ) -> rusqlite::Result<bool> {
+        let deleted = self.conn().execute(
+            "DELETE FROM retention_policies WHERE user_id = ?1 AND project_id IS ?2",
+            params![user, project],
+        )?;
+        Ok(deleted > 0)
+    }
+
+    /// Users with at least one policy, for the background task.
+    pub fn users_with_retention_policies(&self) -> rusqlite::Result<Vec<UserId>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare("SELECT DISTINCT user_id FROM retention_policies")?;
+        let users = stmt.query_map([], |row| row.get(0))?.collect();
+        users
+    }
+
+    /// Archives or deletes the entries the user's policies say are too old
+    /// at `now`, and records what was removed. A dry run only reports it.
+    pub fn apply_retention(
+        &self,
+        user: UserId,
+        now: i64,
+        trigger: RunTrigger,
+        dry_run: bool,
+    ) -> rusqlite::Result<RetentionRun> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let policies = list_policies(&tx, user)?;
+        let default = policies.iter().find(|policy| policy.project_id.is_none());
+        let by_project: HashMap<&str, &RetentionPolicy> = policies
+            .iter()
+            .filter_map(|policy| Some((policy.project_id.as_deref()?, policy)))
+            .collect();
+
+        let mut entries: Vec<RemovedEntry> =
+            load_records::<TimeEntry>(&tx, "AND end_time IS NOT NULL", [user])?
+                .into_values()
+                .filter_map(|entry| {
+                    let policy = entry
+                        .project_id
+                        .as_deref()
+                        .and_then(|project| by_project.get(project).copied())
+                        .or(default)?;
+                    let cutoff = now - policy.older_than_days * DAY_MS;
+                    (entry.start_time < cutoff).then_some(RemovedEntry {
+                        action: policy.action,
+                        entry,
+                    })
+                })
+                .collect();
+        entries.sort_by(|a, b| {
+            (a.entry.start_time, &a.entry.id).cmp(&(b.entry.start_time, &b.entry.id))
+        });
+
+        let archived = entries
+            .iter()
+            .filter(|removed| removed.action == RetentionAction::Archive)
+            .count();
+        let mut run = RetentionRun {
+            id: None,
+            ran_at: now,
+            trigger,
+            dry_run,
+            archived,
+            deleted: entries.len() - archived,
+            entries,
+        };
+        if dry_run || run.entries.is_empty() {
+            return Ok(run);
+        }
+
+        tx.execute(
+            "INSERT INTO retention_runs (user_id, ran_at, trigger, archived, deleted, entries)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                user,
+                now,
+                trigger.as_str(),
+                run.archived,
+                run.deleted,
+                serde_json::to_string(&run.entries).expect("entries serialize"),
+            ],
+        )?;
+        let run_id = tx.last_insert_rowid();
+        let changed_at = begin_change(&tx, self.actor)?;
+        for removed in &run.entries {
+            let entry = &removed.entry;
+            if removed.action == RetentionAction::Archive {
+                tx.execute(
+                    "INSERT OR REPLACE INTO archived_entries
+                         (user_id, id, entry, start_time, archived_at, run_id)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                    params![
+                        user,
+                        entry.id,
+                        serde_json::to_string(entry).expect("entries serialize"),
+                        entry.start_time,
+                        now,
+                        run_id
+                    ],
+                )?;
+            }
+            remove_record::<TimeEntry>(&tx, user, &entry.id, changed_at)?;
+        }
+        tx.commit()?;
+        run.id = Some(run_id);
+        Ok(run)
+    }
+
+    /// Recorded runs, newest first.
+    pub fn list_retention_runs(
+        &self,
+        user: UserId,
+        limit: i64,
+        offset: i64,
+    ) -> rusqlite::Result<Vec<RetentionRun>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT id, ran_at, trigger, archived, deleted, entries FROM retention_runs
+             WHERE user_id = ?1 ORDER BY ran_at DESC, id DESC LIMIT ?2 OFFSET ?3",
+        )?;
+        let runs = stmt
+            .query_map(params![user, limit, offset], |row| {
+                let trigger: String = row.get(2)?;
+                Ok(RetentionRun {
+                    id: row.get(0)?,
+                    ran_at: row.get(1)?,
+                    trigger: if trigger == RunTrigger::Scheduled.as_str() {
+                        RunTrigger::Scheduled
+                    } else {
+                        RunTrigger::Manual
+                    },
+                    dry_run: false,
+                    archived: row.get(3)?,
+                    deleted: row.get(4)?,
+                    entries: json_column(row, 5)?,
+                })
+            })?
+            .collect();
+        runs
+    }
+
+    /// Archived entries, most recently started first.
+    pub fn list_archived_entries(
+        &self,
+        user: UserId,
+        limit: i64,
+        offset: i64,
+    ) -> rusqlite::Result<Vec<ArchivedEntry>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT entry, archived_at, run_id FROM archived_entries
+             WHERE user_id = ?1 ORDER BY start_time DESC, id DESC LIMIT ?2 OFFSET ?3",
+        )?;
+        let entries = stmt
+            .query_map(params![user, limit, offset], |row| {
+                Ok(ArchivedEntry {
+                    entry: json_column(row, 0)?,
+                    archived_at: row.get(1)?,
+                    run_id: row.get(2)?,
+                })
+            })?
+            .collect();
+        entries
+    }
+}
+
+fn list_policies(conn: &Connection, user: UserId) -> rusqlite::Result<Vec<RetentionPolicy>> {
+    let mut stmt = conn.prepare(
+        "SELECT project_id, older_than_days, action, updated_at FROM retention_policies
+         WHERE user_id = ?1 ORDER BY project_id IS NOT NULL, project_id",
+    )?;
+    let policies = stmt.query_map([user], RetentionPolicy::from_row)?.collect();
+    policies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions};
+
+    const USER: UserId = 1;
+    const NOW: i64 = 100 * DAY_MS;
+
+    fn entry(id: &str, days_ago: i64, project: Option<&str>) -> TimeEntry {
+        let start_time = NOW - days_ago * DAY_MS;
+        TimeEntry {
+            id: id.to_string(),
+            start_time,
+            end_time: Some(start_time + 1_000),
+            project_id: project.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn db() -> Db {
+        let db = Db::open_in_memory().unwrap();
+        let mut changes = Dataset::default();
+        changes.projects.insert(
+            "p1".to_string(),
+            Project {
+                id: "p1".to_string(),
+                name: "Client".to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+        changes.time_entries = [
+            entry("old", 40, None),
+            entry("recent", 10, None),
+            entry("client-old", 40, Some("p1")),
+            entry("client-ancient", 400, Some("p1")),
+        ]
+        .into_iter()
+        .map(|entry| (entry.id.clone(), entry))
+        .collect();
+        db.apply_sync(USER, &changes, &Deletions::default(), 0)
+            .unwrap();
+        db
+    }
+
+    fn ids(run: &RetentionRun) -> Vec<(&str, RetentionAction)> {
+        run.entries
+            .iter()
+            .map(|removed| (removed.entry.id.as_str(), removed.action))
+            .collect()
+    }
+
+    #[test]
+    fn test_project_policies_take_precedence() {
+        let db = db();
+        db.set_retention_policy(USER, None, 30, RetentionAction::Delete)
+            .unwrap()
+            .unwrap();
+        db.set_retention_policy(USER, Some("p1"), 365, RetentionAction::Archive)
+            .unwrap()
+            .unwrap();
+        let missing = db.set_retention_policy(USER, Some("p2"), 1, RetentionAction::Delete);
+        assert_eq!(missing.unwrap(), Err(Rejection::NotFound));
+
+        let preview = db
+            .apply_retention(USER, NOW, RunTrigger::Manual, true)
+            .unwrap();
+        assert_eq!(
+            ids(&preview),
+            [
+                ("client-ancient", RetentionAction::Archive),
+                ("old", RetentionAction::Delete)
+            ]
+        );
+        assert_eq!(preview.id, None);
+        assert!(db.find_entry(USER, "old").unwrap().is_some());
+    }
+
+    #[test]
+    fn test_run_removes_archives_and_records() {
+        let db = db();
+        db.set_retention_policy(USER, None, 30, RetentionAction::Archive)
+            .unwrap()
+            .unwrap();
+
+        let run = db
+            .apply_retention(USER, NOW, RunTrigger::Scheduled, false)
+            .unwrap();
+        assert_eq!(run.archived, 3);
+        assert!(run.id.is_some());
+        assert!(db.find_entry(USER, "old").unwrap().is_none());
+        assert!(db.find_entry(USER, "recent").unwrap().is_some());
+
+        let archived = db.list_archived_entries(USER, 10, 0).unwrap();
+        let archived: Vec<_> = archived.iter().map(|a| a.entry.id.as_str()).collect();
+        assert_eq!(archived, ["old", "client-old", "client-ancient"]);
+        let runs = db.list_retention_runs(USER, 10, 0).unwrap();
+        assert_eq!(runs, [run]);
+
+        let again = db
+            .apply_retention(USER, NOW, RunTrigger::Scheduled, false)
+            .unwrap();
+        assert!(again.entries.is_empty());
+        assert_eq!(db.list_retention_runs(USER, 10, 0).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,1135 @@
+// backend/src/db/sync.rs
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Params, Row, ToSql};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+
+use super::audit;
+use super::timer::stop_superseded_timers;
+use super::work_types::classify_written;
+use super::{Actor, Db, Rejection, UserId};
+use crate::merge::{self, Conflict};
+use crate::model::{Category, Dataset, Deletions, HasId, Project, Records, TimeEntry};
+
+/// What changed on the server between a client's cursor and this sync.
+#[derive(Debug, Default, PartialEq)]
+pub struct SyncDelta {
+    /// Server time of this sync; the client sends it back as `last_synced_at`.
+    pub cursor: i64,
+    pub changes: Dataset,
+    pub deleted: Deletions,
+    /// Fields edited on both sides and how each was resolved.
+    pub conflicts: Vec<Conflict>,
+    /// The client's cursor predates purged tombstones, so `changes` holds the
+    /// full dataset and the client must drop anything it has that is not in it.
+    pub full_sync: bool,
+}
+
+/// Which changes a sync answers with: those stored after `since` and before
+/// `until`.
+#[derive(Clone, Copy)]
+struct Window {
+    since: i64,
+    until: i64,
+}
+
+/// One store a sync touches and whether the client may change it.
+#[derive(Clone, Copy)]
+struct Store {
+    owner: UserId,
+    writable: bool,
+}
+
+/// Which stores a sync reads and writes, and what the client may change.
+///
+/// An account syncs its own store for every kind of record. A workspace
+/// member syncs the workspace's shared projects and categories along with
+/// their own entries in it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SyncScope {
+    pub entries: UserId,
+    pub shared: UserId,
+    pub write_entries: bool,
+    pub write_shared: bool,
+}
+
+impl SyncScope {
+    pub fn personal(user: UserId) -> Self {
+        Self {
+            entries: user,
+            shared: user,
+            write_entries: true,
+            write_shared: true,
+        }
+    }
+}
+
+impl Db {
+    /// Loads every record `user` owns along with a cursor for the next delta
+    /// sync.
+    pub fn snapshot(&self, user: UserId) -> rusqlite::Result<SyncDelta> {
+        self.snapshot_scope(SyncScope::personal(user))
+    }
+
+    /// Loads every record in `scope` along with a cursor for the next delta
+    /// sync.
+    pub fn snapshot_scope(&self, scope: SyncScope) -> rusqlite::Result<SyncDelta> {
+        let conn = self.conn();
+        Ok(SyncDelta {
+            cursor: high_water_mark(&conn)?,
+            changes: Dataset {
+                time_entries: load_records(&conn, "", [scope.entries])?,
+                projects: load_records(&conn, "", [scope.shared])?,
+                categories: load_records(&conn, "", [scope.shared])?,
+            },
+            ..SyncDelta::default()
+        })
+    }
+
+    /// Stores the records a client of `user` changed or deleted and returns
+    /// everything else of theirs that changed after `since`.
+    ///
+    /// Records whose content is identical to what is stored keep their
+    /// version, so resending an unchanged store costs nothing downstream.
+    /// Records edited on both sides are merged as described in [`merge`] and
+    /// returned even though the client just sent them, since its copy is now
+    /// out of date.
+    ///
+    /// Deleted records leave a tombstone behind. An edit to a record that was
+    /// deleted elsewhere loses to the deletion, unless the client sends it
+    /// with version 0, which recreates it.
+    pub fn apply_sync(
+        &self,
+        user: UserId,
+        changes: &Dataset,
+        deleted: &Deletions,
+        since: i64,
+    ) -> rusqlite::Result<SyncDelta> {
+        self.apply_sync_scope(SyncScope::personal(user), changes, deleted, since)
+    }
+
+    /// Like [`Db::apply_sync`], for the stores of `scope`. Changes to records
+    /// the client may not write are dropped, and the stored copies returned
+    /// so the client reverts them.
+    pub fn apply_sync_scope(
+        &self,
+        scope: SyncScope,
+        changes: &Dataset,
+        deleted: &Deletions,
+        since: i64,
+    ) -> rusqlite::Result<SyncDelta> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+
+        let now = begin_change(&tx, self.actor)?;
+
+        let full_sync = since < tombstones_purged_before(&tx)?;
+        // Leave out `now` itself, the client's own writes, unless the client
+        // is replacing its whole store.
+        let window = if full_sync {
+            Window {
+                since: 0,
+                until: now + 1,
+            }
+        } else {
+            Window { since, until: now }
+        };
+
+        let mut delta = SyncDelta {
+            cursor: now,
+            full_sync,
+            ..SyncDelta::default()
+        };
+        let entries = Store {
+            owner: scope.entries,
+            writable: scope.write_entries,
+        };
+        let shared = Store {
+            owner: scope.shared,
+            writable: scope.write_shared,
+        };
+        sync_records(
+            &tx,
+            entries,
+            &changes.time_entries,
+            &deleted.time_entries,
+            now,
+            window,
+            &mut delta,
+        )?;
+        sync_records(
+            &tx,
+            shared,
+            &changes.projects,
+            &deleted.projects,
+            now,
+            window,
+            &mut delta,
+        )?;
+        sync_records(
+            &tx,
+            shared,
+            &changes.categories,
+            &deleted.categories,
+            now,
+            window,
+            &mut delta,
+        )?;
+        for entry in stop_superseded_timers(&tx, scope.entries, now)? {
+            delta.changes.time_entries.insert(entry.id.clone(), entry);
+        }
+        classify_written(&tx, scope.entries)?;
+        if !full_sync {
+            load_tombstones::<TimeEntry>(&tx, scope.entries, window, &mut delta.deleted)?;
+            load_tombstones::<Project>(&tx, scope.shared, window, &mut delta.deleted)?;
+            load_tombstones::<Category>(&tx, scope.shared, window, &mut delta.deleted)?;
+        }
+
+        tx.commit()?;
+        Ok(delta)
+    }
+
+    /// Drops tombstones, and record history no merge can still need, older
+    /// than `cutoff`. Returns how many tombstones were removed.
+    pub fn purge_tombstones(&self, cutoff: i64) -> rusqlite::Result<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let purged = tx.execute("DELETE FROM tombstones WHERE deleted_at < ?1", [cutoff])?;
+        for table in [TimeEntry::NAME, Project::NAME, Category::NAME] {
+            tx.execute(
+                &format!(
+                    "DELETE FROM record_history
+                     WHERE kind = ?1 AND recorded_at < ?2
+                       AND version < (SELECT version FROM {table}
+                                      WHERE user_id = record_history.user_id
+                                        AND id = record_history.id)"
+                ),
+                params![table, cutoff],
+            )?;
+        }
+        tx.execute(
+            "INSERT INTO sync_meta (key, value) VALUES ('tombstones_purged_before', ?1)
+             ON CONFLICT (key) DO UPDATE SET value = MAX(value, excluded.value)",
+            [cutoff],
+        )?;
+        tx.commit()?;
+        Ok(purged)
+    }
+}
+
+/// Maps a record type onto the table that stores it.
+pub(super) trait Table: Clone + Serialize + DeserializeOwned + HasId {
+    /// Table name, also used as the record kind in tombstones and history.
+    const NAME: &'static str;
+    /// Content columns, in the order `from_row` reads and `values` writes
+    /// them. Queries select `id`, these columns, then `version`, and are
+    /// always scoped to one user.
+    const COLUMNS: &'static [&'static str];
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self>;
+    fn values(&self) -> Vec<&dyn ToSql>;
+    /// Where records of this kind go in a sync response.
+    fn collection(dataset: &mut Dataset) -> &mut Records<Self>;
+    fn deletions(deleted: &mut Deletions) -> &mut Vec<String>;
+    fn version(&self) -> i64;
+    fn set_version(&mut self, version: i64);
+
+    fn select() -> String {
+        format!(
+            "SELECT id, {}, version FROM {} WHERE user_id = ?1",
+            Self::COLUMNS.join(", "),
+            Self::NAME
+        )
+    }
+}
+
+impl Table for TimeEntry {
+    const NAME: &'static str = "time_entries";
+    const COLUMNS: &'static [&'static str] = &[
+        "description",
+        "start_time",
+        "end_time",
+        "project_id",
+        "category_id",
+        "billable",
+        "hourly_rate",
+        "currency",
+    ];
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(TimeEntry {
+            id: row.get(0)?,
+            description: row.get(1)?,
+            start_time: row.get(2)?,
+            end_time: row.get(3)?,
+            project_id: row.get(4)?,
+            category_id: row.get(5)?,
+            billable: row.get(6)?,
+            hourly_rate: row.get(7)?,
+            currency: row.get(8)?,
+            version: row.get(9)?,
+        })
+    }
+
+    fn values(&self) -> Vec<&dyn ToSql> {
+        vec![
+            &self.description,
+            &self.start_time,
+            &self.end_time,
+            &self.project_id,
+            &self.category_id,
+            &self.billable,
+            &self.hourly_rate,
+            &self.currency,
+        ]
+    }
+
+    fn collection(dataset: &mut Dataset) -> &mut Records<Self> {
+        &mut dataset.time_entries
+    }
+
+    fn deletions(deleted: &mut Deletions) -> &mut Vec<String> {
+        &mut deleted.time_entries
+    }
+
+    fn version(&self) -> i64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: i64) {
+        self.version = version;
+    }
+}
+
+impl Table for Project {
+    const NAME: &'static str = "projects";
+    const COLUMNS: &'static [&'static str] = &[
+        "name",
+        "color",
+        "archived",
+        "billable",
+        "client",
+        "hourly_rate",
+        "currency",
+    ];
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Project {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            color: row.get(2)?,
+            archived: row.get(3)?,
+            billable: row.get(4)?,
+            client: row.get(5)?,
+            hourly_rate: row.get(6)?,
+            currency: row.get(7)?,
+            version: row.get(8)?,
+        })
+    }
+
+    fn values(&self) -> Vec<&dyn ToSql> {
+        vec![
+            &self.name,
+            &self.color,
+            &self.archived,
+            &self.billable,
+            &self.client,
+            &self.hourly_rate,
+            &self.currency,
+        ]
+    }
+
+    fn collection(dataset: &mut Dataset) -> &mut Records<Self> {
+        &mut dataset.projects
+    }
+
+    fn deletions(deleted: &mut Deletions) -> &mut Vec<String> {
+        &mut deleted.projects
+    }
+
+    fn version(&self) -> i64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: i64) {
+        self.version = version;
+    }
+}
+
+impl Table for Category {
+    const NAME: &'static str = "categories";
+    const COLUMNS: &'static [&'static str] = &["name", "color", "weekly_target_hours"];
+
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(Category {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            color: row.get(2)?,
+            weekly_target_hours: row.get(3)?,
+            version: row.get(4)?,
+        })
+    }
+
+    fn values(&self) -> Vec<&dyn ToSql> {
+        vec![&self.name, &self.color, &self.weekly_target_hours]
+    }
+
+    fn collection(dataset: &mut Dataset) -> &mut Records<Self> {
+        &mut dataset.categories
+    }
+
+    fn deletions(deleted: &mut Deletions) -> &mut Vec<String> {
+        &mut deleted.categories
+    }
+
+    fn version(&self) -> i64 {
+        self.version
+    }
+
+    fn set_version(&mut self, version: i64) {
+        self.version = version;
+    }
+}
+
+/// Applies one collection of a sync and adds the records of that collection
+/// the client has to take back to `delta`.
+fn sync_records<T: Table>(
+    conn: &Connection,
+    store: Store,
+    incoming: &Records<T>,
+    deleted: &[String],
+    now: i64,
+    window: Window,
+    delta: &mut SyncDelta,
+) -> rusqlite::Result<()> {
+    let user = store.owner;
+    if !store.writable {
+        return refuse_records(conn, user, incoming, deleted, window, delta);
+    }
+
+    // Deletions go first so nothing deleted here is handed back below.
+    for id in deleted {
+        delete_record::<T>(conn, user, id, now)?;
+    }
+
+    let mut merged_ids = Vec::new();
+    for record in incoming.values() {
+        if is_tombstoned::<T>(conn, user, record.id())? {
+            if record.version() > 0 {
+                T::deletions(&mut delta.deleted).push(record.id().to_string());
+                continue;
+            }
+            conn.execute(
+                "DELETE FROM tombstones WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+                params![user, T::NAME, record.id()],
+            )?;
+        }
+
+        let current = load_record::<T>(conn, user, record.id())?;
+        let current_version = current.as_ref().map_or(0, T::version);
+        let next = match current {
+            None => Some(record.clone()),
+            Some(current) if record.version() >= current.version() => {
+                (!same_content(record, &current)?).then(|| record.clone())
+            }
+            Some(current) => {
+                let base = load_history::<T>(conn, user, record.id(), record.version())?;
+                let (fields, found) = merge::merge(
+                    T::NAME,
+                    record.id(),
+                    base.as_ref(),
+                    &to_json(&current)?,
+                    &to_json(record)?,
+                );
+                delta.conflicts.extend(found);
+                merged_ids.push(record.id().to_string());
+
+                let mut merged = to_json(&current)?;
+                merged
+                    .as_object_mut()
+                    .expect("records serialize to objects")
+                    .extend(fields);
+                let merged: T = serde_json::from_value(merged).map_err(json_error)?;
+                (!same_content(&merged, &current)?).then_some(merged)
+            }
+        };
+
+        if let Some(mut next) = next {
+            next.set_version(current_version + 1);
+            write_record(conn, user, &next, now)?;
+        }
+    }
+
+    let records = T::collection(&mut delta.changes);
+    records.extend(load_records::<T>(
+        conn,
+        "AND updated_at > ?2 AND updated_at < ?3",
+        params![user, window.since, window.until],
+    )?);
+    for id in merged_ids {
+        if let Some(record) = load_record::<T>(conn, user, &id)? {
+            records.insert(id, record);
+        }
+    }
+    Ok(())
+}
+
+/// Answers changes a client may not make with the stored copies of the
+/// records it sent, or their deletion if they are not stored, along with
+/// the other records that changed in `window`.
+fn refuse_records<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    incoming: &Records<T>,
+    deleted: &[String],
+    window: Window,
+    delta: &mut SyncDelta,
+) -> rusqlite::Result<()> {
+    let mut refused = Vec::new();
+    for id in incoming.keys().chain(deleted) {
+        let Some(stored) = load_record::<T>(conn, user, id)? else {
+            T::deletions(&mut delta.deleted).push(id.clone());
+            continue;
+        };
+        let unchanged = match incoming.get(id) {
+            Some(record) => record.version() == stored.version() && same_content(record, &stored)?,
+            None => false,
+        };
+        if !unchanged {
+            refused.push(stored);
+        }
+    }
+
+    let records = T::collection(&mut delta.changes);
+    records.extend(load_records::<T>(
+        conn,
+        "AND updated_at > ?2 AND updated_at < ?3",
+        params![user, window.since, window.until],
+    )?);
+    for record in refused {
+        records.insert(record.id().to_string(), record);
+    }
+    Ok(())
+}
+
+/// Moves a record to the trash and leaves a tombstone so other devices learn
+/// about it.
+pub(super) fn delete_record<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+    now: i64,
+) -> rusqlite::Result<()> {
+    if let Some(record) = load_record::<T>(conn, user, id)? {
+        conn.execute(
+            "INSERT OR REPLACE INTO trash (user_id, kind, id, data, deleted_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![user, T::NAME, id, to_json(&record)?.to_string(), now],
+        )?;
+    }
+    remove_record::<T>(conn, user, id, now)
+}
+
+/// Removes a record for good, bypassing the trash, and leaves a tombstone.
+pub(super) fn remove_record<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let before = load_record::<T>(conn, user, id)?;
+    audit::record_change(conn, user, before.as_ref(), None, now)?;
+    conn.execute(
+        &format!("DELETE FROM {} WHERE user_id = ?1 AND id = ?2", T::NAME),
+        params![user, id],
+    )?;
+    conn.execute(
+        "DELETE FROM record_history WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+        params![user, T::NAME, id],
+    )?;
+    conn.execute(
+        "INSERT INTO tombstones (user_id, kind, id, deleted_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (user_id, kind, id) DO UPDATE SET deleted_at = excluded.deleted_at",
+        params![user, T::NAME, id, now],
+    )?;
+    Ok(())
+}
+
+pub(super) fn is_tombstoned<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+) -> rusqlite::Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS (
+             SELECT 1 FROM tombstones WHERE user_id = ?1 AND kind = ?2 AND id = ?3)",
+        params![user, T::NAME, id],
+        |row| row.get(0),
+    )
+}
+
+pub(super) fn load_record<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+) -> rusqlite::Result<Option<T>> {
+    conn.query_row(
+        &format!("{} AND id = ?2", T::select()),
+        params![user, id],
+        T::from_row,
+    )
+    .optional()
+}
+
+/// Checks a new record can take `id`. Recreating a deleted record is allowed
+/// and drops its tombstone.
+pub(super) fn claim_id<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+) -> rusqlite::Result<Result<(), Rejection>> {
+    if load_record::<T>(conn, user, id)?.is_some() {
+        return Ok(Err(Rejection::IdTaken));
+    }
+    conn.execute(
+        "DELETE FROM tombstones WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+        params![user, T::NAME, id],
+    )?;
+    Ok(Ok(()))
+}
+
+/// Loads the stored copy of `record`, which must be the version `record`
+/// was based on.
+pub(super) fn load_current<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    record: &T,
+) -> rusqlite::Result<Result<T, Rejection>> {
+    Ok(match load_record::<T>(conn, user, record.id())? {
+        None => Err(Rejection::NotFound),
+        Some(current) if current.version() != record.version() => {
+            Err(Rejection::StaleVersion(current.version()))
+        }
+        Some(current) => Ok(current),
+    })
+}
+
+/// Loads `user`'s records matching `filter`, which continues the query's
+/// `WHERE` clause; `params` must start with the user.
+pub(super) fn load_records<T: Table>(
+    conn: &Connection,
+    filter: &str,
+    params: impl Params,
+) -> rusqlite::Result<Records<T>> {
+    conn.prepare(&format!("{} {}", T::select(), filter))?
+        .query_map(params, |row| {
+            let record = T::from_row(row)?;
+            Ok((record.id().to_string(), record))
+        })?
+        .collect()
+}
+
+/// Upserts `record` as-is and keeps a copy as the base for future merges.
+pub(super) fn write_record<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    record: &T,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let columns = T::COLUMNS;
+    let placeholders = (1..=columns.len() + 4)
+        .map(|i| format!("?{i}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let assignments = columns
+        .iter()
+        .chain(&["version", "updated_at"])
+        .map(|column| format!("{column} = excluded.{column}"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "INSERT INTO {} (user_id, id, {}, version, updated_at) VALUES ({placeholders})
+         ON CONFLICT (user_id, id) DO UPDATE SET {assignments}",
+        T::NAME,
+        columns.join(", "),
+    );
+
+    let id = record.id();
+    let version = record.version();
+    let before = load_record::<T>(conn, user, id)?;
+    audit::record_change(conn, user, before.as_ref(), Some(record), now)?;
+
+    let mut values: Vec<&dyn ToSql> = vec![&user, &id];
+    values.extend(record.values());
+    values.push(&version);
+    values.push(&now);
+    conn.execute(&sql, params_from_iter(values))?;
+
+    conn.execute(
+        "INSERT OR REPLACE INTO record_history (user_id, kind, id, version, data, recorded_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            user,
+            T::NAME,
+            id,
+            version,
+            to_json(record)?.to_string(),
+            now
+        ],
+    )?;
+    Ok(())
+}
+
+/// The record as it was at `version`, if that copy is still kept.
+fn load_history<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+    version: i64,
+) -> rusqlite::Result<Option<Value>> {
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT data FROM record_history
+             WHERE user_id = ?1 AND kind = ?2 AND id = ?3 AND version = ?4",
+            params![user, T::NAME, id, version],
+            |row| row.get(0),
+        )
+        .optional()?;
+    data.map(|data| serde_json::from_str(&data).map_err(json_error))
+        .transpose()
+}
+
+fn same_content<T: Table>(a: &T, b: &T) -> rusqlite::Result<bool> {
+    Ok(merge::content(&to_json(a)?) == merge::content(&to_json(b)?))
+}
+
+pub(super) fn to_json<T: Serialize>(record: &T) -> rusqlite::Result<Value> {
+    serde_json::to_value(record).map_err(json_error)
+}
+
+pub(super) fn json_error(err: serde_json::Error) -> rusqlite::Error {
+    rusqlite::Error::ToSqlConversionFailure(Box::new(err))
+}
+
+/// Starts the changes of one transaction: attributes them to `actor` in the
+/// audit log and returns their timestamp, see [`change_timestamp`].
+pub(super) fn begin_change(conn: &Connection, actor: Option<Actor>) -> rusqlite::Result<i64> {
+    audit::set_context(conn, actor)?;
+    change_timestamp(conn)
+}
+
+/// Timestamp for the changes of one transaction. Every write gets one
+/// strictly newer than anything stored, so cursors stay correct even if two
+/// syncs land in the same millisecond.
+pub(super) fn change_timestamp(conn: &Connection) -> rusqlite::Result<i64> {
+    let latest = high_water_mark(conn)?;
+    Ok(chrono::Utc::now().timestamp_millis().max(latest + 1))
+}
+
+/// The newest change timestamp stored, which is a valid cursor for a
+/// snapshot taken under the same lock.
+fn high_water_mark(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT MAX(
+             (SELECT COALESCE(MAX(updated_at), 0) FROM time_entries),
+             (SELECT COALESCE(MAX(updated_at), 0) FROM projects),
+             (SELECT COALESCE(MAX(updated_at), 0) FROM categories),
+             (SELECT COALESCE(MAX(deleted_at), 0) FROM tombstones),
+             (SELECT COALESCE(MAX(value), 0) FROM sync_meta
+              WHERE key = 'tombstones_purged_before')
+         )",
+        [],
+        |row| row.get(0),
+    )
+}
+
+fn tombstones_purged_before(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(
+             (SELECT value FROM sync_meta WHERE key = 'tombstones_purged_before'), 0)",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Adds the ids of records of kind `T` deleted within `window` to
+/// `deleted`.
+fn load_tombstones<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    window: Window,
+    deleted: &mut Deletions,
+) -> rusqlite::Result<()> {
+    let mut select = conn.prepare(
+        "SELECT id FROM tombstones
+         WHERE user_id = ?1 AND kind = ?2 AND deleted_at > ?3 AND deleted_at < ?4 ORDER BY id",
+    )?;
+    let rows = select.query_map(params![user, T::NAME, window.since, window.until], |row| {
+        row.get::<_, String>(0)
+    })?;
+    let ids = T::deletions(deleted);
+    for id in rows {
+        let id = id?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const USER: UserId = 1;
+
+    fn entry(id: &str, description: &str) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            description: description.to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            project_id: Some("p1".to_string()),
+            category_id: Some("c1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn with_entries(entries: &[TimeEntry]) -> Dataset {
+        let mut dataset = Dataset::default();
+        for entry in entries {
+            dataset.time_entries.insert(entry.id.clone(), entry.clone());
+        }
+        dataset
+    }
+
+    #[test]
+    fn test_apply_sync_round_trips() {
+        let db = Db::open_in_memory().unwrap();
+        let mut dataset = with_entries(&[entry("e1", "Standup")]);
+        dataset.categories.insert(
+            "c1".to_string(),
+            Category {
+                id: "c1".to_string(),
+                name: "Work".to_string(),
+                color: "#10b981".to_string(),
+                weekly_target_hours: Some(40.0),
+                ..Default::default()
+            },
+        );
+
+        db.apply_sync(USER, &dataset, &Deletions::default(), 0)
+            .unwrap();
+        let stored = db.snapshot(USER).unwrap().changes;
+        assert_eq!(stored.time_entries["e1"].version, 1);
+        assert_eq!(stored.categories["c1"].name, "Work");
+    }
+
+    #[test]
+    fn test_apply_sync_returns_only_changes_since_cursor() {
+        let db = Db::open_in_memory().unwrap();
+        let laptop = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        // A sync's own writes are not echoed back to it.
+        assert_eq!(laptop.changes, Dataset::default());
+
+        let phone = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e2", "Review")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        assert_eq!(
+            phone.changes.time_entries.keys().collect::<Vec<_>>(),
+            ["e1"]
+        );
+
+        let laptop = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                laptop.cursor,
+            )
+            .unwrap();
+        assert_eq!(
+            laptop.changes.time_entries.keys().collect::<Vec<_>>(),
+            ["e2"]
+        );
+        assert!(laptop.cursor > phone.cursor);
+    }
+
+    #[test]
+    fn test_apply_sync_ignores_unchanged_records() {
+        let db = Db::open_in_memory().unwrap();
+        let first = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        let resent = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        assert!(resent.changes.time_entries.contains_key("e1"));
+
+        let later = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                first.cursor,
+            )
+            .unwrap();
+        assert!(later.changes.time_entries.is_empty());
+    }
+
+    #[test]
+    fn test_apply_sync_merges_concurrent_edits() {
+        let db = Db::open_in_memory().unwrap();
+        let synced = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        let base = db.snapshot(USER).unwrap().changes.time_entries["e1"].clone();
+
+        // The phone stops the timer...
+        let phone = TimeEntry {
+            end_time: Some(5_000),
+            ..base.clone()
+        };
+        db.apply_sync(
+            USER,
+            &with_entries(&[phone]),
+            &Deletions::default(),
+            synced.cursor,
+        )
+        .unwrap();
+
+        // ...while the laptop, still on the old version, renames the entry.
+        let laptop = TimeEntry {
+            description: "Daily standup".to_string(),
+            ..base
+        };
+        let delta = db
+            .apply_sync(
+                USER,
+                &with_entries(&[laptop]),
+                &Deletions::default(),
+                synced.cursor,
+            )
+            .unwrap();
+
+        assert!(delta.conflicts.is_empty());
+        let merged = &delta.changes.time_entries["e1"];
+        assert_eq!(merged.description, "Daily standup");
+        assert_eq!(merged.end_time, Some(5_000));
+        assert_eq!(merged.version, 3);
+    }
+
+    #[test]
+    fn test_apply_sync_reports_concurrent_stops() {
+        let db = Db::open_in_memory().unwrap();
+        let running = TimeEntry {
+            end_time: None,
+            ..entry("e1", "Standup")
+        };
+        db.apply_sync(USER, &with_entries(&[running]), &Deletions::default(), 0)
+            .unwrap();
+        let base = db.snapshot(USER).unwrap().changes.time_entries["e1"].clone();
+
+        for end_time in [9_000, 7_000] {
+            let stopped = TimeEntry {
+                end_time: Some(end_time),
+                ..base.clone()
+            };
+            db.apply_sync(USER, &with_entries(&[stopped]), &Deletions::default(), 0)
+                .unwrap();
+        }
+
+        let stored = db.snapshot(USER).unwrap().changes.time_entries["e1"].clone();
+        assert_eq!(stored.end_time, Some(7_000));
+
+        let stopped_again = TimeEntry {
+            end_time: Some(8_000),
+            ..base
+        };
+        let delta = db
+            .apply_sync(
+                USER,
+                &with_entries(&[stopped_again]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        assert_eq!(delta.conflicts.len(), 1);
+        assert_eq!(
+            delta.conflicts[0].reason,
+            merge::ConflictReason::ConcurrentStop
+        );
+        assert_eq!(delta.conflicts[0].resolved, 7_000);
+    }
+
+    fn deleting(ids: &[&str]) -> Deletions {
+        Deletions {
+            time_entries: ids.iter().map(|id| id.to_string()).collect(),
+            ..Deletions::default()
+        }
+    }
+
+    #[test]
+    fn test_deletions_propagate_as_tombstones() {
+        let db = Db::open_in_memory().unwrap();
+        let laptop = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup"), entry("e2", "Review")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+
+        let phone = db
+            .apply_sync(USER, &Dataset::default(), &deleting(&["e1"]), laptop.cursor)
+            .unwrap();
+        assert!(phone.deleted.time_entries.is_empty());
+
+        let laptop = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                laptop.cursor,
+            )
+            .unwrap();
+        assert_eq!(laptop.deleted.time_entries, ["e1"]);
+        let stored = db.snapshot(USER).unwrap().changes.time_entries;
+        assert_eq!(stored.keys().collect::<Vec<_>>(), ["e2"]);
+    }
+
+    #[test]
+    fn test_deletion_wins_over_stale_edit() {
+        let db = Db::open_in_memory().unwrap();
+        db.apply_sync(
+            USER,
+            &with_entries(&[entry("e1", "Standup")]),
+            &Deletions::default(),
+            0,
+        )
+        .unwrap();
+        let synced = db.snapshot(USER).unwrap().changes.time_entries["e1"].clone();
+        db.apply_sync(USER, &Dataset::default(), &deleting(&["e1"]), 0)
+            .unwrap();
+
+        let edited = TimeEntry {
+            description: "Renamed".to_string(),
+            ..synced
+        };
+        let delta = db
+            .apply_sync(USER, &with_entries(&[edited]), &Deletions::default(), 0)
+            .unwrap();
+        assert_eq!(delta.deleted.time_entries, ["e1"]);
+        assert!(db.snapshot(USER).unwrap().changes.time_entries.is_empty());
+
+        // Version 0 means the client created it again on purpose.
+        db.apply_sync(
+            USER,
+            &with_entries(&[entry("e1", "Standup")]),
+            &Deletions::default(),
+            0,
+        )
+        .unwrap();
+        assert!(db
+            .snapshot(USER)
+            .unwrap()
+            .changes
+            .time_entries
+            .contains_key("e1"));
+    }
+
+    #[test]
+    fn test_purged_tombstones_force_full_sync_for_stale_cursors() {
+        let db = Db::open_in_memory().unwrap();
+        let stale = db
+            .apply_sync(
+                USER,
+                &with_entries(&[entry("e1", "Standup"), entry("e2", "Review")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        let deleted = db
+            .apply_sync(USER, &Dataset::default(), &deleting(&["e1"]), stale.cursor)
+            .unwrap();
+
+        assert_eq!(db.purge_tombstones(deleted.cursor + 1).unwrap(), 1);
+
+        let delta = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                stale.cursor,
+            )
+            .unwrap();
+        assert!(delta.full_sync);
+        assert!(delta.deleted.time_entries.is_empty());
+        assert_eq!(
+            delta.changes.time_entries.keys().collect::<Vec<_>>(),
+            ["e2"]
+        );
+
+        let fresh = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                delta.cursor,
+            )
+            .unwrap();
+        assert!(!fresh.full_sync);
+    }
+
+    #[test]
+    fn test_users_do_not_see_each_others_records() {
+        let db = Db::open_in_memory().unwrap();
+        db.apply_sync(
+            USER,
+            &with_entries(&[entry("e1", "Mine")]),
+            &Deletions::default(),
+            0,
+        )
+        .unwrap();
+        let other = db
+            .apply_sync(
+                2,
+                &with_entries(&[entry("e1", "Theirs")]),
+                &Deletions::default(),
+                0,
+            )
+            .unwrap();
+        assert!(other.changes.time_entries.is_empty());
+
+        let mine = db.snapshot(USER).unwrap().changes.time_entries;
+        assert_eq!(mine["e1"].description, "Mine");
+        assert_eq!(mine["e1"].version, 1);
+    }
+}
@@ -0,0 +1,198 @@
+// backend/src/db/timer.rs
+//! The running timer: the one time entry per user without an end time.
+//!
+//! The server owns it. Starting a timer stops the running one in the same
+//! transaction, and a sync that leaves several entries running keeps only the
+//! most recently started, so no device can end up with two.
+use rusqlite::Connection;
+
+use super::entries::check_references;
+use super::sync::{begin_change, is_tombstoned, load_record, load_records, write_record};
+use super::work_types::classify_written;
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::TimeEntry;
+
+/// What a timer operation changed.
+#[derive(Debug, Default, PartialEq)]
+pub struct TimerChange {
+    /// The entry now running, if any.
+    pub running: Option<TimeEntry>,
+    /// Entries that were running and have been stopped.
+    pub stopped: Vec<TimeEntry>,
+}
+
+impl Db {
+    /// The entry `user` has running, if any.
+    pub fn running_entry(&self, user: UserId) -> rusqlite::Result<Option<TimeEntry>> {
+        let conn = self.conn();
+        Ok(running_entries(&conn, user)?.pop())
+    }
+
+    /// Stops whatever is running and starts `entry` now. Changes nothing if
+    /// `user` already has or had an entry with its id, or if the project or
+    /// category it refers to does not exist.
+    pub fn start_timer(&self, user: UserId, mut entry: TimeEntry) -> WriteResult<TimerChange> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        if load_record::<TimeEntry>(&tx, user, &entry.id)?.is_some()
+            || is_tombstoned::<TimeEntry>(&tx, user, &entry.id)?
+        {
+            return Ok(Err(Rejection::IdTaken));
+        }
+        if let Err(rejection) = check_references(&tx, user, &entry)? {
+            return Ok(Err(rejection));
+        }
+
+        let now = begin_change(&tx, self.actor)?;
+        let started_at = chrono::Utc::now().timestamp_millis();
+        let stopped = stop_entries(&tx, user, running_entries(&tx, user)?, started_at, now)?;
+
+        entry.start_time = started_at;
+        entry.end_time = None;
+        entry.version = 1;
+        write_record(&tx, user, &entry, now)?;
+        classify_written(&tx, user)?;
+        tx.commit()?;
+        Ok(Ok(TimerChange {
+            running: Some(entry),
+            stopped,
+        }))
+    }
+
+    /// Stops whatever `user` has running.
+    pub fn stop_timer(&self, user: UserId) -> rusqlite::Result<TimerChange> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = begin_change(&tx, self.actor)?;
+        let stopped_at = chrono::Utc::now().timestamp_millis();
+        let stopped = stop_entries(&tx, user, running_entries(&tx, user)?, stopped_at, now)?;
+        tx.commit()?;
+        Ok(TimerChange {
+            running: None,
+            stopped,
+        })
+    }
+}
+
+/// Stops every running entry but the most recently started one, each at the
+/// moment that one started. Returns the entries it stopped.
+pub(super) fn stop_superseded_timers(
+    conn: &Connection,
+    user: UserId,
+    now: i64,
+) -> rusqlite::Result<Vec<TimeEntry>> {
+    let mut running = running_entries(conn, user)?;
+    match running.pop() {
+        Some(newest) => stop_entries(conn, user, running, newest.start_time, now),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// `user`'s running entries, the most recently started last.
+fn running_entries(conn: &Connection, user: UserId) -> rusqlite::Result<Vec<TimeEntry>> {
+    let mut entries: Vec<TimeEntry> =
+        load_records::<TimeEntry>(conn, "AND end_time IS NULL", [user])?
+            .into_values()
+            .collect();
+    entries.sort_by(|a, b| (a.start_time, &a.id).cmp(&(b.start_time, &b.id)));
+    Ok(entries)
+}
+
+fn stop_entries(
+    conn: &Connection,
+    user: UserId,
+    entries: Vec<TimeEntry>,
+    at: i64,
+    now: i64,
+) -> rusqlite::Result<Vec<TimeEntry>> {
+    entries
+        .into_iter()
+        .map(|mut entry| {
+            entry.end_time = Some(at.max(entry.start_time));
+            entry.version += 1;
+            write_record(conn, user, &entry, now)?;
+            Ok(entry)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions};
+
+    const USER: UserId = 1;
+
+    fn entry(id: &str, start_time: i64, end_time: Option<i64>) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            start_time,
+            end_time,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_starting_a_timer_stops_the_running_one() {
+        let db = Db::open_in_memory().unwrap();
+        let first = db.start_timer(USER, entry("t1", 0, None)).unwrap().unwrap();
+        assert!(first.stopped.is_empty());
+
+        let second = db.start_timer(USER, entry("t2", 0, None)).unwrap().unwrap();
+        assert_eq!(second.stopped.len(), 1);
+        assert_eq!(second.stopped[0].id, "t1");
+        assert_eq!(
+            second.stopped[0].end_time,
+            Some(second.running.as_ref().unwrap().start_time)
+        );
+        assert_eq!(db.running_entry(USER).unwrap().unwrap().id, "t2");
+
+        assert_eq!(
+            db.start_timer(USER, entry("t1", 0, None)).unwrap(),
+            Err(Rejection::IdTaken)
+        );
+        let orphan = TimeEntry {
+            project_id: Some("missing".to_string()),
+            ..entry("t3", 0, None)
+        };
+        assert_eq!(
+            db.start_timer(USER, orphan).unwrap(),
+            Err(Rejection::MissingReference("projectId"))
+        );
+        assert_eq!(db.running_entry(USER).unwrap().unwrap().id, "t2");
+    }
+
+    #[test]
+    fn test_stop_timer_ends_the_running_entry() {
+        let db = Db::open_in_memory().unwrap();
+        db.start_timer(USER, entry("t1", 0, None)).unwrap().unwrap();
+
+        let change = db.stop_timer(USER).unwrap();
+        assert_eq!(change.stopped.len(), 1);
+        assert_eq!(change.stopped[0].version, 2);
+        assert_eq!(db.running_entry(USER).unwrap(), None);
+        assert!(db.stop_timer(USER).unwrap().stopped.is_empty());
+    }
+
+    #[test]
+    fn test_sync_keeps_only_the_newest_running_entry() {
+        let db = Db::open_in_memory().unwrap();
+        db.start_timer(USER, entry("server", 0, None))
+            .unwrap()
+            .unwrap();
+        let started = db.running_entry(USER).unwrap().unwrap().start_time;
+
+        let mut changes = Dataset::default();
+        let offline = entry("offline", started - 60_000, None);
+        changes.time_entries.insert(offline.id.clone(), offline);
+        let delta = db
+            .apply_sync(USER, &changes, &Deletions::default(), 0)
+            .unwrap();
+
+        assert_eq!(
+            delta.changes.time_entries["offline"].end_time,
+            Some(started)
+        );
+        assert_eq!(db.running_entry(USER).unwrap().unwrap().id, "server");
+    }
+}
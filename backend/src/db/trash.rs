@@ -0,0 +1,439 @@
+// backend/src/db/trash.rs
+//! Deleted records, kept so they can be restored.
+//!
+//! Every deletion made by a client, whether through sync or the REST
+//! endpoints, copies the record here before removing it. Records deleted in
+//! one transaction share a timestamp and make up one operation, which
+//! [`Db::undo_last_delete`] restores as a whole.
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::entries::check_references;
+use super::sync::{begin_change, json_error, load_record, write_record, Table};
+use super::timer::stop_superseded_timers;
+use super::work_types::classify_written;
+use super::{Db, Rejection, UserId, WriteResult};
+use crate::model::{Category, Project, TimeEntry};
+
+/// Which kind of record a trash item is, named like the sync collections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordKind {
+    TimeEntries,
+    Projects,
+    Categories,
+}
+
+impl RecordKind {
+    /// Restore order: projects and categories first, so the entries that
+    /// refer to them can come back too.
+    const ALL: [RecordKind; 3] = [
+        RecordKind::Projects,
+        RecordKind::Categories,
+        RecordKind::TimeEntries,
+    ];
+
+    pub(super) fn as_str(self) -> &'static str {
+        match self {
+            RecordKind::TimeEntries => TimeEntry::NAME,
+            RecordKind::Projects => Project::NAME,
+            RecordKind::Categories => Category::NAME,
+        }
+    }
+
+    pub(super) fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+}
+
+/// A deleted record as it was just before it was deleted.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TrashedRecord {
+    pub kind: RecordKind,
+    pub id: String,
+    pub deleted_at: i64,
+    pub record: Value,
+}
+
+/// Records taken back out of the trash, with new versions.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Restored {
+    pub time_entries: Vec<TimeEntry>,
+    pub projects: Vec<Project>,
+    pub categories: Vec<Category>,
+    /// Records an undo left in the trash because their id was taken again
+    /// or a project or category they refer to is gone.
+    pub skipped: Vec<TrashedRecord>,
+}
+
+impl Db {
+    /// `user`'s trash, most recently deleted first.
+    pub fn list_trash(
+        &self,
+        user: UserId,
+        kind: Option<RecordKind>,
+        limit: i64,
+        offset: i64,
+    ) -> rusqlite::Result<Vec<TrashedRecord>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT kind, id, deleted_at, data FROM trash
+             WHERE user_id = ?1 AND (?2 IS NULL OR kind = ?2)
+             ORDER BY deleted_at DESC, kind, id LIMIT ?3 OFFSET ?4",
+        )?;
+        let records = stmt
+            .query_map(
+                params![user, kind.map(RecordKind::as_str), limit, offset],
+                trashed_from_row,
+            )?
+            .collect();
+        records
+    }
+
+    /// Puts one record back. Entries can only come back once the project
+    /// and category they refer to exist.
+    pub fn restore_from_trash(
+        &self,
+        user: UserId,
+        kind: RecordKind,
+        id: &str,
+    ) -> WriteResult<Restored> {
+        self.restore_from_trash_in(user, user, kind, id)
+    }
+
+    /// Like [`Db::restore_from_trash`], for a store whose entries refer to
+    /// projects and categories in `shared`, as a workspace member's do.
+    pub fn restore_from_trash_in(
+        &self,
+        user: UserId,
+        shared: UserId,
+        kind: RecordKind,
+        id: &str,
+    ) -> WriteResult<Restored> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = begin_change(&tx, self.actor)?;
+        let mut restored = Restored::default();
+        if let Err(rejection) = restore(&tx, user, shared, kind, id, now, &mut restored)? {
+            return Ok(Err(rejection));
+        }
+        stop_superseded_timers(&tx, user, now)?;
+        classify_written(&tx, user)?;
+        tx.commit()?;
+        Ok(Ok(restored))
+    }
+
+    /// Restores everything the most recent deletion removed, e.g. all
+    /// entries of a prune. Undoing again restores the deletion before it,
+    /// even if some records of this one were skipped; those stay in the
+    /// trash and can still be restored one by one.
+    ///
+    /// Undoing the deletion of a project whose entries were reassigned
+    /// brings the project back but leaves the entries on the project they
+    /// were moved to.
+    pub fn undo_last_delete(&self, user: UserId) -> WriteResult<Restored> {
+        self.undo_last_delete_in(user, user)
+    }
+
+    /// Like [`Db::undo_last_delete`], for a store whose entries refer to
+    /// projects and categories in `shared`.
+    pub fn undo_last_delete_in(&self, user: UserId, shared: UserId) -> WriteResult<Restored> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let last: Option<i64> = tx.query_row(
+            "SELECT MAX(deleted_at) FROM trash WHERE user_id = ?1 AND undone_at IS NULL",
+            [user],
+            |row| row.get(0),
+        )?;
+        let Some(last) = last else {
+            return Ok(Err(Rejection::NotFound));
+        };
+
+        let now = begin_change(&tx, self.actor)?;
+        let mut restored = Restored::default();
+        for kind in RecordKind::ALL {
+            let ids: Vec<String> = tx
+                .prepare(
+                    "SELECT id FROM trash
+                     WHERE user_id = ?1 AND kind = ?2 AND deleted_at = ?3 ORDER BY id",
+                )?
+                .query_map(params![user, kind.as_str(), last], |row| row.get(0))?
+                .collect::<rusqlite::Result<_>>()?;
+            for id in ids {
+                if restore(&tx, user, shared, kind, &id, now, &mut restored)?.is_err() {
+                    restored.skipped.extend(load_trashed(&tx, user, kind, &id)?);
+                }
+            }
+        }
+        tx.execute(
+            "UPDATE trash SET undone_at = ?3 WHERE user_id = ?1 AND deleted_at = ?2",
+            params![user, last, now],
+        )?;
+        stop_superseded_timers(&tx, user, now)?;
+        classify_written(&tx, user)?;
+        tx.commit()?;
+        Ok(Ok(restored))
+    }
+
+    /// Deletes one record from the trash for good. Returns false if it was
+    /// not there.
+    pub fn purge_from_trash(
+        &self,
+        user: UserId,
+        kind: RecordKind,
+        id: &str,
+    ) -> rusqlite::Result<bool> {
+        let purged = self.conn().execute(
+            "DELETE FROM trash WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+            params![user, kind.as_str(), id],
+        )?;
+        Ok(purged > 0)
+    }
+
+    /// Deletes everything in `user`'s trash for good. Returns how many
+    /// records were purged.
+    pub fn empty_trash(&self, user: UserId) -> rusqlite::Result<usize> {
+        self.conn()
+            .execute("DELETE FROM trash WHERE user_id = ?1", [user])
+    }
+
+    /// Purges records of every user deleted before `cutoff`. Returns how
+    /// many were purged.
+    pub fn purge_trash(&self, cutoff: i64) -> rusqlite::Result<usize> {
+        self.conn()
+            .execute("DELETE FROM trash WHERE deleted_at < ?1", [cutoff])
+    }
+}
+
+fn trashed_from_row(row: &rusqlite::Row) -> rusqlite::Result<TrashedRecord> {
+    let kind: String = row.get(0)?;
+    let data: String = row.get(3)?;
+    Ok(TrashedRecord {
+        kind: RecordKind::parse(&kind)
+            .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, kind.into()))?,
+        id: row.get(1)?,
+        deleted_at: row.get(2)?,
+        record: serde_json::from_str(&data)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(3, Type::Text, err.into()))?,
+    })
+}
+
+fn load_trashed(
+    conn: &Connection,
+    user: UserId,
+    kind: RecordKind,
+    id: &str,
+) -> rusqlite::Result<Option<TrashedRecord>> {
+    conn.query_row(
+        "SELECT kind, id, deleted_at, data FROM trash
+         WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+        params![user, kind.as_str(), id],
+        trashed_from_row,
+    )
+    .optional()
+}
+
+/// Restores one record and adds it to `restored`.
+fn restore(
+    conn: &Connection,
+    user: UserId,
+    shared: UserId,
+    kind: RecordKind,
+    id: &str,
+    now: i64,
+    restored: &mut Restored,
+) -> rusqlite::Result<Result<(), Rejection>> {
+    match kind {
+        RecordKind::TimeEntries => {
+            let entry = match take_out::<TimeEntry>(conn, user, id)? {
+                Ok(entry) => entry,
+                Err(rejection) => return Ok(Err(rejection)),
+            };
+            if let Err(rejection) = check_references(conn, shared, &entry)? {
+                return Ok(Err(rejection));
+            }
+            restored
+                .time_entries
+                .push(put_back(conn, user, entry, now)?);
+        }
+        RecordKind::Projects => match take_out::<Project>(conn, user, id)? {
+            Ok(project) => restored.projects.push(put_back(conn, user, project, now)?),
+            Err(rejection) => return Ok(Err(rejection)),
+        },
+        RecordKind::Categories => match take_out::<Category>(conn, user, id)? {
+            Ok(category) => restored
+                .categories
+                .push(put_back(conn, user, category, now)?),
+            Err(rejection) => return Ok(Err(rejection)),
+        },
+    }
+    Ok(Ok(()))
+}
+
+/// The trashed copy of a record, if its id is still free.
+fn take_out<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    id: &str,
+) -> rusqlite::Result<Result<T, Rejection>> {
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT data FROM trash WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+            params![user, T::NAME, id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let Some(data) = data else {
+        return Ok(Err(Rejection::NotFound));
+    };
+    if load_record::<T>(conn, user, id)?.is_some() {
+        return Ok(Err(Rejection::IdTaken));
+    }
+    Ok(Ok(serde_json::from_str(&data).map_err(json_error)?))
+}
+
+/// Stores a trashed record again under a new version, so devices that
+/// dropped it pick it up on their next sync.
+fn put_back<T: Table>(
+    conn: &Connection,
+    user: UserId,
+    mut record: T,
+    now: i64,
+) -> rusqlite::Result<T> {
+    conn.execute(
+        "DELETE FROM trash WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+        params![user, T::NAME, record.id()],
+    )?;
+    conn.execute(
+        "DELETE FROM tombstones WHERE user_id = ?1 AND kind = ?2 AND id = ?3",
+        params![user, T::NAME, record.id()],
+    )?;
+    record.set_version(record.version() + 1);
+    write_record(conn, user, &record, now)?;
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions};
+
+    const USER: UserId = 1;
+
+    fn entry(id: &str) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            description: "Review".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            project_id: Some("p1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn db_with_entries() -> Db {
+        let db = Db::open_in_memory().unwrap();
+        let mut dataset = Dataset::default();
+        dataset.projects.insert(
+            "p1".to_string(),
+            Project {
+                id: "p1".to_string(),
+                name: "Work".to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+        for id in ["e1", "e2"] {
+            dataset.time_entries.insert(id.to_string(), entry(id));
+        }
+        db.apply_sync(USER, &dataset, &Deletions::default(), 0)
+            .unwrap();
+        db
+    }
+
+    #[test]
+    fn test_undo_restores_the_whole_last_deletion() {
+        let db = db_with_entries();
+        assert!(db.delete_entry(USER, "e1").unwrap());
+        let prune = Deletions {
+            time_entries: vec!["e2".to_string()],
+            projects: vec!["p1".to_string()],
+            ..Deletions::default()
+        };
+        let before = db.apply_sync(USER, &Dataset::default(), &prune, 0).unwrap();
+        assert_eq!(db.list_trash(USER, None, 10, 0).unwrap().len(), 3);
+
+        let restored = db.undo_last_delete(USER).unwrap().unwrap();
+        assert_eq!(restored.projects.len(), 1);
+        assert_eq!(restored.time_entries[0].id, "e2");
+        assert_eq!(restored.time_entries[0].version, 2);
+
+        // Other devices see the records come back.
+        let delta = db
+            .apply_sync(
+                USER,
+                &Dataset::default(),
+                &Deletions::default(),
+                before.cursor,
+            )
+            .unwrap();
+        assert!(delta.changes.time_entries.contains_key("e2"));
+        assert!(delta.deleted.time_entries.is_empty());
+
+        let trash = db.list_trash(USER, None, 10, 0).unwrap();
+        assert_eq!(trash.len(), 1);
+        assert_eq!(trash[0].id, "e1");
+    }
+
+    #[test]
+    fn test_restore_needs_a_free_id_and_live_references() {
+        let db = db_with_entries();
+        db.apply_sync(
+            USER,
+            &Dataset::default(),
+            &Deletions {
+                time_entries: vec!["e1".to_string()],
+                projects: vec!["p1".to_string()],
+                ..Deletions::default()
+            },
+            0,
+        )
+        .unwrap();
+        assert_eq!(
+            db.restore_from_trash(USER, RecordKind::TimeEntries, "e1")
+                .unwrap(),
+            Err(Rejection::MissingReference("projectId"))
+        );
+        db.restore_from_trash(USER, RecordKind::Projects, "p1")
+            .unwrap()
+            .unwrap();
+        db.create_entry(USER, entry("e1")).unwrap().unwrap();
+        assert_eq!(
+            db.restore_from_trash(USER, RecordKind::TimeEntries, "e1")
+                .unwrap(),
+            Err(Rejection::IdTaken)
+        );
+
+        assert_eq!(db.purge_trash(i64::MAX).unwrap(), 1);
+        assert_eq!(db.undo_last_delete(USER).unwrap(), Err(Rejection::NotFound));
+    }
+
+    #[test]
+    fn test_skipped_records_do_not_block_older_undos() {
+        let db = db_with_entries();
+        assert!(db.delete_entry(USER, "e1").unwrap());
+        assert!(db.delete_entry(USER, "e2").unwrap());
+        db.create_entry(USER, entry("e2")).unwrap().unwrap();
+
+        let first = db.undo_last_delete(USER).unwrap().unwrap();
+        assert!(first.time_entries.is_empty());
+        assert_eq!(first.skipped[0].id, "e2");
+
+        let second = db.undo_last_delete(USER).unwrap().unwrap();
+        assert_eq!(second.time_entries[0].id, "e1");
+        assert_eq!(db.undo_last_delete(USER).unwrap(), Err(Rejection::NotFound));
+        assert_eq!(db.list_trash(USER, None, 10, 0).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,230 @@
+// backend/src/db/users.rs
+use chrono::Weekday;
+use rusqlite::{params, OptionalExtension, Row};
+use serde::Serialize;
+
+use super::{Db, DeviceId, UserId};
+
+/// Tables whose rows belong to a user.
+const OWNED_TABLES: &[&str] = &[
+    "time_entries",
+    "projects",
+    "categories",
+    "tombstones",
+    "record_history",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    /// IANA time zone days and weeks are reported in.
+    pub timezone: String,
+    pub week_start: Weekday,
+}
+
+impl User {
+    /// Reads a user from the first four columns of `row`, in the order of
+    /// `USER_COLUMNS`.
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        let week_start: u8 = row.get(3)?;
+        Ok(Self {
+            id: row.get(0)?,
+            username: row.get(1)?,
+            timezone: row.get(2)?,
+            week_start: Weekday::try_from(week_start).unwrap_or(Weekday::Mon),
+        })
+    }
+}
+
+const USER_COLUMNS: &str = "users.id, users.username, users.timezone, users.week_start";
+
+impl Db {
+    /// Creates an account, or returns `None` if the username is taken.
+    ///
+    /// The first account also takes over the records stored before accounts
+    /// existed, so a single-user install keeps its data.
+    pub fn create_user(
+        &self,
+        username: &str,
+        password_hash: &str,
+    ) -> rusqlite::Result<Option<User>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let inserted = tx.execute(
+            "INSERT INTO users (username, password_hash, created_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (username) DO NOTHING",
+            params![username, password_hash, now],
+        )?;
+        if inserted == 0 {
+            return Ok(None);
+        }
+
+        let user = User {
+            id: tx.last_insert_rowid(),
+            username: username.to_string(),
+            timezone: "UTC".to_string(),
+            week_start: Weekday::Mon,
+        };
+        let accounts: i64 = tx.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+        if accounts == 1 {
+            for table in OWNED_TABLES {
+                tx.execute(
+                    &format!("UPDATE {table} SET user_id = ?1 WHERE user_id = 0"),
+                    [user.id],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(Some(user))
+    }
+
+    /// Looks up an account and its password hash by username.
+    pub fn find_credentials(&self, username: &str) -> rusqlite::Result<Option<(User, String)>> {
+        self.conn()
+            .query_row(
+                &format!("SELECT {USER_COLUMNS}, password_hash FROM users WHERE username = ?1"),
+                [username],
+                |row| Ok((User::from_row(row)?, row.get(4)?)),
+            )
+            .optional()
+    }
+
+    /// Stores the user's time zone and week start, returning the account as
+    /// updated.
+    pub fn update_user_settings(
+        &self,
+        user: UserId,
+        timezone: &str,
+        week_start: Weekday,
+    ) -> rusqlite::Result<User> {
+        let conn = self.conn();
+        conn.execute(
+            "UPDATE users SET timezone = ?2, week_start = ?3 WHERE id = ?1",
+            params![user, timezone, week_start.num_days_from_monday()],
+        )?;
+        conn.query_row(
+            &format!("SELECT {USER_COLUMNS} FROM users WHERE id = ?1"),
+            [user],
+            User::from_row,
+        )
+    }
+
+    pub fn create_token(
+        &self,
+        user: UserId,
+        device: Option<DeviceId>,
+        token_hash: &str,
+        expires_at: i64,
+    ) -> rusqlite::Result<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn().execute(
+            "INSERT INTO auth_tokens (token_hash, user_id, device_id, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![token_hash, user, device, now, expires_at],
+        )?;
+        Ok(())
+    }
+
+    /// The owner of a token that is neither expired nor revoked, and the
+    /// device it was issued to.
+    pub fn session_for_token(
+        &self,
+        token_hash: &str,
+    ) -> rusqlite::Result<Option<(User, Option<DeviceId>)>> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn()
+            .query_row(
+                &format!(
+                    "SELECT {USER_COLUMNS}, auth_tokens.device_id FROM auth_tokens
+                     JOIN users ON users.id = auth_tokens.user_id
+                     WHERE token_hash = ?1 AND expires_at > ?2 AND revoked_at IS NULL"
+                ),
+                params![token_hash, now],
+                |row| Ok((User::from_row(row)?, row.get(4)?)),
+            )
+            .optional()
+    }
+
+    pub fn revoke_token(&self, token_hash: &str) -> rusqlite::Result<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        self.conn().execute(
+            "UPDATE auth_tokens SET revoked_at = ?2
+             WHERE token_hash = ?1 AND revoked_at IS NULL",
+            params![token_hash, now],
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_usernames_are_unique_ignoring_case() {
+        let db = Db::open_in_memory().unwrap();
+        assert!(db.create_user("Ada", "hash").unwrap().is_some());
+        assert!(db.create_user("ada", "hash").unwrap().is_none());
+
+        let (user, hash) = db.find_credentials("ADA").unwrap().unwrap();
+        assert_eq!(user.username, "Ada");
+        assert_eq!(hash, "hash");
+    }
+
+    #[test]
+    fn test_settings_reach_the_session() {
+        let db = Db::open_in_memory().unwrap();
+        let user = db.create_user("ada", "hash").unwrap().unwrap();
+        let later = chrono::Utc::now().timestamp_millis() + 60_000;
+        db.create_token(user.id, None, "live", later).unwrap();
+
+        let updated = db
+            .update_user_settings(user.id, "Europe/Berlin", Weekday::Sun)
+            .unwrap();
+        assert_eq!(updated.timezone, "Europe/Berlin");
+        let (session, _) = db.session_for_token("live").unwrap().unwrap();
+        assert_eq!(session, updated);
+        assert_eq!(session.week_start, Weekday::Sun);
+    }
+
+    #[test]
+    fn test_revoked_and_expired_tokens_are_rejected() {
+        let db = Db::open_in_memory().unwrap();
+        let user = db.create_user("ada", "hash").unwrap().unwrap();
+        let later = chrono::Utc::now().timestamp_millis() + 60_000;
+
+        db.create_token(user.id, None, "live", later).unwrap();
+        db.create_token(user.id, None, "expired", 0).unwrap();
+        assert_eq!(
+            db.session_for_token("live").unwrap(),
+            Some((user.clone(), None))
+        );
+        assert_eq!(db.session_for_token("expired").unwrap(), None);
+
+        db.revoke_token("live").unwrap();
+        assert_eq!(db.session_for_token("live").unwrap(), None);
+    }
+
+    #[test]
+    fn test_first_account_claims_existing_records() {
+        let db = Db::open_in_memory().unwrap();
+        db.conn()
+            .execute(
+                "INSERT INTO projects (user_id, id, name, color) VALUES (0, 'p1', 'Old', '#000000')",
+                [],
+            )
+            .unwrap();
+
+        let first = db.create_user("ada", "hash").unwrap().unwrap();
+        let second = db.create_user("grace", "hash").unwrap().unwrap();
+        assert!(db
+            .snapshot(first.id)
+            .unwrap()
+            .changes
+            .projects
+            .contains_key("p1"));
+        assert!(db.snapshot(second.id).unwrap().changes.projects.is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+// backend/src/db/work_types.rs
+//! Work-type rules and the work type each entry was classified as.
+//!
+//! Entries are classified as they are written: every write that can add an
+//! entry or change its description classifies the entries that are new or
+//! whose description changed. Rule changes only reach entries classified
+//! before them through `reclassify_entries`.
+use rusqlite::types::Type;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, ToSql};
+use std::collections::HashMap;
+
+use super::{Db, UserId};
+use crate::classify::{Classifier, RuleSet};
+
+impl Db {
+    /// The user's own rules, or `None` while they use the defaults.
+    pub fn work_type_rules(&self, user: UserId) -> rusqlite::Result<Option<RuleSet>> {
+        load_rules(&self.conn(), user)
+    }
+
+    pub fn set_work_type_rules(&self, user: UserId, rules: &RuleSet) -> rusqlite::Result<()> {
+        let now = chrono::Utc::now().timestamp_millis();
+        let rules = serde_json::to_string(rules).expect("rules serialize");
+        self.conn().execute(
+            "INSERT INTO work_type_rules (user_id, rules, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT (user_id) DO UPDATE SET
+                 rules = excluded.rules, updated_at = excluded.updated_at",
+            params![user, rules, now],
+        )?;
+        Ok(())
+    }
+
+    /// Goes back to the default rules. Returns false if the user had none
+    /// of their own.
+    pub fn reset_work_type_rules(&self, user: UserId) -> rusqlite::Result<bool> {
+        let deleted = self
+            .conn()
+            .execute("DELETE FROM work_type_rules WHERE user_id = ?1", [user])?;
+        Ok(deleted > 0)
+    }
+
+    /// Classifies every entry starting in `[from, to)` again, after the rules
+    /// changed. Returns how many entries were classified.
+    pub fn reclassify_entries(
+        &self,
+        user: UserId,
+        classifier: &Classifier,
+        from: Option<i64>,
+        to: Option<i64>,
+    ) -> rusqlite::Result<usize> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let classified = classify(
+            &tx,
+            user,
+            classifier,
+            "AND (?2 IS NULL OR entry.start_time >= ?2) AND (?3 IS NULL OR entry.start_time < ?3)",
+            params![user, from, to],
+        )?;
+        tx.commit()?;
+        Ok(classified)
+    }
+
+    /// Work types by entry id, as last classified.
+    pub fn entry_work_types(&self, user: UserId) -> rusqlite::Result<HashMap<String, String>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!("{WORK_TYPE_SELECT} WHERE work.user_id = ?1"))?;
+        let work_types = stmt
+            .query_map([user], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect();
+        work_types
+    }
+
+    /// Work types of the entries `ids`, by entry id, as last classified.
+    pub fn work_types_of(
+        &self,
+        user: UserId,
+        ids: &[&str],
+    ) -> rusqlite::Result<HashMap<String, String>> {
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let mut values: Vec<&dyn ToSql> = vec![&user];
+        values.extend(ids.iter().map(|id| id as &dyn ToSql));
+        let placeholders: Vec<_> = (2..=values.len()).map(|i| format!("?{i}")).collect();
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "{WORK_TYPE_SELECT} WHERE work.user_id = ?1 AND work.entry_id IN ({})",
+            placeholders.join(", ")
+        ))?;
+        let work_types = stmt
+            .query_map(params_from_iter(values), |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect();
+        work_types
+    }
+
+    /// The work type entry `id` was classified as.
+    pub fn entry_work_type(&self, user: UserId, id: &str) -> rusqlite::Result<Option<String>> {
+        self.conn()
+            .query_row(
+                &format!("{WORK_TYPE_SELECT} WHERE work.user_id = ?1 AND work.entry_id = ?2"),
+                params![user, id],
+                |row| row.get(1),
+            )
+            .optional()
+    }
+}
+
+/// Work types of live entries; those of deleted ones linger until the next
+/// write classifies entries.
+const WORK_TYPE_SELECT: &str = "SELECT work.entry_id, work.work_type FROM entry_work_types work
+     JOIN time_entries entry ON entry.user_id = work.user_id AND entry.id = work.entry_id";
+
+/// Entries that are new or whose description changed since they were
+/// classified.
+const UNCLASSIFIED: &str = "AND (work.entry_id IS NULL OR work.description != entry.description)";
+
+/// Classifies the user's entries that are new or whose description changed,
+/// with their current rules, and forgets deleted ones. Called by every write
+/// that can add an entry or change its description.
+pub(super) fn classify_written(conn: &Connection, user: UserId) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM entry_work_types WHERE user_id = ?1 AND NOT EXISTS (
+             SELECT 1 FROM time_entries
+             WHERE time_entries.user_id = ?1 AND time_entries.id = entry_id
+         )",
+        [user],
+    )?;
+    let pending: bool = conn.query_row(
+        &format!(
+            "SELECT EXISTS (SELECT 1 FROM time_entries entry
+             LEFT JOIN entry_work_types work
+                 ON work.user_id = entry.user_id AND work.entry_id = entry.id
+             WHERE entry.user_id = ?1 {UNCLASSIFIED})"
+        ),
+        [user],
+        |row| row.get(0),
+    )?;
+    if pending {
+        let rules = load_rules(conn, user)?.unwrap_or_default();
+        classify(conn, user, &Classifier::new(&rules), UNCLASSIFIED, [user])?;
+    }
+    Ok(())
+}
+
+fn load_rules(conn: &Connection, user: UserId) -> rusqlite::Result<Option<RuleSet>> {
+    let rules: Option<String> = conn
+        .query_row(
+            "SELECT rules FROM work_type_rules WHERE user_id = ?1",
+            [user],
+            |row| row.get(0),
+        )
+        .optional()?;
+    rules
+        .map(|rules| {
+            serde_json::from_str(&rules).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(0, Type::Text, Box::new(err))
+            })
+        })
+        .transpose()
+}
+
+/// Classifies the user's entries that `filter` selects and stores the result.
+fn classify(
+    conn: &Connection,
+    user: UserId,
+    classifier: &Classifier,
+    filter: &str,
+    params: impl rusqlite::Params,
+) -> rusqlite::Result<usize> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let entries: Vec<(String, String)> = conn
+        .prepare(&format!(
+            "SELECT entry.id, entry.description FROM time_entries entry
+             LEFT JOIN entry_work_types work
+                 ON work.user_id = entry.user_id AND work.entry_id = entry.id
+             WHERE entry.user_id = ?1 {filter}"
+        ))?
+        .query_map(params, |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut upsert = conn.prepare(
+        "INSERT INTO entry_work_types (user_id, entry_id, description, work_type, classified_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT (user_id, entry_id) DO UPDATE SET
+             description = excluded.description,
+             work_type = excluded.work_type,
+             classified_at = excluded.classified_at",
+    )?;
+    for (id, description) in &entries {
+        let work_type = classifier.classify(description);
+        upsert.execute(params![user, id, description, work_type, now])?;
+    }
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::classify::WorkType;
+    use crate::model::TimeEntry;
+
+    const USER: UserId = 1;
+
+    fn entry(id: &str, description: &str) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            description: description.to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_are_classified_as_they_are_written() {
+        let db = Db::open_in_memory().unwrap();
+        db.create_entry(USER, entry("e1", "Standup"))
+            .unwrap()
+            .unwrap();
+        db.create_entry(USER, entry("e2", "Fix bug"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            db.entry_work_type(USER, "e1").unwrap().as_deref(),
+            Some("meeting")
+        );
+
+        let mut edited = db.find_entry(USER, "e2").unwrap().unwrap();
+        edited.description = "Write blog post".to_string();
+        db.update_entry(USER, edited).unwrap().unwrap();
+        assert!(db.delete_entry(USER, "e1").unwrap());
+
+        let work_types = db.entry_work_types(USER).unwrap();
+        assert_eq!(work_types.len(), 1);
+        assert_eq!(work_types["e2"], "content");
+    }
+
+    #[test]
+    fn test_rule_changes_apply_on_reclassify() {
+        let db = Db::open_in_memory().unwrap();
+        db.create_entry(USER, entry("e1", "Standup"))
+            .unwrap()
+            .unwrap();
+
+        let rules = RuleSet {
+            work_types: vec![WorkType {
+                id: "rituals".to_string(),
+                name: "Rituals".to_string(),
+                color: "#000000".to_string(),
+                keywords: vec!["standup".to_string()],
+                patterns: Vec::new(),
+                fuzzy: false,
+            }],
+            fuzzy_threshold: 0.4,
+        };
+        db.set_work_type_rules(USER, &rules).unwrap();
+        let stored = db.work_type_rules(USER).unwrap().unwrap();
+        assert_eq!(stored, rules);
+
+        let classifier = Classifier::new(&stored);
+        db.create_entry(USER, entry("e2", "Standup"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(db.entry_work_types(USER).unwrap()["e1"], "meeting");
+        assert_eq!(db.entry_work_types(USER).unwrap()["e2"], "rituals");
+        assert_eq!(
+            db.reclassify_entries(USER, &classifier, None, None)
+                .unwrap(),
+            2
+        );
+        assert_eq!(db.entry_work_types(USER).unwrap()["e1"], "rituals");
+    }
+}
@@ -0,0 +1,459 @@
+// backend/src/db/workspaces.rs
+//! Workspaces, their members and the stores their records live in.
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+
+use super::sync::SyncScope;
+use super::{Db, Rejection, UserId, WriteResult};
+
+/// Row id of a workspace in the `workspaces` table.
+pub type WorkspaceId = i64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Can only read the shared projects and categories.
+    Viewer,
+    /// Logs their own entries against the shared projects and categories.
+    Member,
+    /// Also manages the shared records and the members, and sees team totals.
+    Admin,
+    /// Also appoints other owners.
+    Owner,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Member => "member",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(Role::Viewer),
+            "member" => Some(Role::Member),
+            "admin" => Some(Role::Admin),
+            "owner" => Some(Role::Owner),
+            _ => None,
+        }
+    }
+
+    pub fn can_log_entries(self) -> bool {
+        self >= Role::Member
+    }
+
+    pub fn is_manager(self) -> bool {
+        self >= Role::Admin
+    }
+}
+
+/// A workspace as one of its members sees it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Workspace {
+    pub id: WorkspaceId,
+    pub name: String,
+    pub role: Role,
+    pub created_at: i64,
+    /// Default rate of the workspace's billable time, for entries and
+    /// projects without a rate of their own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Member {
+    pub user_id: UserId,
+    pub username: String,
+    pub role: Role,
+    pub joined_at: i64,
+}
+
+/// What a member may do in a workspace and where its records are.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Membership {
+    pub role: Role,
+    /// Store of the shared projects and categories.
+    pub shared_store: UserId,
+    /// Store of the member's own entries.
+    pub entry_store: UserId,
+}
+
+impl Membership {
+    pub fn sync_scope(&self) -> SyncScope {
+        SyncScope {
+            entries: self.entry_store,
+            shared: self.shared_store,
+            write_entries: self.role.can_log_entries(),
+            write_shared: self.role.is_manager(),
+        }
+    }
+}
+
+/// The store of one member's entries, for team reports.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MemberStore {
+    pub user_id: UserId,
+    pub username: String,
+    pub store: UserId,
+}
+
+impl Db {
+    /// Creates a workspace owned by `user`.
+    pub fn create_workspace(&self, user: UserId, name: &str) -> rusqlite::Result<Workspace> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let now = chrono::Utc::now().timestamp_millis();
+        let store = new_store(&tx)?;
+        tx.execute(
+            "INSERT INTO workspaces (name, store_id, created_at) VALUES (?1, ?2, ?3)",
+            params![name, store, now],
+        )?;
+        let workspace = Workspace {
+            id: tx.last_insert_rowid(),
+            name: name.to_string(),
+            role: Role::Owner,
+            created_at: now,
+            hourly_rate: None,
+            currency: None,
+        };
+        join(&tx, workspace.id, user, Role::Owner, now)?;
+        tx.commit()?;
+        Ok(workspace)
+    }
+
+    /// The workspaces `user` belongs to, oldest first.
+    pub fn list_workspaces(&self, user: UserId) -> rusqlite::Result<Vec<Workspace>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&format!(
+            "{WORKSPACE_SELECT} WHERE member.user_id = ?1 AND member.left_at IS NULL
+             ORDER BY workspaces.id"
+        ))?;
+        let workspaces = stmt.query_map([user], workspace_from_row)?.collect();
+        workspaces
+    }
+
+    /// `workspace` as `user` sees it, or `None` if they are not a member.
+    pub fn workspace(
+        &self,
+        workspace: WorkspaceId,
+        user: UserId,
+    ) -> rusqlite::Result<Option<Workspace>> {
+        load_workspace(&self.conn(), workspace, user)
+    }
+
+    /// Renames `workspace` and sets its default rate, which is cleared when
+    /// `hourly_rate` and `currency` are `None`.
+    pub fn update_workspace(
+        &self,
+        workspace: WorkspaceId,
+        user: UserId,
+        name: &str,
+        hourly_rate: Option<f64>,
+        currency: Option<&str>,
+    ) -> rusqlite::Result<Option<Workspace>> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "UPDATE workspaces SET name = ?2, hourly_rate = ?3, currency = ?4 WHERE id = ?1",
+            params![workspace, name, hourly_rate, currency],
+        )?;
+        let workspace = load_workspace(&tx, workspace, user)?;
+        tx.commit()?;
+        Ok(workspace)
+    }
+
+    /// `user`'s membership of `workspace`, or `None` if they are not a
+    /// member.
+    pub fn membership(
+        &self,
+        workspace: WorkspaceId,
+        user: UserId,
+    ) -> rusqlite::Result<Option<Membership>> {
+        self.conn()
+            .query_row(
+                "SELECT member.role, workspaces.store_id, member.store_id
+                 FROM workspace_members member
+                 JOIN workspaces ON workspaces.id = member.workspace_id
+                 WHERE member.workspace_id = ?1 AND member.user_id = ?2
+                   AND member.left_at IS NULL",
+                params![workspace, user],
+                |row| {
+                    Ok(Membership {
+                        role: role_at(row, 0)?,
+                        shared_store: -row.get::<_, i64>(1)?,
+                        entry_store: -row.get::<_, i64>(2)?,
+                    })
+                },
+            )
+            .optional()
+    }
+
+    /// Current members, by username.
+    pub fn list_members(&self, workspace: WorkspaceId) -> rusqlite::Result<Vec<Member>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT users.id, users.username, member.role, member.joined_at
+             FROM workspace_members member JOIN users ON users.id = member.user_id
+             WHERE member.workspace_id = ?1 AND member.left_at IS NULL
+             ORDER BY users.username",
+        )?;
+        let members = stmt.query_map([workspace], member_from_row)?.collect();
+        members
+    }
+
+    /// The entry store of everyone who ever was a member, so team totals
+    /// keep the time of members who left.
+    pub fn member_stores(&self, workspace: WorkspaceId) -> rusqlite::Result<Vec<MemberStore>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT users.id, users.username, member.store_id
+             FROM workspace_members member JOIN users ON users.id = member.user_id
+             WHERE member.workspace_id = ?1 ORDER BY users.username",
+        )?;
+        let stores = stmt
+            .query_map([workspace], |row| {
+                Ok(MemberStore {
+                    user_id: row.get(0)?,
+                    username: row.get(1)?,
+                    store: -row.get::<_, i64>(2)?,
+                })
+            })?
+            .collect();
+        stores
+    }
+
+    /// Adds the account named `username`. [`Rejection::NotFound`] if there
+    /// is no such account, [`Rejection::IdTaken`] if it is a member already.
+    pub fn add_member(
+        &self,
+        workspace: WorkspaceId,
+        username: &str,
+        role: Role,
+    ) -> WriteResult<Member> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let user: Option<UserId> = tx
+            .query_row(
+                "SELECT id FROM users WHERE username = ?1",
+                [username],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(user) = user else {
+            return Ok(Err(Rejection::NotFound));
+        };
+        if load_member(&tx, workspace, user)?.is_some() {
+            return Ok(Err(Rejection::IdTaken));
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        join(&tx, workspace, user, role, now)?;
+        let member = load_member(&tx, workspace, user)?.expect("member was just added");
+        tx.commit()?;
+        Ok(Ok(member))
+    }
+
+    /// Changes a member's role. A workspace always keeps an owner, so the
+    /// last one cannot step down.
+    pub fn set_member_role(
+        &self,
+        workspace: WorkspaceId,
+        user: UserId,
+        role: Role,
+    ) -> WriteResult<Member> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let Some(member) = load_member(&tx, workspace, user)? else {
+            return Ok(Err(Rejection::NotFound));
+        };
+        if member.role == Role::Owner && role != Role::Owner && owners(&tx, workspace)? == 1 {
+            return Ok(Err(Rejection::LastOwner));
+        }
+        tx.execute(
+            "UPDATE workspace_members SET role = ?3 WHERE workspace_id = ?1 AND user_id = ?2",
+            params![workspace, user, role.as_str()],
+        )?;
+        tx.commit()?;
+        Ok(Ok(Member { role, ..member }))
+    }
+
+    /// Takes `user` out of the workspace. Their entries stay, and count in
+    /// team totals.
+    pub fn remove_member(&self, workspace: WorkspaceId, user: UserId) -> WriteResult<()> {
+        let mut conn = self.conn();
+        let tx = conn.transaction()?;
+        let Some(member) = load_member(&tx, workspace, user)? else {
+            return Ok(Err(Rejection::NotFound));
+        };
+        if member.role == Role::Owner && owners(&tx, workspace)? == 1 {
+            return Ok(Err(Rejection::LastOwner));
+        }
+        let now = chrono::Utc::now().timestamp_millis();
+        tx.execute(
+            "UPDATE workspace_members SET left_at = ?3 WHERE workspace_id = ?1 AND user_id = ?2",
+            params![workspace, user, now],
+        )?;
+        tx.commit()?;
+        Ok(Ok(()))
+    }
+}
+
+const WORKSPACE_SELECT: &str =
+    "SELECT workspaces.id, workspaces.name, member.role, workspaces.created_at,
+            workspaces.hourly_rate, workspaces.currency
+     FROM workspaces
+     JOIN workspace_members member ON member.workspace_id = workspaces.id";
+
+fn workspace_from_row(row: &Row) -> rusqlite::Result<Workspace> {
+    Ok(Workspace {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        role: role_at(row, 2)?,
+        created_at: row.get(3)?,
+        hourly_rate: row.get(4)?,
+        currency: row.get(5)?,
+    })
+}
+
+fn load_workspace(
+    conn: &Connection,
+    workspace: WorkspaceId,
+    user: UserId,
+) -> rusqlite::Result<Option<Workspace>> {
+    conn.query_row(
+        &format!(
+            "{WORKSPACE_SELECT} WHERE workspaces.id = ?1 AND member.user_id = ?2
+             AND member.left_at IS NULL"
+        ),
+        params![workspace, user],
+        workspace_from_row,
+    )
+    .optional()
+}
+
+fn role_at(row: &Row, index: usize) -> rusqlite::Result<Role> {
+    let role: String = row.get(index)?;
+    Role::parse(&role)
+        .ok_or_else(|| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, role.into()))
+}
+
+fn member_from_row(row: &Row) -> rusqlite::Result<Member> {
+    Ok(Member {
+        user_id: row.get(0)?,
+        username: row.get(1)?,
+        role: role_at(row, 2)?,
+        joined_at: row.get(3)?,
+    })
+}
+
+fn load_member(
+    conn: &Connection,
+    workspace: WorkspaceId,
+    user: UserId,
+) -> rusqlite::Result<Option<Member>> {
+    conn.query_row(
+        "SELECT users.id, users.username, member.role, member.joined_at
+         FROM workspace_members member JOIN users ON users.id = member.user_id
+         WHERE member.workspace_id = ?1 AND member.user_id = ?2 AND member.left_at IS NULL",
+        params![workspace, user],
+        member_from_row,
+    )
+    .optional()
+}
+
+fn owners(conn: &Connection, workspace: WorkspaceId) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COUNT(*) FROM workspace_members
+         WHERE workspace_id = ?1 AND role = 'owner' AND left_at IS NULL",
+        [workspace],
+        |row| row.get(0),
+    )
+}
+
+/// Allocates a store and returns its id in the `stores` table.
+fn new_store(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.execute("INSERT INTO stores DEFAULT VALUES", [])?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Makes `user` a member, reusing their entry store if they were one before.
+fn join(
+    conn: &Connection,
+    workspace: WorkspaceId,
+    user: UserId,
+    role: Role,
+    now: i64,
+) -> rusqlite::Result<()> {
+    let rejoined = conn.execute(
+        "UPDATE workspace_members SET role = ?3, joined_at = ?4, left_at = NULL
+         WHERE workspace_id = ?1 AND user_id = ?2",
+        params![workspace, user, role.as_str(), now],
+    )?;
+    if rejoined == 0 {
+        let store = new_store(conn)?;
+        conn.execute(
+            "INSERT INTO workspace_members (workspace_id, user_id, role, store_id, joined_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![workspace, user, role.as_str(), store, now],
+        )?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_members_get_their_own_entry_store() {
+        let db = Db::open_in_memory().unwrap();
+        let ada = db.create_user("ada", "hash").unwrap().unwrap();
+        let grace = db.create_user("grace", "hash").unwrap().unwrap();
+        let workspace = db.create_workspace(ada.id, "Acme").unwrap();
+        db.add_member(workspace.id, "grace", Role::Member)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            db.add_member(workspace.id, "grace", Role::Viewer).unwrap(),
+            Err(Rejection::IdTaken)
+        );
+
+        let owner = db.membership(workspace.id, ada.id).unwrap().unwrap();
+        let member = db.membership(workspace.id, grace.id).unwrap().unwrap();
+        assert_eq!(owner.shared_store, member.shared_store);
+        assert_ne!(owner.entry_store, member.entry_store);
+        assert!(member.entry_store < 0 && member.shared_store < 0);
+
+        db.remove_member(workspace.id, grace.id).unwrap().unwrap();
+        assert_eq!(db.membership(workspace.id, grace.id).unwrap(), None);
+        assert_eq!(db.member_stores(workspace.id).unwrap().len(), 2);
+        db.add_member(workspace.id, "grace", Role::Viewer)
+            .unwrap()
+            .unwrap();
+        let rejoined = db.membership(workspace.id, grace.id).unwrap().unwrap();
+        assert_eq!(rejoined.entry_store, member.entry_store);
+        assert_eq!(rejoined.role, Role::Viewer);
+    }
+
+    #[test]
+    fn test_last_owner_stays() {
+        let db = Db::open_in_memory().unwrap();
+        let ada = db.create_user("ada", "hash").unwrap().unwrap();
+        let workspace = db.create_workspace(ada.id, "Acme").unwrap();
+        assert_eq!(
+            db.set_member_role(workspace.id, ada.id, Role::Admin)
+                .unwrap(),
+            Err(Rejection::LastOwner)
+        );
+        assert_eq!(
+            db.remove_member(workspace.id, ada.id).unwrap(),
+            Err(Rejection::LastOwner)
+        );
+    }
+}
@@ -0,0 +1,95 @@
+// backend/src/devices.rs
+//! Clients a user has logged in from, listed and revoked through the API.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+
+use crate::auth::AuthUser;
+use crate::db::{Device, DeviceId};
+use crate::error::{ApiError, FieldError};
+use crate::AppState;
+
+const MAX_DEVICE_NAME_LENGTH: usize = 64;
+
+/// A device as listed to its owner.
+#[derive(Debug, Serialize)]
+pub struct DeviceResponse {
+    #[serde(flatten)]
+    pub device: Device,
+    /// Whether this is the device making the request.
+    pub current: bool,
+}
+
+/// Checks a device name given at login.
+pub fn validate_name(name: &str, path: &str, errors: &mut Vec<FieldError>) {
+    let name = name.trim();
+    if name.is_empty() || name.len() > MAX_DEVICE_NAME_LENGTH {
+        errors.push(FieldError::new(
+            path,
+            format!("must be between 1 and {MAX_DEVICE_NAME_LENGTH} characters"),
+        ));
+    }
+}
+
+/// List the user's devices and when each last synced
+pub async fn list_devices(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<DeviceResponse>>, ApiError> {
+    let devices = state
+        .db
+        .list_devices(auth.user.id)?
+        .into_iter()
+        .map(|device| DeviceResponse {
+            current: auth.device == Some(device.id),
+            device,
+        })
+        .collect();
+    Ok(Json(devices))
+}
+
+/// Revoke a device, logging it out everywhere
+pub async fn revoke_device(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<DeviceId>,
+) -> Result<StatusCode, ApiError> {
+    if state.db.revoke_device(auth.user.id, id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound("device"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_list_marks_the_current_device() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let laptop = state.db.create_device(ada.user.id, "Laptop").unwrap();
+        let phone = state.db.create_device(ada.user.id, "Phone").unwrap();
+        let auth = AuthUser {
+            device: Some(phone.id),
+            ..ada
+        };
+
+        let devices = list_devices(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(devices.len(), 2);
+        assert!(!devices[0].current);
+        assert!(devices[1].current);
+
+        revoke_device(State(state.clone()), auth.clone(), Path(laptop.id))
+            .await
+            .unwrap();
+        let missing = revoke_device(State(state), auth, Path(laptop.id)).await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+    }
+}
@@ -0,0 +1,414 @@
+// backend/src/entries.rs
+//! REST endpoints for single time entries, for scripts and integrations that
+//! do not keep a synced store.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::db::{EntryQuery, Rejection};
+use crate::error::{join_path, ApiError, FieldError, ValidJson, ValidQuery};
+use crate::model::{nullable, TimeEntry, Validate};
+use crate::AppState;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+/// Filters for listing entries. `from` and `to` are epoch milliseconds and
+/// match entries that start within `[from, to)`.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ListEntries {
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub project: Option<String>,
+    pub category: Option<String>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for ListEntries {
+    fn default() -> Self {
+        Self {
+            from: None,
+            to: None,
+            project: None,
+            category: None,
+            limit: DEFAULT_PAGE_SIZE,
+            offset: 0,
+        }
+    }
+}
+
+impl Validate for ListEntries {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if to < from {
+                errors.push(FieldError::new(
+                    join_path(path, "to"),
+                    "must not be before from",
+                ));
+            }
+        }
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit) {
+            errors.push(FieldError::new(
+                join_path(path, "limit"),
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if self.offset < 0 {
+            errors.push(FieldError::new(
+                join_path(path, "offset"),
+                "must not be negative",
+            ));
+        }
+    }
+}
+
+/// An entry with the work type it was classified as, which is read-only.
+#[derive(Debug, Serialize)]
+pub struct ClassifiedEntry {
+    #[serde(flatten)]
+    pub entry: TimeEntry,
+    #[serde(rename = "workType", skip_serializing_if = "Option::is_none")]
+    pub work_type: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EntryList {
+    pub entries: Vec<ClassifiedEntry>,
+    /// How many entries match the filters across all pages.
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+/// Fields to change on an entry; absent fields are kept. `endTime`,
+/// `projectId`, `categoryId` and the billing fields can be set to null to
+/// clear them.
+///
+/// If `version` is given the patch only applies to that version.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct EntryPatch {
+    pub description: Option<String>,
+    pub start_time: Option<i64>,
+    #[serde(deserialize_with = "nullable")]
+    pub end_time: Option<Option<i64>>,
+    #[serde(deserialize_with = "nullable")]
+    pub project_id: Option<Option<String>>,
+    #[serde(deserialize_with = "nullable")]
+    pub category_id: Option<Option<String>>,
+    #[serde(deserialize_with = "nullable")]
+    pub billable: Option<Option<bool>>,
+    #[serde(deserialize_with = "nullable")]
+    pub hourly_rate: Option<Option<f64>>,
+    #[serde(deserialize_with = "nullable")]
+    pub currency: Option<Option<String>>,
+    pub version: Option<i64>,
+}
+
+impl Validate for EntryPatch {
+    // Checked once applied, against the whole entry.
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+impl EntryPatch {
+    fn apply(self, entry: &mut TimeEntry) {
+        if let Some(description) = self.description {
+            entry.description = description;
+        }
+        if let Some(start_time) = self.start_time {
+            entry.start_time = start_time;
+        }
+        if let Some(end_time) = self.end_time {
+            entry.end_time = end_time;
+        }
+        if let Some(project_id) = self.project_id {
+            entry.project_id = project_id;
+        }
+        if let Some(category_id) = self.category_id {
+            entry.category_id = category_id;
+        }
+        if let Some(billable) = self.billable {
+            entry.billable = billable;
+        }
+        if let Some(hourly_rate) = self.hourly_rate {
+            entry.hourly_rate = hourly_rate;
+        }
+        if let Some(currency) = self.currency {
+            entry.currency = currency;
+        }
+    }
+}
+
+/// List the user's entries, newest first
+pub async fn list_entries(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ListEntries>,
+) -> Result<Json<EntryList>, ApiError> {
+    let page = state.db.list_entries(
+        auth.user.id,
+        &EntryQuery {
+            from: query.from,
+            to: query.to,
+            project_id: query.project,
+            category_id: query.category,
+            after: None,
+            limit: query.limit,
+            offset: query.offset,
+        },
+    )?;
+    let ids: Vec<&str> = page.entries.iter().map(|entry| entry.id.as_str()).collect();
+    let mut work_types = state.db.work_types_of(auth.user.id, &ids)?;
+    let entries = page
+        .entries
+        .into_iter()
+        .map(|entry| ClassifiedEntry {
+            work_type: work_types.remove(&entry.id),
+            entry,
+        })
+        .collect();
+    Ok(Json(EntryList {
+        entries,
+        total: page.total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Get an entry by id
+pub async fn get_entry(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<ClassifiedEntry>, ApiError> {
+    let entry = state
+        .db
+        .find_entry(auth.user.id, &id)?
+        .ok_or(ApiError::NotFound("time entry"))?;
+    classified(&state, &auth, entry).map(Json)
+}
+
+/// Create an entry
+pub async fn create_entry(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(entry): ValidJson<TimeEntry>,
+) -> Result<(StatusCode, Json<ClassifiedEntry>), ApiError> {
+    let entry = auth
+        .db(&state)
+        .create_entry(auth.user.id, entry)?
+        .map_err(|rejection| ApiError::rejected("time entry", rejection))?;
+    Ok((StatusCode::CREATED, Json(classified(&state, &auth, entry)?)))
+}
+
+/// Change some fields of an entry
+pub async fn patch_entry(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidJson(patch): ValidJson<EntryPatch>,
+) -> Result<Json<ClassifiedEntry>, ApiError> {
+    let mut entry = state
+        .db
+        .find_entry(auth.user.id, &id)?
+        .ok_or(ApiError::NotFound("time entry"))?;
+    if patch
+        .version
+        .is_some_and(|version| version != entry.version)
+    {
+        return Err(ApiError::rejected(
+            "time entry",
+            Rejection::StaleVersion(entry.version),
+        ));
+    }
+    patch.apply(&mut entry);
+
+    let mut errors = Vec::new();
+    entry.validate("", &mut errors);
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let entry = auth
+        .db(&state)
+        .update_entry(auth.user.id, entry)?
+        .map_err(|rejection| ApiError::rejected("time entry", rejection))?;
+    classified(&state, &auth, entry).map(Json)
+}
+
+/// Delete an entry
+pub async fn delete_entry(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    if auth.db(&state).delete_entry(auth.user.id, &id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound("time entry"))
+    }
+}
+
+/// `entry` with the work type it was classified as when it was written.
+fn classified(
+    state: &AppState,
+    auth: &AuthUser,
+    entry: TimeEntry,
+) -> Result<ClassifiedEntry, ApiError> {
+    Ok(ClassifiedEntry {
+        work_type: state.db.entry_work_type(auth.user.id, &entry.id)?,
+        entry,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Project;
+
+    fn setup() -> (AppState, AuthUser) {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let mut changes = crate::model::Dataset::default();
+        changes.projects.insert(
+            "p1".to_string(),
+            Project {
+                id: "p1".to_string(),
+                name: "Work".to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+        state
+            .db
+            .apply_sync(auth.user.id, &changes, &Default::default(), 0)
+            .unwrap();
+        (state, auth)
+    }
+
+    fn entry() -> TimeEntry {
+        TimeEntry {
+            id: "e1".to_string(),
+            description: "Review".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            project_id: Some("p1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_patch_tells_null_from_missing() {
+        let patch: EntryPatch = serde_json::from_str(r#"{"endTime": null}"#).unwrap();
+        assert_eq!(patch.end_time, Some(None));
+        assert_eq!(patch.project_id, None);
+    }
+
+    #[tokio::test]
+    async fn test_patch_validates_the_patched_entry() {
+        let (state, auth) = setup();
+        let _ = create_entry(State(state.clone()), auth.clone(), ValidJson(entry()))
+            .await
+            .unwrap();
+
+        let backwards = EntryPatch {
+            start_time: Some(5_000),
+            ..EntryPatch::default()
+        };
+        let result = patch_entry(
+            State(state.clone()),
+            auth.clone(),
+            Path("e1".to_string()),
+            ValidJson(backwards),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+
+        let clear_project = EntryPatch {
+            project_id: Some(None),
+            version: Some(1),
+            ..EntryPatch::default()
+        };
+        let patched = patch_entry(
+            State(state.clone()),
+            auth.clone(),
+            Path("e1".to_string()),
+            ValidJson(clear_project),
+        )
+        .await
+        .unwrap();
+        assert_eq!(patched.entry.project_id, None);
+        assert_eq!(patched.entry.version, 2);
+
+        let stale = EntryPatch {
+            version: Some(1),
+            ..EntryPatch::default()
+        };
+        let result =
+            patch_entry(State(state), auth, Path("e1".to_string()), ValidJson(stale)).await;
+        assert!(matches!(result, Err(ApiError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_entries_carry_their_work_type() {
+        let (state, auth) = setup();
+        let standup = TimeEntry {
+            description: "Standup".to_string(),
+            ..entry()
+        };
+        let (_, created) = create_entry(State(state.clone()), auth.clone(), ValidJson(standup))
+            .await
+            .unwrap();
+        assert_eq!(created.work_type.as_deref(), Some("meeting"));
+
+        let list = list_entries(
+            State(state.clone()),
+            auth.clone(),
+            ValidQuery(ListEntries::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(list.entries[0].work_type.as_deref(), Some("meeting"));
+
+        let found = get_entry(State(state.clone()), auth.clone(), Path("e1".to_string()))
+            .await
+            .unwrap();
+        let json = serde_json::to_value(&found.0).unwrap();
+        assert_eq!(json["workType"], "meeting");
+        assert_eq!(json["description"], "Standup");
+
+        let rename = EntryPatch {
+            description: Some("Fix bug".to_string()),
+            ..EntryPatch::default()
+        };
+        let patched = patch_entry(
+            State(state),
+            auth,
+            Path("e1".to_string()),
+            ValidJson(rename),
+        )
+        .await
+        .unwrap();
+        assert_eq!(patched.work_type.as_deref(), Some("coding"));
+    }
+
+    #[test]
+    fn test_list_query_limits() {
+        let query = ListEntries {
+            from: Some(10),
+            to: Some(5),
+            limit: 0,
+            ..ListEntries::default()
+        };
+        let mut errors = Vec::new();
+        query.validate("", &mut errors);
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["to", "limit"]);
+    }
+}
@@ -0,0 +1,338 @@
+// backend/src/error.rs
+use axum::{
+    body::Bytes,
+    extract::{FromRequest, FromRequestParts, Query, Request},
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::db::Rejection;
+use crate::model::Validate;
+
+/// A single field that failed to parse or validate.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Errors returned by API handlers, rendered as JSON bodies.
+#[derive(Debug)]
+pub enum ApiError {
+    /// The body was not JSON at all.
+    UnsupportedMediaType,
+    /// The body could not be parsed as JSON.
+    MalformedJson(String),
+    /// The body parsed but one or more fields are invalid.
+    Validation(Vec<FieldError>),
+    /// The request carried no valid credentials.
+    Unauthorized,
+    /// The account may not do this; says what it would take.
+    Forbidden(&'static str),
+    /// The named resource does not exist, or belongs to someone else.
+    NotFound(&'static str),
+    /// The request clashes with existing data, e.g. a taken username.
+    Conflict(String),
+    /// The database failed; details are logged rather than returned.
+    Storage(rusqlite::Error),
+    /// Anything else that went wrong on our side; logged, not returned.
+    Internal(String),
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(err: rusqlite::Error) -> Self {
+        ApiError::Storage(err)
+    }
+}
+
+impl ApiError {
+    /// Explains why a write to a `resource`, e.g. "time entry", was refused.
+    pub fn rejected(resource: &'static str, rejection: Rejection) -> Self {
+        match rejection {
+            Rejection::NotFound => ApiError::NotFound(resource),
+            Rejection::IdTaken => {
+                ApiError::Conflict(format!("a {resource} with this id already exists"))
+            }
+            Rejection::StaleVersion(version) => ApiError::Conflict(format!(
+                "the {resource} changed since it was read; it is now at version {version}"
+            )),
+            Rejection::MissingReference(field) => {
+                ApiError::Validation(vec![FieldError::new(field, "does not exist")])
+            }
+            Rejection::InUse(count) => ApiError::Conflict(format!(
+                "the {resource} is still used by {count} time entries"
+            )),
+            Rejection::LastOwner => {
+                ApiError::Conflict("a workspace must keep at least one owner".to_string())
+            }
+        }
+    }
+}
+
+impl From<tokio::task::JoinError> for ApiError {
+    fn from(err: tokio::task::JoinError) -> Self {
+        ApiError::Internal(err.to_string())
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    error: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "<[FieldError]>::is_empty")]
+    details: &'a [FieldError],
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, error, message, details) = match &self {
+            ApiError::UnsupportedMediaType => (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "unsupported_media_type",
+                "expected a request with `Content-Type: application/json`".to_string(),
+                &[][..],
+            ),
+            ApiError::MalformedJson(message) => (
+                StatusCode::BAD_REQUEST,
+                "malformed_json",
+                message.clone(),
+                &[][..],
+            ),
+            ApiError::Validation(details) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "validation_failed",
+                format!("{} field(s) failed validation", details.len()),
+                &details[..],
+            ),
+            ApiError::Unauthorized => (
+                StatusCode::UNAUTHORIZED,
+                "unauthorized",
+                "a valid bearer token is required".to_string(),
+                &[][..],
+            ),
+            ApiError::Forbidden(reason) => (
+                StatusCode::FORBIDDEN,
+                "forbidden",
+                reason.to_string(),
+                &[][..],
+            ),
+            ApiError::NotFound(resource) => (
+                StatusCode::NOT_FOUND,
+                "not_found",
+                format!("{resource} not found"),
+                &[][..],
+            ),
+            ApiError::Conflict(message) => {
+                (StatusCode::CONFLICT, "conflict", message.clone(), &[][..])
+            }
+            ApiError::Storage(err) => {
+                tracing::error!("Storage error: {}", err);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "storage_error",
+                    "the request could not be completed".to_string(),
+                    &[][..],
+                )
+            }
+            ApiError::Internal(err) => {
+                tracing::error!("Internal error: {}", err);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    "internal_error",
+                    "the request could not be completed".to_string(),
+                    &[][..],
+                )
+            }
+        };
+
+        let body = ErrorBody {
+            error,
+            message,
+            details,
+        };
+        let mut response = (status, Json(body)).into_response();
+        if matches!(self, ApiError::Unauthorized) {
+            response.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static("Bearer"),
+            );
+        }
+        response
+    }
+}
+
+/// JSON extractor that reports the exact field that failed to deserialize
+/// and then runs [`Validate`] on the result.
+pub struct ValidJson<T>(pub T);
+
+impl<S, T> FromRequest<S> for ValidJson<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let is_json = req
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.starts_with("application/json"));
+        if !is_json {
+            return Err(ApiError::UnsupportedMediaType);
+        }
+
+        let bytes = Bytes::from_request(req, state)
+            .await
+            .map_err(|rejection| ApiError::MalformedJson(rejection.body_text()))?;
+        let value: T = parse(&bytes)?;
+
+        let mut errors = Vec::new();
+        value.validate("", &mut errors);
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        Ok(ValidJson(value))
+    }
+}
+
+/// Query string extractor that reports failures like [`ValidJson`] does and
+/// then runs [`Validate`] on the result.
+pub struct ValidQuery<T>(pub T);
+
+impl<S, T> FromRequestParts<S> for ValidQuery<T>
+where
+    S: Send + Sync,
+    T: DeserializeOwned + Validate,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::try_from_uri(&parts.uri).map_err(|rejection| {
+            // The text reads "Failed to deserialize query string: <field>: <problem>".
+            let text = rejection.body_text();
+            let detail = text
+                .split_once(": ")
+                .map_or(text.as_str(), |(_, detail)| detail);
+            let error = match detail.split_once(": ") {
+                Some((field, message)) => FieldError::new(field, message),
+                None => FieldError::new("query", detail),
+            };
+            ApiError::Validation(vec![error])
+        })?;
+
+        let mut errors = Vec::new();
+        value.validate("", &mut errors);
+        if !errors.is_empty() {
+            return Err(ApiError::Validation(errors));
+        }
+
+        Ok(ValidQuery(value))
+    }
+}
+
+fn parse<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, ApiError> {
+    let deserializer = &mut serde_json::Deserializer::from_slice(bytes);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let inner = err.inner();
+        if !inner.is_data() {
+            return ApiError::MalformedJson(inner.to_string());
+        }
+
+        let mut field = err.path().to_string();
+        let mut message = inner.to_string();
+        // The position is meaningless to clients that did not hand-write the body.
+        if let Some(at) = message.rfind(" at line ") {
+            message.truncate(at);
+        }
+        // serde reports a missing field against its parent; point at the field itself.
+        if let Some(missing) = message
+            .strip_prefix("missing field `")
+            .and_then(|rest| rest.split('`').next())
+        {
+            field = match field.as_str() {
+                "." => missing.to_string(),
+                parent => format!("{parent}.{missing}"),
+            };
+        }
+        ApiError::Validation(vec![FieldError::new(field, message)])
+    })
+}
+
+/// Joins a parent path and a field name, leaving out the separator at the root.
+pub fn join_path(parent: &str, field: &str) -> String {
+    if parent.is_empty() {
+        field.to_string()
+    } else {
+        format!("{parent}.{field}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Records, TimeEntry};
+
+    fn field_errors(result: Result<Records<TimeEntry>, ApiError>) -> Vec<FieldError> {
+        match result {
+            Err(ApiError::Validation(errors)) => errors,
+            other => panic!("expected validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_parse_reports_wrong_type_path() {
+        let body = br#"{"e1": {"id": "e1", "description": "", "startTime": "soon"}}"#;
+        let errors = field_errors(parse(body));
+        assert_eq!(errors[0].field, "e1.startTime");
+        assert!(!errors[0].message.contains("line"));
+    }
+
+    #[test]
+    fn test_parse_reports_missing_field_path() {
+        let body = br#"{"e1": {"id": "e1", "startTime": 1}}"#;
+        let errors = field_errors(parse(body));
+        assert_eq!(errors[0].field, "e1.description");
+    }
+
+    #[test]
+    fn test_parse_syntax_error_is_malformed() {
+        let result: Result<Records<TimeEntry>, _> = parse(b"{not json");
+        assert!(matches!(result, Err(ApiError::MalformedJson(_))));
+    }
+
+    #[tokio::test]
+    async fn test_query_errors_name_the_parameter() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Page {
+            #[allow(dead_code)]
+            limit: i64,
+        }
+        impl Validate for Page {
+            fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+        }
+
+        let (mut parts, ()) = Request::builder()
+            .uri("/entries?limit=lots")
+            .body(())
+            .unwrap()
+            .into_parts();
+        let result = ValidQuery::<Page>::from_request_parts(&mut parts, &()).await;
+        match result {
+            Err(ApiError::Validation(errors)) => assert_eq!(errors[0].field, "limit"),
+            _ => panic!("expected a validation error"),
+        }
+    }
+}
@@ -0,0 +1,480 @@
+// backend/src/export.rs
+//! Export of time entries as CSV, JSON or iCalendar, streamed page by page so
+//! large histories never have to be held in memory at once.
+use axum::{
+    body::{Body, Bytes},
+    extract::State,
+    http::header,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, SecondsFormat};
+use futures_util::stream;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::db::{Db, EntryQuery, UserId};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::ics;
+use crate::model::{Project, TimeEntry, Validate};
+use crate::periods::Calendar;
+use crate::AppState;
+
+/// Columns of the CSV export. The first five are the ones the frontend's
+/// `exportTimeEntries` writes, in the same order. The date is the local one
+/// in the user's time zone; the times are UTC. The billing columns after the
+/// project hold the entry's own overrides, then the project's settings.
+const CSV_HEADER: [&str; 13] = [
+    "Date",
+    "Start Time",
+    "End Time",
+    "Category",
+    "Description",
+    "Project",
+    "Billable",
+    "Hourly Rate",
+    "Currency",
+    "Client",
+    "Project Billable",
+    "Project Hourly Rate",
+    "Project Currency",
+];
+
+/// Entries loaded from the database per chunk of the response.
+pub const PAGE_SIZE: i64 = 500;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+    Ics,
+}
+
+/// Which entries to export; the filters match those of `GET /entries`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ExportQuery {
+    pub format: ExportFormat,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub project: Option<String>,
+    pub category: Option<String>,
+}
+
+impl Validate for ExportQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if to < from {
+                errors.push(FieldError::new(
+                    join_path(path, "to"),
+                    "must not be before from",
+                ));
+            }
+        }
+    }
+}
+
+/// An exported entry: the entry itself plus the names it refers to, like
+/// the frontend's JSON export, and how its project is billed so an import
+/// can create the project the same way.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedEntry<'a> {
+    id: &'a str,
+    description: &'a str,
+    start_time: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end_time: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    category_id: Option<&'a str>,
+    project: Option<&'a str>,
+    category: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    billable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hourly_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    currency: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    client: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_billable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_hourly_rate: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_currency: Option<&'a str>,
+}
+
+/// Projects, and category names, by id, archived projects included.
+struct Names {
+    projects: HashMap<String, Project>,
+    categories: HashMap<String, String>,
+}
+
+impl Names {
+    fn load(db: &Db, user: UserId) -> rusqlite::Result<Self> {
+        Ok(Self {
+            projects: db
+                .list_projects(user, true)?
+                .into_iter()
+                .map(|project| (project.id.clone(), project))
+                .collect(),
+            categories: db
+                .list_categories(user)?
+                .into_iter()
+                .map(|category| (category.id, category.name))
+                .collect(),
+        })
+    }
+
+    fn project<'a>(&'a self, entry: &TimeEntry) -> Option<&'a str> {
+        self.project_record(entry)
+            .map(|project| project.name.as_str())
+    }
+
+    fn project_record<'a>(&'a self, entry: &TimeEntry) -> Option<&'a Project> {
+        let id = entry.project_id.as_ref()?;
+        self.projects.get(id)
+    }
+
+    fn category<'a>(&'a self, entry: &TimeEntry) -> Option<&'a str> {
+        let id = entry.category_id.as_ref()?;
+        self.categories.get(id).map(String::as_str)
+    }
+}
+
+/// Where the response stream is.
+enum Stage {
+    Header,
+    Page(EntryQuery),
+    Footer,
+    Done,
+}
+
+/// Export the user's entries, newest first
+pub async fn export(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ExportQuery>,
+) -> Result<Response, ApiError> {
+    let entries = EntryQuery {
+        from: query.from,
+        to: query.to,
+        project_id: query.project,
+        category_id: query.category,
+        after: None,
+        limit: PAGE_SIZE,
+        offset: 0,
+    };
+    let calendar = Calendar::for_user(&auth.user, None);
+    let body = entries_body(&state.db, auth.user.id, calendar, query.format, entries)?;
+
+    let (content_type, filename) = match query.format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "time-entries.csv"),
+        ExportFormat::Json => ("application/json", "time-entries.json"),
+        ExportFormat::Ics => ("text/calendar; charset=utf-8", "time-entries.ics"),
+    };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{filename}\""),
+            ),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// The entries `entries` selects, newest first, as a streamed body in
+/// `format`. `entries.limit` is the page size; pages follow until every
+/// matching entry is written.
+pub fn entries_body(
+    db: &Db,
+    user: UserId,
+    calendar: Calendar,
+    format: ExportFormat,
+    entries: EntryQuery,
+) -> Result<Body, ApiError> {
+    let mut writer = EntryWriter {
+        format,
+        user,
+        calendar,
+        names: Names::load(db, user)?,
+        stamp: chrono::Utc::now().timestamp_millis(),
+        first: true,
+    };
+
+    let db = db.clone();
+    let chunks = stream::unfold(Stage::Header, move |stage| {
+        let chunk = match stage {
+            Stage::Header => Some(Ok((writer.header(), Stage::Page(entries.clone())))),
+            Stage::Page(mut query) => match db.list_entries(user, &query) {
+                Ok(page) => {
+                    let mut chunk = String::new();
+                    for entry in &page.entries {
+                        writer.write(&mut chunk, entry);
+                    }
+                    let next = match page.entries.last() {
+                        Some(last) if page.entries.len() as i64 == query.limit => {
+                            query.after = Some((last.start_time, last.id.clone()));
+                            Stage::Page(query)
+                        }
+                        _ => Stage::Footer,
+                    };
+                    Some(Ok((chunk, next)))
+                }
+                Err(err) => {
+                    tracing::error!("Export failed mid-stream: {}", err);
+                    Some(Err(err))
+                }
+            },
+            Stage::Footer => Some(Ok((writer.footer(), Stage::Done))),
+            Stage::Done => None,
+        };
+        let chunk = chunk.map(|result| match result {
+            Ok((chunk, next)) => (Ok(Bytes::from(chunk)), next),
+            Err(err) => (Err(err), Stage::Done),
+        });
+        async move { chunk }
+    });
+    Ok(Body::from_stream(chunks))
+}
+
+/// Formats entries one at a time.
+struct EntryWriter {
+    format: ExportFormat,
+    user: UserId,
+    /// Dates entries to the user's days.
+    calendar: Calendar,
+    names: Names,
+    /// When the export was generated, for calendar events.
+    stamp: i64,
+    /// Whether no entry has been written yet.
+    first: bool,
+}
+
+impl EntryWriter {
+    fn header(&self) -> String {
+        match self.format {
+            ExportFormat::Csv => csv_record(CSV_HEADER),
+            ExportFormat::Json => "[".to_string(),
+            ExportFormat::Ics => ics::header(),
+        }
+    }
+
+    fn footer(&self) -> String {
+        match self.format {
+            ExportFormat::Csv => String::new(),
+            ExportFormat::Json => "]".to_string(),
+            ExportFormat::Ics => ics::footer(),
+        }
+    }
+
+    fn write(&mut self, out: &mut String, entry: &TimeEntry) {
+        let names = &self.names;
+        match self.format {
+            ExportFormat::Csv => {
+                let date = self.calendar.local_date(entry.start_time);
+                let project = names.project_record(entry);
+                let text = |value: Option<String>| value.unwrap_or_default();
+                out.push_str(&csv_record([
+                    date.format("%Y-%m-%d").to_string().as_str(),
+                    &timestamp(entry.start_time),
+                    &entry.end_time.map(timestamp).unwrap_or_default(),
+                    names.category(entry).unwrap_or_default(),
+                    &entry.description,
+                    names.project(entry).unwrap_or_default(),
+                    &text(entry.billable.map(|billable| billable.to_string())),
+                    &text(entry.hourly_rate.map(|rate| rate.to_string())),
+                    entry.currency.as_deref().unwrap_or_default(),
+                    project
+                        .and_then(|project| project.client.as_deref())
+                        .unwrap_or_default(),
+                    &text(project.map(|project| project.billable.to_string())),
+                    &text(
+                        project
+                            .and_then(|project| project.hourly_rate.map(|rate| rate.to_string())),
+                    ),
+                    project
+                        .and_then(|project| project.currency.as_deref())
+                        .unwrap_or_default(),
+                ]));
+            }
+            ExportFormat::Json => {
+                if !self.first {
+                    out.push(',');
+                }
+                let project = names.project_record(entry);
+                let exported = ExportedEntry {
+                    id: &entry.id,
+                    description: &entry.description,
+                    start_time: entry.start_time,
+                    end_time: entry.end_time,
+                    project_id: entry.project_id.as_deref(),
+                    category_id: entry.category_id.as_deref(),
+                    project: names.project(entry),
+                    category: names.category(entry),
+                    billable: entry.billable,
+                    hourly_rate: entry.hourly_rate,
+                    currency: entry.currency.as_deref(),
+                    client: project.and_then(|project| project.client.as_deref()),
+                    project_billable: project.map(|project| project.billable),
+                    project_hourly_rate: project.and_then(|project| project.hourly_rate),
+                    project_currency: project.and_then(|project| project.currency.as_deref()),
+                };
+                out.push_str(&serde_json::to_string(&exported).expect("entries serialize"));
+            }
+            ExportFormat::Ics => {
+                let Some(end_time) = entry.end_time else {
+                    return;
+                };
+                let categories: Vec<&str> = [names.project(entry), names.category(entry)]
+                    .into_iter()
+                    .flatten()
+                    .collect();
+                let summary = match entry.description.trim() {
+                    "" => categories.first().copied().unwrap_or("Time entry"),
+                    description => description,
+                };
+                let event = ics::Event {
+                    uid: format!("{}@{}.time-tracker", entry.id, self.user),
+                    summary,
+                    start_time: entry.start_time,
+                    end_time,
+                    categories,
+                };
+                ics::write_event(out, &event, self.stamp);
+            }
+        }
+        self.first = false;
+    }
+}
+
+/// Epoch milliseconds as the frontend's `Date.toISOString()` formats them.
+fn timestamp(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .map(|time| time.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .unwrap_or_default()
+}
+
+/// One CSV record per RFC 4180: fields quoted where needed, CRLF-terminated.
+fn csv_record<'a>(fields: impl IntoIterator<Item = &'a str>) -> String {
+    let mut record = fields
+        .into_iter()
+        .map(|field| {
+            if field.contains([',', '"', '\r', '\n']) {
+                format!("\"{}\"", field.replace('"', "\"\""))
+            } else {
+                field.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    record.push_str("\r\n");
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Category;
+
+    #[test]
+    fn test_csv_fields_are_quoted_when_needed() {
+        assert_eq!(
+            csv_record(["plain", "a,b", "say \"hi\"", "two\nlines"]),
+            "plain,\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+    }
+
+    #[test]
+    fn test_timestamps_match_to_iso_string() {
+        assert_eq!(timestamp(1_672_567_200_000), "2023-01-01T10:00:00.000Z");
+    }
+
+    async fn export_body(state: &AppState, auth: &AuthUser, format: ExportFormat) -> String {
+        let query = ExportQuery {
+            format,
+            ..ExportQuery::default()
+        };
+        let response = export(State(state.clone()), auth.clone(), ValidQuery(query))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_export_resolves_names() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let user = auth.user.id;
+        let project = Project {
+            id: "p1".to_string(),
+            name: "Client, Inc.".to_string(),
+            color: "#3b82f6".to_string(),
+            archived: true,
+            ..Default::default()
+        };
+        let category = Category {
+            id: "c1".to_string(),
+            name: "Work".to_string(),
+            color: "#10b981".to_string(),
+            ..Default::default()
+        };
+        state.db.create_project(user, project).unwrap().unwrap();
+        state.db.create_category(user, category).unwrap().unwrap();
+        let entry = TimeEntry {
+            id: "e1".to_string(),
+            description: "Kickoff".to_string(),
+            start_time: 1_672_567_200_000,
+            end_time: Some(1_672_572_600_000),
+            project_id: Some("p1".to_string()),
+            category_id: Some("c1".to_string()),
+            ..Default::default()
+        };
+        state.db.create_entry(user, entry).unwrap().unwrap();
+
+        let csv = export_body(&state, &auth, ExportFormat::Csv).await;
+        assert_eq!(
+            csv,
+            "Date,Start Time,End Time,Category,Description,Project,Billable,Hourly Rate,Currency,\
+             Client,Project Billable,Project Hourly Rate,Project Currency\r\n\
+             2023-01-01,2023-01-01T10:00:00.000Z,2023-01-01T11:30:00.000Z,Work,Kickoff,\
+             \"Client, Inc.\",,,,,false,,\r\n"
+        );
+
+        let json = export_body(&state, &auth, ExportFormat::Json).await;
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed[0]["category"], "Work");
+        assert_eq!(parsed[0]["project"], "Client, Inc.");
+        assert_eq!(parsed[0].get("version"), None);
+    }
+
+    #[tokio::test]
+    async fn test_export_pages_through_every_entry() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let total = PAGE_SIZE as usize + 3;
+        for index in 0..total {
+            let entry = TimeEntry {
+                id: format!("e{index}"),
+                start_time: 1_000 * (index as i64 % 7),
+                ..Default::default()
+            };
+            state.db.create_entry(auth.user.id, entry).unwrap().unwrap();
+        }
+
+        let json = export_body(&state, &auth, ExportFormat::Json).await;
+        let parsed: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.len(), total);
+    }
+}
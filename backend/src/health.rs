@@ -0,0 +1,150 @@
+// backend/src/health.rs
+//! Liveness and readiness checks.
+//!
+//! Liveness (`/health/live`, and `/health` for older probes) only says the
+//! process is up and serving requests. Readiness (`/health/ready`) also checks
+//! that storage answers and its schema is fully migrated, and responds with
+//! 503 until it is, so traffic is held back from an instance that cannot
+//! serve it yet.
+use axum::{extract::State, http::StatusCode, Json};
+use serde::Serialize;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+use crate::db;
+use crate::AppState;
+
+/// When the process started, for reporting uptime.
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+
+/// Version of this build, from Cargo.toml.
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Starts the uptime clock; call once at startup.
+pub fn init() {
+    LazyLock::force(&STARTED);
+}
+
+#[derive(Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub version: &'static str,
+    pub uptime_seconds: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadinessResponse {
+    pub status: String,
+    pub version: &'static str,
+    pub uptime_seconds: u64,
+    /// Resident memory of this process, where the platform exposes it.
+    pub memory_rss_bytes: Option<u64>,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Check {
+    pub name: &'static str,
+    pub ok: bool,
+    pub detail: String,
+}
+
+/// Liveness: the process is up and serving requests.
+pub async fn health() -> Json<HealthResponse> {
+    Json(HealthResponse {
+        status: "OK".to_string(),
+        version: VERSION,
+        uptime_seconds: uptime_seconds(),
+    })
+}
+
+/// Readiness: storage answers and its schema is fully migrated.
+///
+/// Responds with 503 and status `DEGRADED` if any check fails.
+pub async fn ready(State(state): State<AppState>) -> (StatusCode, Json<ReadinessResponse>) {
+    let response = assess(state.db.schema_version());
+    let status = if response.checks.iter().all(|check| check.ok) {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(response))
+}
+
+fn assess(schema_version: rusqlite::Result<usize>) -> ReadinessResponse {
+    let checks = match schema_version {
+        Ok(applied) => vec![
+            Check {
+                name: "storage",
+                ok: true,
+                detail: "database is reachable".to_string(),
+            },
+            Check {
+                name: "migrations",
+                ok: applied == db::SCHEMA_VERSION,
+                detail: format!("{} of {} applied", applied, db::SCHEMA_VERSION),
+            },
+        ],
+        Err(err) => vec![
+            Check {
+                name: "storage",
+                ok: false,
+                detail: err.to_string(),
+            },
+            Check {
+                name: "migrations",
+                ok: false,
+                detail: "unknown, storage is unreachable".to_string(),
+            },
+        ],
+    };
+
+    let healthy = checks.iter().all(|check| check.ok);
+    ReadinessResponse {
+        status: if healthy { "OK" } else { "DEGRADED" }.to_string(),
+        version: VERSION,
+        uptime_seconds: uptime_seconds(),
+        memory_rss_bytes: memory_rss_bytes(),
+        checks,
+    }
+}
+
+fn uptime_seconds() -> u64 {
+    STARTED.elapsed().as_secs()
+}
+
+/// Reads the resident set size from procfs; `None` on other platforms.
+fn memory_rss_bytes() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_ready_when_storage_is_migrated() {
+        let (status, response) = ready(State(AppState::for_tests())).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(response.status, "OK");
+    }
+
+    #[test]
+    fn test_degraded_when_migrations_are_pending() {
+        let response = assess(Ok(db::SCHEMA_VERSION - 1));
+        assert_eq!(response.status, "DEGRADED");
+        let migrations = &response.checks[1];
+        assert!(!migrations.ok);
+        assert!(response.checks[0].ok);
+    }
+
+    #[test]
+    fn test_degraded_when_storage_is_unreachable() {
+        let response = assess(Err(rusqlite::Error::InvalidQuery));
+        assert_eq!(response.status, "DEGRADED");
+        assert!(response.checks.iter().all(|check| !check.ok));
+    }
+}
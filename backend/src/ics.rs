@@ -0,0 +1,154 @@
+// backend/src/ics.rs
+//! iCalendar (RFC 5545) output: each finished time entry becomes a VEVENT.
+//!
+//! Running entries have no end yet and are left out; they appear once
+//! stopped.
+use chrono::DateTime;
+
+/// Identifies this server as the producer of the calendar.
+const PRODUCT_ID: &str = "-//Time Tracker//Time Entries//EN";
+
+/// Longest content line in octets, excluding the CRLF, before it is folded.
+const MAX_LINE_OCTETS: usize = 75;
+
+/// How often subscribed calendar apps are asked to refetch a feed.
+const REFRESH_INTERVAL: &str = "PT1H";
+
+/// A finished entry as a calendar event.
+pub struct Event<'a> {
+    /// Unique across users, and stable so apps update events in place.
+    pub uid: String,
+    pub summary: &'a str,
+    pub start_time: i64,
+    pub end_time: i64,
+    /// Project and category names, shown as the event's categories.
+    pub categories: Vec<&'a str>,
+}
+
+/// Opens a calendar, asking subscribers to refetch it hourly.
+pub fn header() -> String {
+    let mut out = String::new();
+    for line in [
+        "BEGIN:VCALENDAR",
+        "VERSION:2.0",
+        &format!("PRODID:{PRODUCT_ID}"),
+        "CALSCALE:GREGORIAN",
+        "METHOD:PUBLISH",
+        "X-WR-CALNAME:Time tracking",
+        &format!("REFRESH-INTERVAL;VALUE=DURATION:{REFRESH_INTERVAL}"),
+        &format!("X-PUBLISHED-TTL:{REFRESH_INTERVAL}"),
+    ] {
+        push_line(&mut out, line);
+    }
+    out
+}
+
+pub fn footer() -> String {
+    let mut out = String::new();
+    push_line(&mut out, "END:VCALENDAR");
+    out
+}
+
+/// Appends `event` to `out`, stamped with the time the calendar was generated.
+pub fn write_event(out: &mut String, event: &Event, stamp: i64) {
+    let categories = event
+        .categories
+        .iter()
+        .map(|name| escape_text(name))
+        .collect::<Vec<_>>()
+        .join(",");
+    push_line(out, "BEGIN:VEVENT");
+    push_line(out, &format!("UID:{}", escape_text(&event.uid)));
+    push_line(out, &format!("DTSTAMP:{}", date_time(stamp)));
+    push_line(out, &format!("DTSTART:{}", date_time(event.start_time)));
+    push_line(out, &format!("DTEND:{}", date_time(event.end_time)));
+    push_line(out, &format!("SUMMARY:{}", escape_text(event.summary)));
+    if !categories.is_empty() {
+        push_line(out, &format!("CATEGORIES:{categories}"));
+    }
+    push_line(out, "END:VEVENT");
+}
+
+/// Epoch milliseconds as a UTC DATE-TIME, e.g. `20230101T100000Z`.
+fn date_time(millis: i64) -> String {
+    DateTime::from_timestamp_millis(millis)
+        .map(|time| time.format("%Y%m%dT%H%M%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// Escapes a TEXT value: backslashes, separators and line breaks.
+fn escape_text(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Appends a content line, folded so no line exceeds 75 octets and no
+/// character is split across lines.
+fn push_line(out: &mut String, line: &str) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_OCTETS {
+            out.push_str("\r\n ");
+            // The leading space of a continuation line counts towards it.
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_lines() {
+        let mut out = String::new();
+        let event = Event {
+            uid: "e1@1.time-tracker".to_string(),
+            summary: "Plan; then build, test\nship",
+            start_time: 1_672_567_200_000,
+            end_time: 1_672_572_600_000,
+            categories: vec!["Client, Inc.", "Work"],
+        };
+        write_event(&mut out, &event, 1_672_572_600_000);
+
+        assert_eq!(
+            out,
+            "BEGIN:VEVENT\r\n\
+             UID:e1@1.time-tracker\r\n\
+             DTSTAMP:20230101T113000Z\r\n\
+             DTSTART:20230101T100000Z\r\n\
+             DTEND:20230101T113000Z\r\n\
+             SUMMARY:Plan\\; then build\\, test\\nship\r\n\
+             CATEGORIES:Client\\, Inc.,Work\r\n\
+             END:VEVENT\r\n"
+        );
+    }
+
+    #[test]
+    fn test_long_lines_fold_on_character_boundaries() {
+        let mut out = String::new();
+        push_line(&mut out, &format!("SUMMARY:{}", "é".repeat(60)));
+
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.len() <= MAX_LINE_OCTETS));
+        assert!(lines[1].starts_with(' '));
+        assert_eq!(
+            out.replace("\r\n ", ""),
+            format!("SUMMARY:{}\r\n", "é".repeat(60))
+        );
+    }
+}
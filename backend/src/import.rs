@@ -0,0 +1,694 @@
+// backend/src/import.rs
+//! Import of time entries from this app's own exports and from other
+//! trackers' CSV exports.
+//!
+//! Supported layouts:
+//! - `json`: the JSON written by `GET /export` or the frontend's export.
+//! - `csv`: the CSV written by `GET /export` or the frontend's export.
+//! - `toggl`: Toggl Track's detailed report CSV.
+//! - `clockify`: Clockify's detailed report CSV.
+//!
+//! Third-party CSVs carry local wall-clock times without a zone. They are
+//! read in the user's time zone, clock changes included, unless the request
+//! gives a fixed offset from UTC.
+use axum::{body::Bytes, extract::State, Json};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::db::{ImportedEntry, ProjectBilling, Reference, SkippedRow};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::{validate_rate, Validate};
+use crate::periods::Calendar;
+use crate::AppState;
+
+/// Largest file `POST /import` accepts.
+pub const MAX_IMPORT_BYTES: usize = 32 * 1024 * 1024;
+
+/// Widest offset from UTC any zone uses, in minutes.
+const MAX_UTC_OFFSET_MINUTES: i32 = 14 * 60;
+
+const DATE_FORMATS: [&str; 4] = ["%Y-%m-%d", "%m/%d/%Y", "%d.%m.%Y", "%Y/%m/%d"];
+const TIME_FORMATS: [&str; 4] = ["%H:%M:%S", "%H:%M", "%I:%M:%S %p", "%I:%M %p"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Json,
+    Csv,
+    Toggl,
+    Clockify,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ImportQuery {
+    /// Detected from the file when left out.
+    pub format: Option<ImportFormat>,
+    /// Report what would happen without storing anything.
+    pub dry_run: bool,
+    /// Offset of the local times in third-party CSVs from UTC, e.g. 60 for
+    /// CET or -300 for EST, if they are not in the user's time zone.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+impl Validate for ImportQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self
+            .utc_offset_minutes
+            .is_some_and(|minutes| minutes.abs() > MAX_UTC_OFFSET_MINUTES)
+        {
+            errors.push(FieldError::new(
+                join_path(path, "utc_offset_minutes"),
+                format!("must be between -{MAX_UTC_OFFSET_MINUTES} and {MAX_UTC_OFFSET_MINUTES}"),
+            ));
+        }
+    }
+}
+
+/// What the import created, or would create in a dry run, and which rows it
+/// left out. Rows are numbered from 1: array positions for JSON and line
+/// numbers for CSV.
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub format: ImportFormat,
+    pub created_entries: usize,
+    pub created_projects: Vec<String>,
+    pub created_categories: Vec<String>,
+    pub duplicates: Vec<SkippedRow>,
+    pub rejected: Vec<SkippedRow>,
+}
+
+/// An entry as this app exports it to JSON.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedEntry {
+    id: Option<String>,
+    #[serde(default)]
+    description: String,
+    start_time: i64,
+    end_time: Option<i64>,
+    project_id: Option<String>,
+    category_id: Option<String>,
+    project: Option<String>,
+    category: Option<String>,
+    billable: Option<bool>,
+    hourly_rate: Option<f64>,
+    currency: Option<String>,
+    client: Option<String>,
+    project_billable: Option<bool>,
+    project_hourly_rate: Option<f64>,
+    project_currency: Option<String>,
+}
+
+/// Import entries from an export file in the request body
+pub async fn import(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ImportQuery>,
+    body: Bytes,
+) -> Result<Json<ImportReport>, ApiError> {
+    let text = std::str::from_utf8(&body)
+        .map_err(|_| body_error("must be UTF-8 text"))?
+        .trim_start_matches('\u{feff}');
+
+    let format = match query.format {
+        Some(format) => format,
+        None => detect_format(text)?,
+    };
+    let (entries, mut rejected) = match (format, query.utc_offset_minutes) {
+        (ImportFormat::Json, _) => parse_json(text)?,
+        (_, Some(minutes)) => {
+            let offset = FixedOffset::east_opt(minutes * 60).expect("validated offset");
+            parse_csv(text, format, &offset)?
+        }
+        (_, None) => parse_csv(text, format, &Calendar::for_user(&auth.user, None).tz)?,
+    };
+
+    let outcome = auth
+        .db(&state)
+        .import_entries(auth.user.id, entries, query.dry_run)?;
+    rejected.extend(outcome.rejected);
+    rejected.sort_by_key(|row| row.row);
+    Ok(Json(ImportReport {
+        dry_run: query.dry_run,
+        format,
+        created_entries: outcome.created_entries,
+        created_projects: outcome.created_projects,
+        created_categories: outcome.created_categories,
+        duplicates: outcome.duplicates,
+        rejected,
+    }))
+}
+
+fn body_error(message: &str) -> ApiError {
+    ApiError::Validation(vec![FieldError::new("body", message)])
+}
+
+fn detect_format(text: &str) -> Result<ImportFormat, ApiError> {
+    if text.trim_start().starts_with('[') {
+        return Ok(ImportFormat::Json);
+    }
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|err| body_error(&format!("is neither JSON nor CSV: {err}")))?;
+    let has = |name: &str| headers.iter().any(|header| header.trim() == name);
+
+    if has("Start date") && has("Start time") {
+        Ok(ImportFormat::Toggl)
+    } else if has("Start Date") && has("Start Time") {
+        Ok(ImportFormat::Clockify)
+    } else if has("Date") && has("Start Time") && has("End Time") {
+        Ok(ImportFormat::Csv)
+    } else {
+        Err(body_error(
+            "has columns that match no known export; pass `format` to choose one",
+        ))
+    }
+}
+
+fn parse_json(text: &str) -> Result<(Vec<ImportedEntry>, Vec<SkippedRow>), ApiError> {
+    let values: Vec<serde_json::Value> = serde_json::from_str(text)
+        .map_err(|err| body_error(&format!("is not a JSON array: {err}")))?;
+
+    let mut entries = Vec::new();
+    let mut rejected = Vec::new();
+    for (index, value) in values.into_iter().enumerate() {
+        let row = index + 1;
+        let exported: ExportedEntry = match serde_json::from_value(value) {
+            Ok(exported) => exported,
+            Err(err) => {
+                rejected.push(SkippedRow {
+                    row,
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let reference = |id: Option<String>, name: Option<String>| {
+            (id.is_some() || name.is_some()).then_some(Reference { id, name })
+        };
+        let entry = ImportedEntry {
+            row,
+            id: exported.id.filter(|id| !id.trim().is_empty()),
+            description: exported.description,
+            start_time: exported.start_time,
+            end_time: 0,
+            project: reference(exported.project_id, exported.project),
+            category: reference(exported.category_id, exported.category),
+            billable: exported.billable,
+            hourly_rate: exported.hourly_rate,
+            currency: exported.currency,
+            project_billing: exported.project_billable.map(|billable| ProjectBilling {
+                billable,
+                client: exported.client,
+                hourly_rate: exported.project_hourly_rate,
+                currency: exported.project_currency,
+            }),
+        };
+        match finish(entry, Some(exported.start_time), exported.end_time) {
+            Ok(entry) => entries.push(entry),
+            Err(skipped) => rejected.push(skipped),
+        }
+    }
+    Ok((entries, rejected))
+}
+
+/// Column names of one CSV layout.
+struct Columns {
+    description: &'static str,
+    project: &'static str,
+    category: &'static str,
+    start_date: &'static str,
+    start_time: &'static str,
+    end_date: &'static str,
+    end_time: &'static str,
+}
+
+const TOGGL: Columns = Columns {
+    description: "Description",
+    project: "Project",
+    category: "Tags",
+    start_date: "Start date",
+    start_time: "Start time",
+    end_date: "End date",
+    end_time: "End time",
+};
+
+const CLOCKIFY: Columns = Columns {
+    description: "Description",
+    project: "Project",
+    category: "Tags",
+    start_date: "Start Date",
+    start_time: "Start Time",
+    end_date: "End Date",
+    end_time: "End Time",
+};
+
+/// This app's layout keeps full timestamps in the time columns.
+const APP: Columns = Columns {
+    description: "Description",
+    project: "Project",
+    category: "Category",
+    start_date: "Date",
+    start_time: "Start Time",
+    end_date: "Date",
+    end_time: "End Time",
+};
+
+fn parse_csv<Z: TimeZone>(
+    text: &str,
+    format: ImportFormat,
+    zone: &Z,
+) -> Result<(Vec<ImportedEntry>, Vec<SkippedRow>), ApiError> {
+    let columns = match format {
+        ImportFormat::Toggl => &TOGGL,
+        ImportFormat::Clockify => &CLOCKIFY,
+        _ => &APP,
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    let headers: HashMap<String, usize> = reader
+        .headers()
+        .map_err(|err| body_error(&format!("is not valid CSV: {err}")))?
+        .iter()
+        .enumerate()
+        .map(|(index, header)| (header.trim().to_string(), index))
+        .collect();
+    for required in [columns.start_time, columns.end_time] {
+        if !headers.contains_key(required) {
+            return Err(body_error(&format!("has no `{required}` column")));
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut rejected = Vec::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                let row = err
+                    .position()
+                    .map_or(0, |position| line_at(text, position.byte()));
+                rejected.push(SkippedRow {
+                    row,
+                    reason: err.to_string(),
+                });
+                continue;
+            }
+        };
+        let row = record
+            .position()
+            .map_or(0, |position| line_at(text, position.byte()));
+        let field = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|&index| record.get(index))
+                .map(str::trim)
+                .unwrap_or_default()
+        };
+        let named = |name: &str| {
+            let name = match format {
+                // Entries can carry several tags; the first one becomes the category.
+                ImportFormat::Toggl | ImportFormat::Clockify if name == columns.category => {
+                    field(name).split(',').next().unwrap_or_default().trim()
+                }
+                _ => field(name),
+            };
+            (!name.is_empty()).then(|| Reference {
+                id: None,
+                name: Some(name.to_string()),
+            })
+        };
+
+        let billing = match format {
+            ImportFormat::Csv => csv_billing(&field),
+            _ => Ok(Billing::default()),
+        };
+        let billing = match billing {
+            Ok(billing) => billing,
+            Err(reason) => {
+                rejected.push(SkippedRow { row, reason });
+                continue;
+            }
+        };
+        let (start, end) = match format {
+            ImportFormat::Csv => (
+                parse_timestamp(field(columns.start_time)),
+                parse_timestamp(field(columns.end_time)),
+            ),
+            _ => (
+                parse_local(field(columns.start_date), field(columns.start_time), zone),
+                parse_local(field(columns.end_date), field(columns.end_time), zone),
+            ),
+        };
+        let entry = ImportedEntry {
+            row,
+            id: None,
+            description: field(columns.description).to_string(),
+            start_time: 0,
+            end_time: 0,
+            project: named(columns.project),
+            category: named(columns.category),
+            billable: billing.billable,
+            hourly_rate: billing.hourly_rate,
+            currency: billing.currency,
+            project_billing: billing.project,
+        };
+        match finish(entry, start, end) {
+            Ok(entry) => entries.push(entry),
+            Err(skipped) => rejected.push(skipped),
+        }
+    }
+    Ok((entries, rejected))
+}
+
+/// The billing columns of this app's CSV layout. Files written before they
+/// existed leave them out.
+#[derive(Default)]
+struct Billing {
+    billable: Option<bool>,
+    hourly_rate: Option<f64>,
+    currency: Option<String>,
+    project: Option<ProjectBilling>,
+}
+
+fn csv_billing<'a>(field: &impl Fn(&str) -> &'a str) -> Result<Billing, String> {
+    let flag = |name: &str| match field(name) {
+        "" => Ok(None),
+        "true" => Ok(Some(true)),
+        "false" => Ok(Some(false)),
+        value => Err(format!("has `{value}` as {name}, not true or false")),
+    };
+    let rate = |name: &str| match field(name) {
+        "" => Ok(None),
+        value => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("has `{value}` as {name}, not a number")),
+    };
+    let text = |name: &str| Some(field(name).to_string()).filter(|value| !value.is_empty());
+    let project = match flag("Project Billable")? {
+        Some(billable) => Some(ProjectBilling {
+            billable,
+            client: text("Client"),
+            hourly_rate: rate("Project Hourly Rate")?,
+            currency: text("Project Currency"),
+        }),
+        None => None,
+    };
+    Ok(Billing {
+        billable: flag("Billable")?,
+        hourly_rate: rate("Hourly Rate")?,
+        currency: text("Currency"),
+        project,
+    })
+}
+
+/// The 1-based line a record starting at `byte` is on. On CRLF files, which
+/// is what `GET /export` writes, the csv crate places records on the `\n`
+/// ending the previous line, so that byte counts towards the next one.
+fn line_at(text: &str, byte: u64) -> usize {
+    let end = (byte as usize + 1).min(text.len());
+    text.as_bytes()[..end]
+        .iter()
+        .filter(|&&byte| byte == b'\n')
+        .count()
+        + 1
+}
+
+/// Checks the times of a parsed entry and fills them in.
+fn finish(
+    mut entry: ImportedEntry,
+    start: Option<i64>,
+    end: Option<i64>,
+) -> Result<ImportedEntry, SkippedRow> {
+    let reject = |reason: &str| SkippedRow {
+        row: entry.row,
+        reason: reason.to_string(),
+    };
+    let Some(start) = start.filter(|&start| start >= 0) else {
+        return Err(reject("has no valid start time"));
+    };
+    let Some(end) = end else {
+        return Err(reject(
+            "has no valid end time; running entries are not imported",
+        ));
+    };
+    if end < start {
+        return Err(reject("ends before it starts"));
+    }
+    let mut errors = Vec::new();
+    validate_rate(
+        entry.hourly_rate,
+        entry.currency.as_deref(),
+        ("hourly rate", "currency"),
+        &mut errors,
+    );
+    if let Some(project) = &entry.project_billing {
+        validate_rate(
+            project.hourly_rate,
+            project.currency.as_deref(),
+            ("project hourly rate", "project currency"),
+            &mut errors,
+        );
+    }
+    if let Some(error) = errors.first() {
+        return Err(reject(&format!("{} {}", error.field, error.message)));
+    }
+    entry.start_time = start;
+    entry.end_time = end;
+    Ok(entry)
+}
+
+/// An RFC 3339 timestamp as epoch milliseconds.
+fn parse_timestamp(value: &str) -> Option<i64> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|time| time.timestamp_millis())
+}
+
+/// A local date and time in `zone` as epoch milliseconds. Of a time that
+/// occurs twice as the clocks go back, the first is taken; a time skipped as
+/// they go forward does not parse.
+fn parse_local<Z: TimeZone>(date: &str, time: &str, zone: &Z) -> Option<i64> {
+    let date = DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())?;
+    let time = TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveTime::parse_from_str(time, format).ok())?;
+    zone.from_local_datetime(&NaiveDateTime::new(date, time))
+        .earliest()
+        .map(|time| time.timestamp_millis())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UTC: i32 = 0;
+
+    fn offset(minutes: i32) -> FixedOffset {
+        FixedOffset::east_opt(minutes * 60).unwrap()
+    }
+
+    #[test]
+    fn test_detects_each_layout() {
+        let detect = |text: &str| detect_format(text).unwrap();
+        assert_eq!(detect("[]"), ImportFormat::Json);
+        assert_eq!(
+            detect("Date,Start Time,End Time,Category,Description\n"),
+            ImportFormat::Csv
+        );
+        assert_eq!(
+            detect("User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags\n"),
+            ImportFormat::Toggl
+        );
+        assert_eq!(
+            detect("Project,Client,Description,Task,User,Email,Tags,Billable,Start Date,Start Time,End Date,End Time,Duration (h)\n"),
+            ImportFormat::Clockify
+        );
+        assert!(detect_format("a,b,c\n").is_err());
+    }
+
+    #[test]
+    fn test_parses_own_csv_export() {
+        let text = "Date,Start Time,End Time,Category,Description,Project\r\n\
+                    2023-01-01,2023-01-01T10:00:00.000Z,2023-01-01T11:30:00.000Z,Work,\"Plan, then build\",\"Client, Inc.\"\r\n\
+                    2023-01-02,2023-01-02T10:00:00.000Z,,Work,Running,\r\n";
+        let (entries, rejected) = parse_csv(text, ImportFormat::Csv, &offset(UTC)).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].description, "Plan, then build");
+        assert_eq!(entries[0].start_time, 1_672_567_200_000);
+        assert_eq!(entries[0].end_time, 1_672_572_600_000);
+        assert_eq!(
+            entries[0].project.as_ref().unwrap().name.as_deref(),
+            Some("Client, Inc.")
+        );
+        assert_eq!(rejected[0].row, 3);
+    }
+
+    #[test]
+    fn test_parses_clockify_local_times() {
+        let text = "Project,Description,Tags,Start Date,Start Time,End Date,End Time\n\
+                    Website,Design,\"Work, Billable\",01/01/2023,11:00:00 AM,01/01/2023,12:30:00 PM\n";
+        let (entries, rejected) = parse_csv(text, ImportFormat::Clockify, &offset(60)).unwrap();
+
+        assert!(rejected.is_empty());
+        assert_eq!(entries[0].start_time, 1_672_567_200_000);
+        assert_eq!(entries[0].end_time, 1_672_572_600_000);
+        assert_eq!(
+            entries[0].category.as_ref().unwrap().name.as_deref(),
+            Some("Work")
+        );
+    }
+
+    #[test]
+    fn test_local_times_follow_clock_changes() {
+        let berlin = chrono_tz::Europe::Berlin;
+        // Winter time is an hour ahead of UTC, summer time two.
+        assert_eq!(
+            parse_local("2023-01-01", "11:00:00", &berlin),
+            Some(1_672_567_200_000)
+        );
+        assert_eq!(
+            parse_local("2023-07-01", "12:00:00", &berlin),
+            Some(1_688_205_600_000)
+        );
+        assert_eq!(parse_local("2023-03-26", "02:30:00", &berlin), None);
+    }
+
+    #[test]
+    fn test_json_rows_are_rejected_individually() {
+        let text = r#"[
+            {"id": "e1", "description": "Kept", "startTime": 1000, "endTime": 2000, "category": "Work"},
+            {"id": "e2", "description": "No start"},
+            {"id": "e3", "description": "Backwards", "startTime": 2000, "endTime": 1000}
+        ]"#;
+        let (entries, rejected) = parse_json(text).unwrap();
+
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id.as_deref(), Some("e1"));
+        let rows: Vec<_> = rejected.iter().map(|row| row.row).collect();
+        assert_eq!(rows, [2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_import_reports_duplicates_on_second_run() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let body = Bytes::from_static(
+            b"User,Email,Client,Project,Task,Description,Billable,Start date,Start time,End date,End time,Duration,Tags\n\
+              Ada,ada@example.com,,Website,,Design,No,2023-01-01,10:00:00,2023-01-01,11:00:00,01:00:00,\n",
+        );
+        let run = |dry_run| {
+            let query = ImportQuery {
+                dry_run,
+                ..ImportQuery::default()
+            };
+            import(
+                State(state.clone()),
+                auth.clone(),
+                ValidQuery(query),
+                body.clone(),
+            )
+        };
+
+        let preview = run(true).await.unwrap();
+        assert_eq!(preview.format, ImportFormat::Toggl);
+        assert_eq!(preview.created_projects, ["Website"]);
+        assert!(state
+            .db
+            .list_projects(auth.user.id, true)
+            .unwrap()
+            .is_empty());
+
+        let applied = run(false).await.unwrap();
+        assert_eq!(applied.created_entries, 1);
+        let again = run(false).await.unwrap();
+        assert_eq!(again.created_entries, 0);
+        assert_eq!(again.duplicates[0].row, 2);
+    }
+
+    #[tokio::test]
+    async fn test_exports_import_with_their_billing() {
+        use crate::export::{export, ExportFormat, ExportQuery};
+        use crate::model::{Project, TimeEntry};
+
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let project = Project {
+            id: "p1".to_string(),
+            name: "Website".to_string(),
+            color: "#3b82f6".to_string(),
+            billable: true,
+            client: Some("Acme".to_string()),
+            hourly_rate: Some(90.0),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        state
+            .db
+            .create_project(ada.user.id, project)
+            .unwrap()
+            .unwrap();
+        let entry = TimeEntry {
+            id: "e1".to_string(),
+            description: "Design".to_string(),
+            start_time: 1_672_567_200_000,
+            end_time: Some(1_672_572_600_000),
+            project_id: Some("p1".to_string()),
+            billable: Some(false),
+            hourly_rate: Some(120.5),
+            currency: Some("USD".to_string()),
+            ..Default::default()
+        };
+        state.db.create_entry(ada.user.id, entry).unwrap().unwrap();
+
+        for (index, format) in [ExportFormat::Json, ExportFormat::Csv]
+            .into_iter()
+            .enumerate()
+        {
+            let query = ExportQuery {
+                format,
+                ..ExportQuery::default()
+            };
+            let response = export(State(state.clone()), ada.clone(), ValidQuery(query))
+                .await
+                .unwrap();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+
+            let other = AuthUser::for_tests(&state, &format!("user{index}"));
+            let report = import(
+                State(state.clone()),
+                other.clone(),
+                ValidQuery(ImportQuery::default()),
+                body,
+            )
+            .await
+            .unwrap();
+            assert_eq!(report.created_entries, 1, "{format:?}");
+
+            let projects = state.db.list_projects(other.user.id, true).unwrap();
+            assert!(projects[0].billable);
+            assert_eq!(projects[0].client.as_deref(), Some("Acme"));
+            assert_eq!(projects[0].hourly_rate, Some(90.0));
+            assert_eq!(projects[0].currency.as_deref(), Some("EUR"));
+            let entries = state
+                .db
+                .overlapping_entries(other.user.id, None, None)
+                .unwrap();
+            assert_eq!(entries[0].billable, Some(false));
+            assert_eq!(entries[0].hourly_rate, Some(120.5));
+            assert_eq!(entries[0].currency.as_deref(), Some("USD"));
+        }
+    }
+}
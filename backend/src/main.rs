@@ -1,92 +1,371 @@
 // backend/src/main.rs
 use axum::{
-    routing::{get, post},
-    serve, Json, Router,
+    extract::DefaultBodyLimit,
+    http::{header, HeaderValue, Method},
+    routing::{delete, get, patch, post, put},
+    serve, Router,
 };
-use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
-use tower_http::cors::{Any, CorsLayer};
-
-#[derive(Debug, Serialize, Deserialize)]
-struct SyncRequest {
-    last_synced_at: i64,
-    time_entries: serde_json::Value,
-    projects: serde_json::Value,
-    categories: serde_json::Value,
+use clap::Parser;
+use std::sync::Arc;
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{error, info};
+use tracing_subscriber::EnvFilter;
+
+mod audit;
+mod auth;
+mod billing;
+mod calendar;
+mod categories;
+mod classify;
+mod config;
+mod db;
+mod devices;
+mod entries;
+mod error;
+mod export;
+mod health;
+mod ics;
+mod import;
+mod merge;
+mod model;
+mod periods;
+mod projects;
+mod reports;
+mod retention;
+mod sync;
+mod targets;
+mod timer;
+mod trash;
+mod work_types;
+mod workspaces;
+
+use config::{Cli, Config};
+use db::{Db, RunTrigger};
+use health::health;
+
+/// How often expired tombstones are garbage-collected.
+const TOMBSTONE_GC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often records past the trash retention window are purged.
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How often users' retention policies are applied.
+const RETENTION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Shared state handed to every handler.
+#[derive(Clone)]
+pub struct AppState {
+    pub db: Db,
+    pub config: Arc<Config>,
 }
 
-#[derive(Debug, Serialize)]
-struct SyncResponse {
-    last_synced_at: i64,
-    time_entries: serde_json::Value,
-    projects: serde_json::Value,
-    categories: serde_json::Value,
+#[cfg(test)]
+impl AppState {
+    /// State backed by a fresh in-memory database and default settings.
+    pub fn for_tests() -> Self {
+        Self {
+            db: Db::open_in_memory().unwrap(),
+            config: Arc::new(Config::default()),
+        }
+    }
+}
+
+#[cfg(test)]
+impl auth::AuthUser {
+    /// A newly registered user called `name`, signed in without a device.
+    pub fn for_tests(state: &AppState, name: &str) -> Self {
+        Self {
+            user: state.db.create_user(name, "hash").unwrap().unwrap(),
+            device: None,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
+    // Load and validate configuration before anything else
+    let config = match Config::load(Cli::parse()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+
     // Initialize logging
-    println!("Starting time tracker backend server...");
+    health::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::new(&config.log_level))
+        .init();
+    info!("Starting time tracker backend server...");
+
+    // Open storage and run any pending migrations
+    let db = Db::open(&config.database_path).expect("failed to open database");
+    info!("Using database at {}", config.database_path.display());
+
+    // Garbage-collect tombstones in the background
+    info!(
+        "Keeping tombstones for {} days",
+        config.retention.tombstone_days
+    );
+    tokio::spawn(collect_tombstones(
+        db.clone(),
+        config.retention.tombstone_days,
+    ));
+
+    // Purge expired records from the trash in the background
+    info!(
+        "Keeping deleted records for {} days",
+        config.retention.trash_days
+    );
+    tokio::spawn(purge_trash(db.clone(), config.retention.trash_days));
+
+    // Apply users' retention policies in the background
+    info!(
+        "Applying retention policies every {} minutes",
+        RETENTION_INTERVAL.as_secs() / 60
+    );
+    tokio::spawn(apply_retention_policies(db.clone()));
 
     // Set up CORS
-    let cors = CorsLayer::new().allow_origin(Any);
+    let cors = CorsLayer::new()
+        .allow_origin(allowed_origins(&config))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]);
 
     // Build our application with routes
     let app = Router::new()
         .route("/", get(|| async { "Time Tracker API" }))
-        .route("/sync", get(get_sync))
-        .route("/sync", post(post_sync))
+        .route("/audit", get(audit::list_events))
+        .route("/auth/register", post(auth::register))
+        .route("/auth/login", post(auth::login))
+        .route("/auth/logout", post(auth::logout))
+        .route("/auth/me", get(auth::me).patch(auth::update_me))
+        .route("/devices", get(devices::list_devices))
+        .route("/devices/{id}", delete(devices::revoke_device))
+        .route(
+            "/entries",
+            get(entries::list_entries).post(entries::create_entry),
+        )
+        .route(
+            "/entries/{id}",
+            get(entries::get_entry)
+                .patch(entries::patch_entry)
+                .delete(entries::delete_entry),
+        )
+        .route(
+            "/projects",
+            get(projects::list_projects).post(projects::create_project),
+        )
+        .route(
+            "/projects/{id}",
+            get(projects::get_project)
+                .patch(projects::patch_project)
+                .delete(projects::delete_project),
+        )
+        .route("/projects/{id}/archive", post(projects::archive_project))
+        .route(
+            "/projects/{id}/unarchive",
+            post(projects::unarchive_project),
+        )
+        .route(
+            "/categories",
+            get(categories::list_categories).post(categories::create_category),
+        )
+        .route(
+            "/categories/{id}",
+            get(categories::get_category)
+                .patch(categories::patch_category)
+                .delete(categories::delete_category),
+        )
+        .route(
+            "/calendar/feed",
+            post(calendar::create_feed).delete(calendar::delete_feed),
+        )
+        .route("/calendar/feed.ics", get(calendar::feed))
+        .route("/export", get(export::export))
+        .route(
+            "/import",
+            post(import::import).layer(DefaultBodyLimit::max(import::MAX_IMPORT_BYTES)),
+        )
+        .route("/reports/summary", get(reports::summary))
+        .route("/reports/billing", get(billing::billing))
+        .route("/retention/policies", get(retention::list_policies))
+        .route(
+            "/retention/policies/default",
+            put(retention::put_default_policy).delete(retention::delete_default_policy),
+        )
+        .route(
+            "/retention/policies/projects/{id}",
+            put(retention::put_project_policy).delete(retention::delete_project_policy),
+        )
+        .route("/retention/preview", get(retention::preview))
+        .route("/retention/apply", post(retention::apply))
+        .route("/retention/runs", get(retention::list_runs))
+        .route("/retention/archive", get(retention::list_archive))
+        .route("/sync", get(sync::get_sync))
+        .route("/sync", post(sync::post_sync))
+        .route("/targets", get(targets::progress))
+        .route("/timer/start", post(timer::start_timer))
+        .route("/timer/stop", post(timer::stop_timer))
+        .route("/timer/current", get(timer::current_timer))
+        .route(
+            "/work-types",
+            get(work_types::get_rules)
+                .put(work_types::put_rules)
+                .delete(work_types::reset_rules),
+        )
+        .route("/work-types/reclassify", post(work_types::reclassify))
+        .route("/trash", get(trash::list_trash).delete(trash::empty))
+        .route("/trash/undo", post(trash::undo))
+        .route("/trash/{kind}/{id}", delete(trash::purge))
+        .route("/trash/{kind}/{id}/restore", post(trash::restore))
+        .route(
+            "/workspaces",
+            get(workspaces::list_workspaces).post(workspaces::create_workspace),
+        )
+        .route("/workspaces/{id}", patch(workspaces::update_workspace))
+        .route(
+            "/workspaces/{id}/members",
+            get(workspaces::list_members).post(workspaces::add_member),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}",
+            patch(workspaces::update_member).delete(workspaces::remove_member),
+        )
+        .route(
+            "/workspaces/{id}/trash",
+            get(trash::list_workspace_trash).delete(trash::empty_workspace_trash),
+        )
+        .route(
+            "/workspaces/{id}/trash/undo",
+            post(trash::undo_in_workspace),
+        )
+        .route(
+            "/workspaces/{id}/trash/{kind}/{record_id}",
+            delete(trash::purge_in_workspace),
+        )
+        .route(
+            "/workspaces/{id}/trash/{kind}/{record_id}/restore",
+            post(trash::restore_in_workspace),
+        )
+        .route("/workspaces/{id}/audit", get(audit::list_workspace_events))
+        .route(
+            "/workspaces/{id}/members/{user_id}/trash",
+            get(trash::list_member_trash).delete(trash::empty_member_trash),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}/trash/undo",
+            post(trash::undo_for_member),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}/trash/{kind}/{record_id}",
+            delete(trash::purge_for_member),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}/trash/{kind}/{record_id}/restore",
+            post(trash::restore_for_member),
+        )
+        .route(
+            "/workspaces/{id}/members/{user_id}/audit",
+            get(audit::list_member_events),
+        )
+        .route(
+            "/workspaces/{id}/sync",
+            get(sync::get_workspace_sync).post(sync::post_workspace_sync),
+        )
         .route("/health", get(health))
-        .layer(cors);
+        .route("/health/live", get(health))
+        .route("/health/ready", get(health::ready))
+        .layer(cors)
+        .with_state(AppState {
+            db,
+            config: Arc::new(config.clone()),
+        });
 
     // Run the server
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
-    println!("Listening on {}", addr);
+    let addr = config.socket_addr();
+    info!("Listening on {}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     serve(listener, app).await.unwrap();
 }
 
-/// Get the last synced data for the user
-async fn get_sync() -> Json<SyncResponse> {
-    // For now, simply echo back the data with an updated timestamp
-    let current_time = chrono::Utc::now().timestamp_millis();
-
-    Json(SyncResponse {
-        last_synced_at: current_time,
-        time_entries: serde_json::Value::Null,
-        projects: serde_json::Value::Null,
-        categories: serde_json::Value::Null,
-    })
+fn allowed_origins(config: &Config) -> AllowOrigin {
+    if config.allows_any_origin() {
+        return AllowOrigin::from(Any);
+    }
+    // Validated when the config was loaded.
+    AllowOrigin::list(
+        config
+            .cors_allowed_origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin).expect("validated origin")),
+    )
 }
 
-/// Update the last synced data for the user
-async fn post_sync(Json(payload): Json<SyncRequest>) -> Json<SyncResponse> {
-    // For now, simply echo back the data with an updated timestamp
-    // In a real implementation, you'd compare with stored data
-    let current_time = chrono::Utc::now().timestamp_millis();
-
-    Json(SyncResponse {
-        last_synced_at: current_time,
-        time_entries: payload.time_entries,
-        projects: payload.projects,
-        categories: payload.categories,
-    })
+/// Periodically drops tombstones older than the retention window.
+async fn collect_tombstones(db: Db, retention_days: i64) {
+    let mut interval = tokio::time::interval(TOMBSTONE_GC_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff =
+            (chrono::Utc::now() - chrono::Duration::days(retention_days)).timestamp_millis();
+        match db.purge_tombstones(cutoff) {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} expired tombstones", purged),
+            Err(err) => error!("Failed to purge tombstones: {}", err),
+        }
+    }
 }
 
-async fn health() -> Json<HealthResponse> {
-    let current_time = chrono::Utc::now().timestamp_millis();
-    let memory_usage = sys_info::mem_info().unwrap();
-    let cpu_usage = sys_info::cpu_usage().unwrap();
-    let uptime = sys_info::uptime().unwrap();
-
-    Json(HealthResponse {
-        status: "OK".to_string(),
-        last_synced_at: current_time,
-        memory_usage,
-        cpu_usage,
-        uptime,
-    })
+/// Periodically purges deleted records older than the trash retention
+/// window.
+async fn purge_trash(db: Db, retention_days: i64) {
+    let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let cutoff =
+            (chrono::Utc::now() - chrono::Duration::days(retention_days)).timestamp_millis();
+        match db.purge_trash(cutoff) {
+            Ok(0) => {}
+            Ok(purged) => info!("Purged {} records from the trash", purged),
+            Err(err) => error!("Failed to purge the trash: {}", err),
+        }
+    }
+}
+
+/// Periodically applies every user's retention policies.
+async fn apply_retention_policies(db: Db) {
+    let mut interval = tokio::time::interval(RETENTION_INTERVAL);
+    loop {
+        interval.tick().await;
+        let users = match db.users_with_retention_policies() {
+            Ok(users) => users,
+            Err(err) => {
+                error!("Failed to load retention policies: {}", err);
+                continue;
+            }
+        };
+        let now = chrono::Utc::now().timestamp_millis();
+        for user in users {
+            match db.apply_retention(user, now, RunTrigger::Scheduled, false) {
+                Ok(run) if run.entries.is_empty() => {}
+                Ok(run) => info!(
+                    "Retention archived {} and deleted {} entries of user {}",
+                    run.archived, run.deleted, user
+                ),
+                Err(err) => error!("Failed to apply retention for user {}: {}", user, err),
+            }
+        }
+    }
 }
 
 #[cfg(test)]
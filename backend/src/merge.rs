@@ -0,0 +1,186 @@
+// backend/src/merge.rs
+//! Three-way merge of records edited concurrently on several devices.
+//!
+//! Every stored record carries a `version` that the server bumps on each
+//! change. A client sends back the version it last saw; when that is older
+//! than the stored one, both sides edited the record and it is merged field
+//! by field against the copy stored at the client's version (the base):
+//!
+//! - a field only one side changed takes that side's value;
+//! - a field both sides changed to different values is a conflict and the
+//!   last writer wins, which is the client whose sync is being applied;
+//! - except when both sides stopped the same running entry: the earlier
+//!   `endTime` is kept, because the timer was already stopped at that point.
+//!
+//! Every conflict is reported back so the client can show it. If the base is
+//! no longer available, every differing field is treated as a conflict.
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Fields that describe the record rather than its content.
+const BOOKKEEPING_FIELDS: &[&str] = &["id", "version"];
+
+/// Why a field needed resolving.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictReason {
+    /// Both sides changed the field; the client's value won.
+    ConcurrentUpdate,
+    /// Both sides stopped the same running entry; the earlier stop won.
+    ConcurrentStop,
+}
+
+/// A field that was edited on both sides, with every value involved.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Conflict {
+    /// Collection the record belongs to, e.g. `time_entries`.
+    pub kind: &'static str,
+    pub id: String,
+    pub field: String,
+    /// Value at the client's version, or null if that is no longer known.
+    pub base: Value,
+    pub server: Value,
+    pub client: Value,
+    pub resolved: Value,
+    pub reason: ConflictReason,
+}
+
+/// Content fields of a serialized record, with bookkeeping stripped.
+pub fn content(record: &Value) -> Map<String, Value> {
+    let mut fields = record.as_object().cloned().unwrap_or_default();
+    for field in BOOKKEEPING_FIELDS {
+        fields.remove(*field);
+    }
+    fields
+}
+
+/// Merges `client` into `server` given their common ancestor `base`.
+///
+/// Returns the merged content (without bookkeeping fields) and the conflicts,
+/// which are tagged with `kind` and the record's id.
+pub fn merge(
+    kind: &'static str,
+    id: &str,
+    base: Option<&Value>,
+    server: &Value,
+    client: &Value,
+) -> (Map<String, Value>, Vec<Conflict>) {
+    let base = base.map(content);
+    let server = content(server);
+    let client = content(client);
+
+    let mut fields: Vec<&String> = server.keys().chain(client.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    let mut merged = Map::new();
+    let mut conflicts = Vec::new();
+    for field in fields {
+        let base_value = base.as_ref().map(|base| value_of(base, field));
+        let server_value = value_of(&server, field);
+        let client_value = value_of(&client, field);
+
+        let resolved = if server_value == client_value || base_value.as_ref() == Some(&client_value)
+        {
+            server_value
+        } else if base_value.as_ref() == Some(&server_value) {
+            client_value
+        } else {
+            let (resolved, reason) =
+                resolve(field, base_value.as_ref(), &server_value, &client_value);
+            conflicts.push(Conflict {
+                kind,
+                id: id.to_string(),
+                field: field.clone(),
+                base: base_value.unwrap_or(Value::Null),
+                server: server_value,
+                client: client_value,
+                resolved: resolved.clone(),
+                reason,
+            });
+            resolved
+        };
+        merged.insert(field.clone(), resolved);
+    }
+
+    (merged, conflicts)
+}
+
+fn resolve(
+    field: &str,
+    base: Option<&Value>,
+    server: &Value,
+    client: &Value,
+) -> (Value, ConflictReason) {
+    let was_running = base.is_some_and(Value::is_null);
+    if field == "endTime" && was_running {
+        if let (Some(server_stop), Some(client_stop)) = (server.as_i64(), client.as_i64()) {
+            return (
+                Value::from(server_stop.min(client_stop)),
+                ConflictReason::ConcurrentStop,
+            );
+        }
+    }
+    (client.clone(), ConflictReason::ConcurrentUpdate)
+}
+
+/// Optional fields are omitted when unset, which is the same as null.
+fn value_of(fields: &Map<String, Value>, field: &str) -> Value {
+    fields.get(field).cloned().unwrap_or(Value::Null)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_disjoint_edits_merge_without_conflict() {
+        let base = json!({"id": "e1", "description": "a", "startTime": 1, "version": 1});
+        let server = json!({"id": "e1", "description": "a", "startTime": 5, "version": 2});
+        let client = json!({"id": "e1", "description": "b", "startTime": 1, "version": 1});
+
+        let (merged, conflicts) = merge("time_entries", "e1", Some(&base), &server, &client);
+        assert!(conflicts.is_empty());
+        assert_eq!(
+            Value::Object(merged),
+            json!({"description": "b", "startTime": 5})
+        );
+    }
+
+    #[test]
+    fn test_same_field_edits_let_client_win() {
+        let base = json!({"description": "a"});
+        let server = json!({"description": "server"});
+        let client = json!({"description": "client"});
+
+        let (merged, conflicts) = merge("time_entries", "e1", Some(&base), &server, &client);
+        assert_eq!(merged["description"], "client");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].reason, ConflictReason::ConcurrentUpdate);
+        assert_eq!(conflicts[0].server, "server");
+    }
+
+    #[test]
+    fn test_concurrent_stops_keep_earlier_end_time() {
+        let base = json!({"startTime": 1});
+        let server = json!({"startTime": 1, "endTime": 300});
+        let client = json!({"startTime": 1, "endTime": 200});
+
+        let (merged, conflicts) = merge("time_entries", "e1", Some(&base), &server, &client);
+        assert_eq!(merged["endTime"], 200);
+        assert_eq!(conflicts[0].reason, ConflictReason::ConcurrentStop);
+        assert_eq!(conflicts[0].field, "endTime");
+    }
+
+    #[test]
+    fn test_unknown_base_reports_every_difference() {
+        let server = json!({"name": "Work", "color": "#000000"});
+        let client = json!({"name": "Job", "color": "#000000"});
+
+        let (merged, conflicts) = merge("projects", "p1", None, &server, &client);
+        assert_eq!(merged["name"], "Job");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].base, Value::Null);
+    }
+}
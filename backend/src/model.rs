@@ -0,0 +1,410 @@
+// backend/src/model.rs
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
+
+use crate::error::{join_path, FieldError};
+
+/// Upper bound for a category's weekly target: there are 168 hours in a week.
+const MAX_WEEKLY_TARGET_HOURS: f64 = 168.0;
+
+/// Upper bound for an hourly rate, to catch amounts typed in minor units.
+const MAX_HOURLY_RATE: f64 = 1_000_000.0;
+
+/// A tracked span of time, mirroring the frontend's `TimeEntry`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimeEntry {
+    pub id: String,
+    pub description: String,
+    pub start_time: i64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_time: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category_id: Option<String>,
+    /// Overrides the project's billable flag when set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub billable: Option<bool>,
+    /// Overrides the project's rate when set, together with `currency`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Server-assigned version this copy is based on; 0 if never synced.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// A project entries can be assigned to, mirroring the frontend's `Project`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Project {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    /// Hidden from pickers; entries keep it and reports still count it.
+    #[serde(default)]
+    pub archived: bool,
+    /// Whether its entries are billed, unless they say otherwise.
+    #[serde(default)]
+    pub billable: bool,
+    /// Who the project is billed to.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Rate for its entries without one of their own, together with
+    /// `currency`; the workspace's default applies otherwise.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hourly_rate: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// Server-assigned version this copy is based on; 0 if never synced.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// A category entries can be assigned to, mirroring the frontend's `Category`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Category {
+    pub id: String,
+    pub name: String,
+    pub color: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weekly_target_hours: Option<f64>,
+    /// Server-assigned version this copy is based on; 0 if never synced.
+    #[serde(default)]
+    pub version: i64,
+}
+
+/// Records keyed by id, the same shape the zustand store persists.
+pub type Records<T> = BTreeMap<String, T>;
+
+/// Everything a client keeps in its store.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Dataset {
+    pub time_entries: Records<TimeEntry>,
+    pub projects: Records<Project>,
+    pub categories: Records<Category>,
+}
+
+/// Ids of records removed since a client's cursor, grouped by kind.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Deletions {
+    #[serde(default)]
+    pub time_entries: Vec<String>,
+    #[serde(default)]
+    pub projects: Vec<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+}
+
+/// Semantic checks that serde alone cannot express.
+///
+/// `path` is the location of the value in the request body and is used as
+/// the prefix of every reported field, e.g. `time_entries.e1.endTime`.
+pub trait Validate {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>);
+}
+
+impl Validate for TimeEntry {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        require_id(&self.id, path, errors);
+        require_version(self.version, path, errors);
+        if self.start_time < 0 {
+            errors.push(FieldError::new(
+                join_path(path, "startTime"),
+                "must not be negative",
+            ));
+        }
+        if let Some(end_time) = self.end_time {
+            if end_time < self.start_time {
+                errors.push(FieldError::new(
+                    join_path(path, "endTime"),
+                    "must not be before startTime",
+                ));
+            }
+        }
+        for (field, value) in [
+            ("projectId", &self.project_id),
+            ("categoryId", &self.category_id),
+        ] {
+            if value.as_deref().is_some_and(|id| id.trim().is_empty()) {
+                errors.push(FieldError::new(
+                    join_path(path, field),
+                    "must not be empty when present",
+                ));
+            }
+        }
+        validate_rate(
+            self.hourly_rate,
+            self.currency.as_deref(),
+            (&join_path(path, "hourlyRate"), &join_path(path, "currency")),
+            errors,
+        );
+    }
+}
+
+impl Validate for Project {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        require_id(&self.id, path, errors);
+        require_version(self.version, path, errors);
+        require_name(&self.name, path, errors);
+        require_color(&self.color, path, errors);
+        if self
+            .client
+            .as_deref()
+            .is_some_and(|client| client.trim().is_empty())
+        {
+            errors.push(FieldError::new(
+                join_path(path, "client"),
+                "must not be empty when present",
+            ));
+        }
+        validate_rate(
+            self.hourly_rate,
+            self.currency.as_deref(),
+            (&join_path(path, "hourlyRate"), &join_path(path, "currency")),
+            errors,
+        );
+    }
+}
+
+impl Validate for Category {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        require_id(&self.id, path, errors);
+        require_version(self.version, path, errors);
+        require_name(&self.name, path, errors);
+        require_color(&self.color, path, errors);
+        if let Some(hours) = self.weekly_target_hours {
+            if !hours.is_finite() || !(0.0..=MAX_WEEKLY_TARGET_HOURS).contains(&hours) {
+                errors.push(FieldError::new(
+                    join_path(path, "weeklyTargetHours"),
+                    format!("must be between 0 and {MAX_WEEKLY_TARGET_HOURS}"),
+                ));
+            }
+        }
+    }
+}
+
+impl Validate for Deletions {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        for (field, ids) in [
+            ("time_entries", &self.time_entries),
+            ("projects", &self.projects),
+            ("categories", &self.categories),
+        ] {
+            for (index, id) in ids.iter().enumerate() {
+                if id.trim().is_empty() {
+                    errors.push(FieldError::new(
+                        join_path(path, &format!("{field}[{index}]")),
+                        "must not be empty",
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Every record must be stored under its own id.
+impl<T: Validate + HasId> Validate for Records<T> {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        for (key, record) in self {
+            let record_path = join_path(path, key);
+            if record.id() != key {
+                errors.push(FieldError::new(
+                    join_path(&record_path, "id"),
+                    format!("does not match record key `{key}`"),
+                ));
+            }
+            record.validate(&record_path, errors);
+        }
+    }
+}
+
+/// Tells an explicit `null` (clear the field) apart from a missing field.
+pub fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub trait HasId {
+    fn id(&self) -> &str;
+}
+
+impl HasId for TimeEntry {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasId for Project {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+impl HasId for Category {
+    fn id(&self) -> &str {
+        &self.id
+    }
+}
+
+pub fn require_id(id: &str, path: &str, errors: &mut Vec<FieldError>) {
+    if id.trim().is_empty() {
+        errors.push(FieldError::new(join_path(path, "id"), "must not be empty"));
+    }
+}
+
+fn require_version(version: i64, path: &str, errors: &mut Vec<FieldError>) {
+    if version < 0 {
+        errors.push(FieldError::new(
+            join_path(path, "version"),
+            "must not be negative",
+        ));
+    }
+}
+
+pub fn require_name(name: &str, path: &str, errors: &mut Vec<FieldError>) {
+    if name.trim().is_empty() {
+        errors.push(FieldError::new(
+            join_path(path, "name"),
+            "must not be empty",
+        ));
+    }
+}
+
+/// A rate needs a currency, given as an ISO 4217 code like `EUR`, and the
+/// other way round. `fields` are the paths errors are reported at.
+pub fn validate_rate(
+    hourly_rate: Option<f64>,
+    currency: Option<&str>,
+    (rate_field, currency_field): (&str, &str),
+    errors: &mut Vec<FieldError>,
+) {
+    if let Some(rate) = hourly_rate {
+        if !rate.is_finite() || !(0.0..=MAX_HOURLY_RATE).contains(&rate) {
+            errors.push(FieldError::new(
+                rate_field,
+                format!("must be between 0 and {MAX_HOURLY_RATE}"),
+            ));
+        }
+    }
+    if let Some(currency) = currency {
+        if currency.len() != 3 || !currency.chars().all(|c| c.is_ascii_uppercase()) {
+            errors.push(FieldError::new(
+                currency_field,
+                "must be a three-letter currency code like EUR",
+            ));
+        }
+    }
+    match (hourly_rate, currency) {
+        (Some(_), None) => errors.push(FieldError::new(currency_field, "is required with a rate")),
+        (None, Some(_)) => errors.push(FieldError::new(rate_field, "is required with a currency")),
+        _ => {}
+    }
+}
+
+/// Colors come from `<input type="color">`, which always yields `#rrggbb`.
+pub fn require_color(color: &str, path: &str, errors: &mut Vec<FieldError>) {
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        errors.push(FieldError::new(
+            join_path(path, "color"),
+            "must be a hex color like #3b82f6",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> TimeEntry {
+        TimeEntry {
+            id: "e1".to_string(),
+            description: "Write docs".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            project_id: Some("p1".to_string()),
+            version: 3,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_time_entry_uses_frontend_shape() {
+        let json = serde_json::to_value(entry()).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "id": "e1",
+                "description": "Write docs",
+                "startTime": 1000,
+                "endTime": 2000,
+                "projectId": "p1",
+                "version": 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_end_before_start_is_rejected() {
+        let mut records = Records::new();
+        records.insert(
+            "e1".to_string(),
+            TimeEntry {
+                end_time: Some(500),
+                ..entry()
+            },
+        );
+
+        let mut errors = Vec::new();
+        records.validate("time_entries", &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "time_entries.e1.endTime");
+    }
+
+    #[test]
+    fn test_record_key_must_match_id() {
+        let mut records = Records::new();
+        records.insert(
+            "p2".to_string(),
+            Project {
+                id: "p1".to_string(),
+                name: "Default Project".to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+
+        let mut errors = Vec::new();
+        records.validate("projects", &mut errors);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "projects.p2.id");
+    }
+
+    #[test]
+    fn test_category_target_out_of_range() {
+        let category = Category {
+            id: "c1".to_string(),
+            name: "Work".to_string(),
+            color: "blue".to_string(),
+            weekly_target_hours: Some(200.0),
+            ..Default::default()
+        };
+
+        let mut errors = Vec::new();
+        category.validate("categories.c1", &mut errors);
+        let fields: Vec<_> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(
+            fields,
+            ["categories.c1.color", "categories.c1.weeklyTargetHours"]
+        );
+    }
+}
@@ -0,0 +1,213 @@
+// backend/src/periods.rs
+//! Calendar days, weeks and months as a user sees them, and splitting spans
+//! of time at their boundaries.
+//!
+//! Boundaries are local midnights in the user's time zone, so a day is 23 or
+//! 25 hours long when the clocks change and an entry running past midnight
+//! counts towards both days.
+use chrono::{DateTime, Datelike, Days, Months, NaiveDate, TimeDelta, TimeZone, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+
+use crate::db::User;
+use crate::error::FieldError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    Week,
+    Month,
+}
+
+impl Period {
+    /// First day of the period after the one starting on `start`.
+    pub fn next(self, start: NaiveDate) -> NaiveDate {
+        match self {
+            Period::Day => start + Days::new(1),
+            Period::Week => start + Days::new(7),
+            Period::Month => start + Months::new(1),
+        }
+    }
+}
+
+/// A time zone and the day its weeks start on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calendar {
+    pub tz: Tz,
+    pub week_start: Weekday,
+}
+
+/// UTC with ISO weeks, what a new account starts with.
+impl Default for Calendar {
+    fn default() -> Self {
+        Self {
+            tz: Tz::UTC,
+            week_start: Weekday::Mon,
+        }
+    }
+}
+
+impl Calendar {
+    /// The calendar in the user's settings, in `timezone` instead if given.
+    pub fn for_user(user: &User, timezone: Option<&str>) -> Self {
+        let tz = timezone
+            .unwrap_or(&user.timezone)
+            .parse()
+            .unwrap_or(Tz::UTC);
+        Self {
+            tz,
+            week_start: user.week_start,
+        }
+    }
+
+    /// First day of the period containing `date`.
+    pub fn period_start(&self, period: Period, date: NaiveDate) -> NaiveDate {
+        match period {
+            Period::Day => date,
+            Period::Week => date - Days::new(date.weekday().days_since(self.week_start).into()),
+            Period::Month => date.with_day(1).expect("every month has a first day"),
+        }
+    }
+
+    /// Names the period starting on `start`: `2023-01-02` for a day,
+    /// `2023-01` for a month, and for a week `2023-W01` if weeks start on
+    /// Monday like ISO weeks, or the date it starts on otherwise.
+    pub fn period_key(&self, period: Period, start: NaiveDate) -> String {
+        match period {
+            Period::Week if self.week_start == Weekday::Mon => {
+                let week = start.iso_week();
+                format!("{}-W{:02}", week.year(), week.week())
+            }
+            Period::Day | Period::Week => start.format("%Y-%m-%d").to_string(),
+            Period::Month => start.format("%Y-%m").to_string(),
+        }
+    }
+
+    /// The local date at an instant, given in epoch milliseconds.
+    pub fn local_date(&self, millis: i64) -> NaiveDate {
+        DateTime::from_timestamp_millis(millis)
+            .unwrap_or_default()
+            .with_timezone(&self.tz)
+            .date_naive()
+    }
+
+    /// The first instant of a local date, in epoch milliseconds: midnight,
+    /// or the end of the gap where a clock change skips midnight.
+    pub fn start_of_day(&self, date: NaiveDate) -> i64 {
+        let midnight = date.and_hms_opt(0, 0, 0).expect("midnight is a valid time");
+        (0..=24 * 4)
+            .find_map(|quarter| {
+                self.tz
+                    .from_local_datetime(&(midnight + TimeDelta::minutes(15 * quarter)))
+                    .earliest()
+            })
+            .expect("no clock change skips a whole day")
+            .timestamp_millis()
+    }
+
+    /// Splits `[start, end)` at the boundaries of `period` into the start
+    /// date of each period it touches and the milliseconds spent in it.
+    pub fn split(&self, period: Period, start: i64, end: i64) -> Vec<(NaiveDate, i64)> {
+        let mut parts = Vec::new();
+        let mut current = start;
+        while current < end {
+            let first_day = self.period_start(period, self.local_date(current));
+            let boundary = self.start_of_day(period.next(first_day));
+            let until = boundary.min(end);
+            parts.push((first_day, until - current));
+            current = until;
+        }
+        parts
+    }
+}
+
+/// Checks that `value` names an IANA time zone.
+pub fn validate_timezone(value: &str, path: &str, errors: &mut Vec<FieldError>) {
+    if value.parse::<Tz>().is_err() {
+        errors.push(FieldError::new(
+            path,
+            "must be an IANA time zone such as Europe/Berlin",
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn berlin() -> Calendar {
+        Calendar {
+            tz: "Europe/Berlin".parse().unwrap(),
+            ..Calendar::default()
+        }
+    }
+
+    #[test]
+    fn test_period_starts_and_keys() {
+        let calendar = Calendar::default();
+        let sunday = date(2023, 1, 1);
+        assert_eq!(
+            calendar.period_start(Period::Week, sunday),
+            date(2022, 12, 26)
+        );
+        assert_eq!(
+            calendar.period_key(Period::Week, date(2023, 1, 2)),
+            "2023-W01"
+        );
+        assert_eq!(
+            calendar.period_key(Period::Week, date(2022, 12, 26)),
+            "2022-W52"
+        );
+        assert_eq!(
+            calendar.period_start(Period::Month, date(2023, 2, 17)),
+            date(2023, 2, 1)
+        );
+        assert_eq!(Period::Month.next(date(2023, 12, 1)), date(2024, 1, 1));
+
+        let sundays = Calendar {
+            week_start: Weekday::Sun,
+            ..calendar
+        };
+        assert_eq!(sundays.period_start(Period::Week, sunday), sunday);
+        assert_eq!(sundays.period_start(Period::Week, date(2023, 1, 7)), sunday);
+        assert_eq!(sundays.period_key(Period::Week, sunday), "2023-01-01");
+    }
+
+    #[test]
+    fn test_split_at_local_midnight() {
+        let calendar = berlin();
+        // 22:00 to 02:00 local time, one hour ahead of UTC in winter.
+        let start = calendar.start_of_day(date(2023, 1, 1)) + 22 * HOUR;
+        let parts = calendar.split(Period::Day, start, start + 4 * HOUR);
+
+        assert_eq!(
+            parts,
+            [(date(2023, 1, 1), 2 * HOUR), (date(2023, 1, 2), 2 * HOUR)]
+        );
+    }
+
+    #[test]
+    fn test_days_around_clock_changes() {
+        let calendar = berlin();
+        let length =
+            |day: NaiveDate| calendar.start_of_day(day + Days::new(1)) - calendar.start_of_day(day);
+        assert_eq!(length(date(2023, 3, 26)), 23 * HOUR);
+        assert_eq!(length(date(2023, 10, 29)), 25 * HOUR);
+
+        // Clocks in Santiago skip from midnight to 01:00.
+        let santiago = Calendar {
+            tz: "America/Santiago".parse().unwrap(),
+            ..calendar
+        };
+        let start = santiago.start_of_day(date(2022, 9, 11));
+        assert_eq!(santiago.local_date(start), date(2022, 9, 11));
+        assert_eq!(santiago.local_date(start - 1), date(2022, 9, 10));
+    }
+}
@@ -0,0 +1,283 @@
+// backend/src/projects.rs
+//! REST endpoints for projects.
+//!
+//! Archiving hides a project from pickers without touching its entries;
+//! deleting one that entries still use requires moving them elsewhere.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::db::Rejection;
+use crate::error::{ApiError, FieldError, ValidJson, ValidQuery};
+use crate::model::{nullable, Project, Validate};
+use crate::AppState;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ListProjects {
+    /// Also list archived projects, e.g. for reports.
+    pub include_archived: bool,
+}
+
+impl Validate for ListProjects {
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DeleteProject {
+    /// Project to move the deleted project's entries to.
+    pub reassign_to: Option<String>,
+}
+
+impl Validate for DeleteProject {
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeletedProject {
+    /// How many entries were moved to `reassign_to`.
+    pub reassigned_entries: usize,
+}
+
+/// Fields to change on a project; absent fields are kept. `client`,
+/// `hourlyRate` and `currency` can be set to null to clear them. If
+/// `version` is given the patch only applies to that version.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default, deny_unknown_fields)]
+pub struct ProjectPatch {
+    pub name: Option<String>,
+    pub color: Option<String>,
+    pub archived: Option<bool>,
+    pub billable: Option<bool>,
+    #[serde(deserialize_with = "nullable")]
+    pub client: Option<Option<String>>,
+    #[serde(deserialize_with = "nullable")]
+    pub hourly_rate: Option<Option<f64>>,
+    #[serde(deserialize_with = "nullable")]
+    pub currency: Option<Option<String>>,
+    pub version: Option<i64>,
+}
+
+impl Validate for ProjectPatch {
+    // Checked once applied, against the whole project.
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+impl ProjectPatch {
+    fn apply(self, project: &mut Project) {
+        if let Some(name) = self.name {
+            project.name = name;
+        }
+        if let Some(color) = self.color {
+            project.color = color;
+        }
+        if let Some(archived) = self.archived {
+            project.archived = archived;
+        }
+        if let Some(billable) = self.billable {
+            project.billable = billable;
+        }
+        if let Some(client) = self.client {
+            project.client = client;
+        }
+        if let Some(hourly_rate) = self.hourly_rate {
+            project.hourly_rate = hourly_rate;
+        }
+        if let Some(currency) = self.currency {
+            project.currency = currency;
+        }
+    }
+}
+
+/// List the user's projects by name
+pub async fn list_projects(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ListProjects>,
+) -> Result<Json<Vec<Project>>, ApiError> {
+    let projects = state
+        .db
+        .list_projects(auth.user.id, query.include_archived)?;
+    Ok(Json(projects))
+}
+
+/// Get a project by id
+pub async fn get_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Project>, ApiError> {
+    let project = state
+        .db
+        .find_project(auth.user.id, &id)?
+        .ok_or(ApiError::NotFound("project"))?;
+    Ok(Json(project))
+}
+
+/// Create a project
+pub async fn create_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(project): ValidJson<Project>,
+) -> Result<(StatusCode, Json<Project>), ApiError> {
+    let project = auth
+        .db(&state)
+        .create_project(auth.user.id, project)?
+        .map_err(|rejection| ApiError::rejected("project", rejection))?;
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+/// Change some fields of a project
+pub async fn patch_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidJson(patch): ValidJson<ProjectPatch>,
+) -> Result<Json<Project>, ApiError> {
+    update_project(&state, &auth, &id, patch).await
+}
+
+/// Archive a project, hiding it from pickers
+pub async fn archive_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Project>, ApiError> {
+    let patch = ProjectPatch {
+        archived: Some(true),
+        ..ProjectPatch::default()
+    };
+    update_project(&state, &auth, &id, patch).await
+}
+
+/// Bring an archived project back
+pub async fn unarchive_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<Json<Project>, ApiError> {
+    let patch = ProjectPatch {
+        archived: Some(false),
+        ..ProjectPatch::default()
+    };
+    update_project(&state, &auth, &id, patch).await
+}
+
+/// Delete a project, moving its entries to `reassign_to`
+///
+/// Refuses with 409 while entries use the project and no target is given.
+pub async fn delete_project(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidQuery(query): ValidQuery<DeleteProject>,
+) -> Result<Json<DeletedProject>, ApiError> {
+    let reassigned_entries = auth
+        .db(&state)
+        .delete_project(auth.user.id, &id, query.reassign_to.as_deref())?
+        .map_err(|rejection| ApiError::rejected("project", rejection))?;
+    Ok(Json(DeletedProject { reassigned_entries }))
+}
+
+async fn update_project(
+    state: &AppState,
+    auth: &AuthUser,
+    id: &str,
+    patch: ProjectPatch,
+) -> Result<Json<Project>, ApiError> {
+    let mut project = state
+        .db
+        .find_project(auth.user.id, id)?
+        .ok_or(ApiError::NotFound("project"))?;
+    if patch
+        .version
+        .is_some_and(|version| version != project.version)
+    {
+        return Err(ApiError::rejected(
+            "project",
+            Rejection::StaleVersion(project.version),
+        ));
+    }
+    patch.apply(&mut project);
+
+    let mut errors = Vec::new();
+    project.validate("", &mut errors);
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+
+    let project = auth
+        .db(state)
+        .update_project(auth.user.id, project)?
+        .map_err(|rejection| ApiError::rejected("project", rejection))?;
+    Ok(Json(project))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (AppState, AuthUser) {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        (state, auth)
+    }
+
+    fn project() -> Project {
+        Project {
+            id: "p1".to_string(),
+            name: "Work".to_string(),
+            color: "#3b82f6".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_archive_and_unarchive() {
+        let (state, auth) = setup();
+        let _ = create_project(State(state.clone()), auth.clone(), ValidJson(project()))
+            .await
+            .unwrap();
+
+        let archived = archive_project(State(state.clone()), auth.clone(), Path("p1".into()))
+            .await
+            .unwrap();
+        assert!(archived.archived);
+        let listed = list_projects(
+            State(state.clone()),
+            auth.clone(),
+            ValidQuery(ListProjects::default()),
+        )
+        .await
+        .unwrap();
+        assert!(listed.is_empty());
+
+        let restored = unarchive_project(State(state), auth, Path("p1".into()))
+            .await
+            .unwrap();
+        assert!(!restored.archived);
+        assert_eq!(restored.version, 3);
+    }
+
+    #[tokio::test]
+    async fn test_patch_rejects_invalid_color() {
+        let (state, auth) = setup();
+        let _ = create_project(State(state.clone()), auth.clone(), ValidJson(project()))
+            .await
+            .unwrap();
+        let patch = ProjectPatch {
+            color: Some("blue".to_string()),
+            ..ProjectPatch::default()
+        };
+        let result = patch_project(State(state), auth, Path("p1".into()), ValidJson(patch)).await;
+        match result {
+            Err(ApiError::Validation(errors)) => assert_eq!(errors[0].field, "color"),
+            other => panic!("expected a validation error, got {other:?}"),
+        }
+    }
+}
@@ -0,0 +1,568 @@
+// backend/src/reports.rs
+//! Aggregated reports over a user's time entries.
+//!
+//! Entries are clipped to the requested range, and running entries count up
+//! to now. When grouping by day, week or month an entry that crosses a
+//! boundary in the report's time zone is split between the periods.
+//!
+//! Reports over a workspace cover the caller's own entries in it, or the
+//! whole team's for owners and admins.
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::classify;
+use crate::db::{UserId, WorkspaceId};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::{TimeEntry, Validate};
+use crate::periods::{self, Calendar, Period};
+use crate::work_types;
+use crate::workspaces;
+use crate::AppState;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    #[default]
+    Day,
+    Week,
+    Month,
+    Project,
+    Category,
+    Description,
+    /// The work type entries were classified as.
+    WorkType,
+    /// The workspace member who logged the entries.
+    Member,
+}
+
+impl GroupBy {
+    fn period(self) -> Option<Period> {
+        match self {
+            GroupBy::Day => Some(Period::Day),
+            GroupBy::Week => Some(Period::Week),
+            GroupBy::Month => Some(Period::Month),
+            GroupBy::Project
+            | GroupBy::Category
+            | GroupBy::Description
+            | GroupBy::WorkType
+            | GroupBy::Member => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct SummaryQuery {
+    pub group_by: GroupBy,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    /// IANA name of the zone whose days, weeks and months are reported, if
+    /// not the user's own.
+    pub timezone: Option<String>,
+    pub project: Option<String>,
+    pub category: Option<String>,
+    /// Report on a workspace instead of the caller's own time.
+    pub workspace: Option<WorkspaceId>,
+}
+
+impl Validate for SummaryQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if to < from {
+                errors.push(FieldError::new(
+                    join_path(path, "to"),
+                    "must not be before from",
+                ));
+            }
+        }
+        if let Some(timezone) = &self.timezone {
+            periods::validate_timezone(timezone, &join_path(path, "timezone"), errors);
+        }
+        match (self.group_by, self.workspace) {
+            (GroupBy::Member, None) => errors.push(FieldError::new(
+                join_path(path, "group_by"),
+                "member is only available for workspace reports",
+            )),
+            (GroupBy::WorkType, Some(_)) => errors.push(FieldError::new(
+                join_path(path, "group_by"),
+                "work_type is not available for workspace reports",
+            )),
+            _ => {}
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummaryReport {
+    pub group_by: GroupBy,
+    pub timezone: String,
+    pub from: Option<i64>,
+    pub to: Option<i64>,
+    pub total_ms: i64,
+    /// Entries with time in the range, each counted once.
+    pub entry_count: usize,
+    pub average_entry_ms: i64,
+    pub groups: Vec<SummaryGroup>,
+}
+
+/// Time spent in one group. Time groups come in order; the others come
+/// largest first.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct SummaryGroup {
+    /// The period (`2023-01-02`, `2023-W01`, `2023-01`), the project,
+    /// category, work type or member id, or the description. `None` collects
+    /// entries without a project or category.
+    pub key: Option<String>,
+    /// Name of the project, category or work type, or the member's username.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// When the period begins, for time groups.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start: Option<i64>,
+    pub total_ms: i64,
+    /// Entries with time in the group; one split between periods counts in
+    /// each.
+    pub entry_count: usize,
+    pub average_entry_ms: i64,
+}
+
+/// Totals, counts and averages of the user's time, grouped
+pub async fn summary(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<SummaryQuery>,
+) -> Result<Json<SummaryReport>, ApiError> {
+    let calendar = Calendar::for_user(&auth.user, query.timezone.as_deref());
+    let now = chrono::Utc::now().timestamp_millis();
+    let source = match query.workspace {
+        Some(workspace) => team_source(&state, &auth, workspace, &query)?,
+        None => personal_source(&state, &auth, &query)?,
+    };
+    let entries: Vec<TimeEntry> = source
+        .entries
+        .into_iter()
+        .filter(|entry| query.project.is_none() || entry.project_id == query.project)
+        .filter(|entry| query.category.is_none() || entry.category_id == query.category)
+        .collect();
+
+    let mut report = summarize(&entries, &query, calendar, now, &source.labels);
+    for group in &mut report.groups {
+        group.name = group
+            .key
+            .as_ref()
+            .and_then(|id| source.names.get(id).cloned());
+    }
+    Ok(Json(report))
+}
+
+/// The entries a report covers, with what it needs to group and name them.
+struct Source {
+    entries: Vec<TimeEntry>,
+    /// The work type or member of each entry, by entry id, when grouping by
+    /// one of those.
+    labels: HashMap<String, String>,
+    /// Names of the groups' keys.
+    names: HashMap<String, String>,
+}
+
+fn personal_source(
+    state: &AppState,
+    auth: &AuthUser,
+    query: &SummaryQuery,
+) -> Result<Source, ApiError> {
+    let user = auth.user.id;
+    let entries = state.db.overlapping_entries(user, query.from, query.to)?;
+    let (labels, names) = match query.group_by {
+        GroupBy::WorkType => {
+            let rules = work_types::rules_for(&state.db, user)?;
+            let names = rules
+                .work_types
+                .into_iter()
+                .map(|work_type| (work_type.id, work_type.name))
+                .collect();
+            (state.db.entry_work_types(user)?, names)
+        }
+        group_by => (HashMap::new(), record_names(state, user, group_by)?),
+    };
+    Ok(Source {
+        entries,
+        labels,
+        names,
+    })
+}
+
+/// The caller's entries in `workspace`, or every member's for managers.
+/// Entry ids are prefixed with the member's id, since two members' entries
+/// can share one.
+fn team_source(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+    query: &SummaryQuery,
+) -> Result<Source, ApiError> {
+    let membership = workspaces::membership(state, auth, workspace)?;
+    let stores = workspaces::report_stores(state, auth, workspace, &membership)?;
+
+    let mut source = Source {
+        entries: Vec::new(),
+        labels: HashMap::new(),
+        names: record_names(state, membership.shared_store, query.group_by)?,
+    };
+    for member in stores {
+        let key = member.user_id.to_string();
+        for mut entry in state
+            .db
+            .overlapping_entries(member.store, query.from, query.to)?
+        {
+            entry.id = format!("{key}/{}", entry.id);
+            source.labels.insert(entry.id.clone(), key.clone());
+            source.entries.push(entry);
+        }
+        source.names.insert(key, member.username);
+    }
+    Ok(source)
+}
+
+/// Names of the projects or categories in `store`, when grouping by them.
+fn record_names(
+    state: &AppState,
+    store: UserId,
+    group_by: GroupBy,
+) -> Result<HashMap<String, String>, ApiError> {
+    Ok(match group_by {
+        GroupBy::Project => state
+            .db
+            .list_projects(store, true)?
+            .into_iter()
+            .map(|project| (project.id, project.name))
+            .collect(),
+        GroupBy::Category => state
+            .db
+            .list_categories(store)?
+            .into_iter()
+            .map(|category| (category.id, category.name))
+            .collect(),
+        _ => HashMap::new(),
+    })
+}
+
+/// Groups `entries` as `query` asks, with running entries ending at `now`.
+/// `labels` maps entry ids to their work type or member when grouping by
+/// one of those.
+fn summarize(
+    entries: &[TimeEntry],
+    query: &SummaryQuery,
+    calendar: Calendar,
+    now: i64,
+    labels: &HashMap<String, String>,
+) -> SummaryReport {
+    // (key, period start) -> (total, entries)
+    let mut groups: HashMap<(Option<String>, Option<i64>), (i64, usize)> = HashMap::new();
+    let mut total_ms = 0;
+    let mut entry_count = 0;
+    for entry in entries {
+        let start = query
+            .from
+            .map_or(entry.start_time, |from| entry.start_time.max(from));
+        let end = entry.end_time.unwrap_or(now.max(entry.start_time));
+        let end = query.to.map_or(end, |to| end.min(to));
+        if end <= start {
+            continue;
+        }
+        total_ms += end - start;
+        entry_count += 1;
+
+        let parts = match query.group_by.period() {
+            Some(period) => calendar
+                .split(period, start, end)
+                .into_iter()
+                .map(|(first_day, ms)| {
+                    let key = (
+                        Some(calendar.period_key(period, first_day)),
+                        Some(calendar.start_of_day(first_day)),
+                    );
+                    (key, ms)
+                })
+                .collect(),
+            None => {
+                let key = match query.group_by {
+                    GroupBy::Project => entry.project_id.clone(),
+                    GroupBy::Category => entry.category_id.clone(),
+                    GroupBy::WorkType => Some(
+                        labels
+                            .get(&entry.id)
+                            .map_or(classify::UNSPECIFIED, String::as_str)
+                            .to_string(),
+                    ),
+                    GroupBy::Member => labels.get(&entry.id).cloned(),
+                    _ => Some(entry.description.trim().to_string()),
+                };
+                vec![((key, None), end - start)]
+            }
+        };
+        for (key, ms) in parts {
+            let group = groups.entry(key).or_default();
+            group.0 += ms;
+            group.1 += 1;
+        }
+    }
+
+    let mut groups: Vec<SummaryGroup> = groups
+        .into_iter()
+        .map(|((key, start), (total_ms, entry_count))| SummaryGroup {
+            key,
+            name: None,
+            start,
+            total_ms,
+            entry_count,
+            average_entry_ms: total_ms / entry_count as i64,
+        })
+        .collect();
+    if query.group_by.period().is_some() {
+        groups.sort_by_key(|group| group.start);
+    } else {
+        groups.sort_by(|a, b| b.total_ms.cmp(&a.total_ms).then_with(|| a.key.cmp(&b.key)));
+    }
+
+    SummaryReport {
+        group_by: query.group_by,
+        timezone: calendar.tz.name().to_string(),
+        from: query.from,
+        to: query.to,
+        total_ms,
+        entry_count,
+        average_entry_ms: if entry_count == 0 {
+            0
+        } else {
+            total_ms / entry_count as i64
+        },
+        groups,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR: i64 = 60 * 60 * 1000;
+    /// 2023-01-01T00:00:00Z, a Sunday.
+    const NEW_YEAR: i64 = 1_672_531_200_000;
+
+    fn entry(id: &str, start_time: i64, end_time: Option<i64>, description: &str) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            description: description.to_string(),
+            start_time,
+            end_time,
+            ..Default::default()
+        }
+    }
+
+    fn query(group_by: GroupBy) -> SummaryQuery {
+        SummaryQuery {
+            group_by,
+            ..SummaryQuery::default()
+        }
+    }
+
+    #[test]
+    fn test_entries_split_at_midnight_in_the_time_zone() {
+        // 23:00 to 01:00 in UTC is 18:00 to 20:00 the same day in New York.
+        let entries = [entry("e1", NEW_YEAR - HOUR, Some(NEW_YEAR + HOUR), "")];
+
+        let utc = summarize(
+            &entries,
+            &query(GroupBy::Day),
+            Calendar::default(),
+            0,
+            &HashMap::new(),
+        );
+        let keys: Vec<_> = utc.groups.iter().map(|g| g.key.as_deref()).collect();
+        assert_eq!(keys, [Some("2022-12-31"), Some("2023-01-01")]);
+        assert_eq!(utc.groups[0].total_ms, HOUR);
+        assert_eq!(utc.groups[1].start, Some(NEW_YEAR));
+        assert_eq!(utc.entry_count, 1);
+
+        let new_york = Calendar {
+            tz: chrono_tz::America::New_York,
+            ..Calendar::default()
+        };
+        let new_york = summarize(&entries, &query(GroupBy::Day), new_york, 0, &HashMap::new());
+        assert_eq!(new_york.groups.len(), 1);
+        assert_eq!(new_york.groups[0].key.as_deref(), Some("2022-12-31"));
+    }
+
+    #[test]
+    fn test_range_clips_entries_and_running_ones_end_now() {
+        let entries = [
+            entry("e1", NEW_YEAR - HOUR, Some(NEW_YEAR + HOUR), "Review"),
+            entry("e2", NEW_YEAR + 2 * HOUR, None, "Review"),
+            entry("e3", NEW_YEAR + 2 * HOUR, Some(NEW_YEAR + 3 * HOUR), "Plan"),
+        ];
+        let query = SummaryQuery {
+            from: Some(NEW_YEAR),
+            ..query(GroupBy::Description)
+        };
+        let report = summarize(
+            &entries,
+            &query,
+            Calendar::default(),
+            NEW_YEAR + 6 * HOUR,
+            &HashMap::new(),
+        );
+
+        assert_eq!(report.total_ms, 6 * HOUR);
+        assert_eq!(report.entry_count, 3);
+        assert_eq!(report.average_entry_ms, 2 * HOUR);
+        assert_eq!(
+            report.groups[0],
+            SummaryGroup {
+                key: Some("Review".to_string()),
+                name: None,
+                start: None,
+                total_ms: 5 * HOUR,
+                entry_count: 2,
+                average_entry_ms: 5 * HOUR / 2,
+            }
+        );
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        let entries = [
+            entry("sunday", NEW_YEAR, Some(NEW_YEAR + HOUR), ""),
+            entry(
+                "monday",
+                NEW_YEAR + 24 * HOUR,
+                Some(NEW_YEAR + 25 * HOUR),
+                "",
+            ),
+        ];
+        let report = summarize(
+            &entries,
+            &query(GroupBy::Week),
+            Calendar::default(),
+            0,
+            &HashMap::new(),
+        );
+        let keys: Vec<_> = report.groups.iter().map(|g| g.key.as_deref()).collect();
+        assert_eq!(keys, [Some("2022-W52"), Some("2023-W01")]);
+    }
+
+    #[tokio::test]
+    async fn test_project_groups_carry_names() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let project = crate::model::Project {
+            id: "p1".to_string(),
+            name: "Website".to_string(),
+            color: "#3b82f6".to_string(),
+            ..Default::default()
+        };
+        state
+            .db
+            .create_project(auth.user.id, project)
+            .unwrap()
+            .unwrap();
+        let mut tracked = entry("e1", NEW_YEAR, Some(NEW_YEAR + HOUR), "");
+        tracked.project_id = Some("p1".to_string());
+        state
+            .db
+            .create_entry(auth.user.id, tracked)
+            .unwrap()
+            .unwrap();
+        let loose = entry("e2", NEW_YEAR, Some(NEW_YEAR + 2 * HOUR), "");
+        state.db.create_entry(auth.user.id, loose).unwrap().unwrap();
+
+        let report = summary(State(state), auth, ValidQuery(query(GroupBy::Project)))
+            .await
+            .unwrap();
+        assert_eq!(report.groups[0].key, None);
+        assert_eq!(report.groups[1].name.as_deref(), Some("Website"));
+    }
+
+    #[tokio::test]
+    async fn test_work_type_groups_use_stored_work_types() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let standup = entry("e1", NEW_YEAR, Some(NEW_YEAR + HOUR), "Standup");
+        state
+            .db
+            .create_entry(auth.user.id, standup)
+            .unwrap()
+            .unwrap();
+        let bug = entry("e2", NEW_YEAR, Some(NEW_YEAR + 2 * HOUR), "Fix bug");
+        state.db.create_entry(auth.user.id, bug).unwrap().unwrap();
+
+        let report = summary(State(state), auth, ValidQuery(query(GroupBy::WorkType)))
+            .await
+            .unwrap();
+        let groups: Vec<_> = report
+            .groups
+            .iter()
+            .map(|group| (group.key.as_deref(), group.name.as_deref()))
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (Some("coding"), Some("Coding")),
+                (Some("meeting"), Some("Meeting"))
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_managers_see_team_totals() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let grace = AuthUser::for_tests(&state, "grace");
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap().id;
+        state
+            .db
+            .add_member(workspace, "grace", crate::db::Role::Member)
+            .unwrap()
+            .unwrap();
+        for (auth, hours) in [(&ada, 1), (&grace, 2)] {
+            let scope = state
+                .db
+                .membership(workspace, auth.user.id)
+                .unwrap()
+                .unwrap()
+                .sync_scope();
+            let mut changes = crate::model::Dataset::default();
+            // Both members use the same entry id.
+            changes.time_entries.insert(
+                "e1".to_string(),
+                entry("e1", NEW_YEAR, Some(NEW_YEAR + hours * HOUR), ""),
+            );
+            state
+                .db
+                .apply_sync_scope(scope, &changes, &Default::default(), 0)
+                .unwrap();
+        }
+
+        let team = SummaryQuery {
+            workspace: Some(workspace),
+            ..query(GroupBy::Member)
+        };
+        let report = summary(State(state.clone()), ada, ValidQuery(team))
+            .await
+            .unwrap();
+        assert_eq!(report.total_ms, 3 * HOUR);
+        let names: Vec<_> = report
+            .groups
+            .iter()
+            .map(|group| group.name.as_deref())
+            .collect();
+        assert_eq!(names, [Some("grace"), Some("ada")]);
+
+        let own = SummaryQuery {
+            workspace: Some(workspace),
+            ..query(GroupBy::Member)
+        };
+        let report = summary(State(state), grace, ValidQuery(own)).await.unwrap();
+        assert_eq!(report.total_ms, 2 * HOUR);
+    }
+}
@@ -0,0 +1,239 @@
+// backend/src/retention.rs
+//! REST endpoints for retention policies, which archive or delete entries
+//! older than a number of days.
+//!
+//! A background task applies every user's policies hourly; the endpoints
+//! here preview or trigger a run and list what earlier runs removed.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::auth::AuthUser;
+use crate::db::{
+    ArchivedEntry, RetentionAction, RetentionPolicy, RetentionRun, RunTrigger, UserId,
+};
+use crate::error::{join_path, ApiError, FieldError, ValidJson, ValidQuery};
+use crate::model::Validate;
+use crate::AppState;
+
+const MAX_OLDER_THAN_DAYS: i64 = 100 * 365;
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRequest {
+    pub older_than_days: i64,
+    pub action: RetentionAction,
+}
+
+impl Validate for PolicyRequest {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if !(1..=MAX_OLDER_THAN_DAYS).contains(&self.older_than_days) {
+            errors.push(FieldError::new(
+                join_path(path, "older_than_days"),
+                format!("must be between 1 and {MAX_OLDER_THAN_DAYS}"),
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Page {
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self {
+            limit: DEFAULT_PAGE_SIZE,
+            offset: 0,
+        }
+    }
+}
+
+impl Validate for Page {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit) {
+            errors.push(FieldError::new(
+                join_path(path, "limit"),
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if self.offset < 0 {
+            errors.push(FieldError::new(
+                join_path(path, "offset"),
+                "must not be negative",
+            ));
+        }
+    }
+}
+
+/// List the retention policies
+pub async fn list_policies(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<RetentionPolicy>>, ApiError> {
+    Ok(Json(state.db.list_retention_policies(auth.user.id)?))
+}
+
+/// Set the policy for entries whose project has none of its own
+pub async fn put_default_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(request): ValidJson<PolicyRequest>,
+) -> Result<Json<RetentionPolicy>, ApiError> {
+    set_policy(&state, auth.user.id, None, request)
+}
+
+/// Remove the policy for entries whose project has none of its own
+pub async fn delete_default_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    remove_policy(&state, auth.user.id, None)
+}
+
+/// Set a project's policy
+pub async fn put_project_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+    ValidJson(request): ValidJson<PolicyRequest>,
+) -> Result<Json<RetentionPolicy>, ApiError> {
+    set_policy(&state, auth.user.id, Some(&id), request)
+}
+
+/// Remove a project's policy
+pub async fn delete_project_policy(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    remove_policy(&state, auth.user.id, Some(&id))
+}
+
+/// What applying the policies now would remove, without removing it
+pub async fn preview(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<RetentionRun>, ApiError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let run = auth
+        .db(&state)
+        .apply_retention(auth.user.id, now, RunTrigger::Manual, true)?;
+    Ok(Json(run))
+}
+
+/// Apply the policies now instead of waiting for the background task
+pub async fn apply(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<RetentionRun>, ApiError> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let run = auth
+        .db(&state)
+        .apply_retention(auth.user.id, now, RunTrigger::Manual, false)?;
+    Ok(Json(run))
+}
+
+/// Earlier runs that removed entries, newest first
+pub async fn list_runs(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(page): ValidQuery<Page>,
+) -> Result<Json<Vec<RetentionRun>>, ApiError> {
+    let runs = state
+        .db
+        .list_retention_runs(auth.user.id, page.limit, page.offset)?;
+    Ok(Json(runs))
+}
+
+/// Entries archived by a policy, most recently started first
+pub async fn list_archive(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(page): ValidQuery<Page>,
+) -> Result<Json<Vec<ArchivedEntry>>, ApiError> {
+    let entries = state
+        .db
+        .list_archived_entries(auth.user.id, page.limit, page.offset)?;
+    Ok(Json(entries))
+}
+
+fn set_policy(
+    state: &AppState,
+    user: UserId,
+    project: Option<&str>,
+    request: PolicyRequest,
+) -> Result<Json<RetentionPolicy>, ApiError> {
+    let policy = state
+        .db
+        .set_retention_policy(user, project, request.older_than_days, request.action)?
+        .map_err(|rejection| ApiError::rejected("project", rejection))?;
+    Ok(Json(policy))
+}
+
+fn remove_policy(
+    state: &AppState,
+    user: UserId,
+    project: Option<&str>,
+) -> Result<StatusCode, ApiError> {
+    if state.db.remove_retention_policy(user, project)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound("retention policy"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TimeEntry;
+
+    #[tokio::test]
+    async fn test_preview_then_apply() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let entry = TimeEntry {
+            id: "e1".to_string(),
+            description: "Kickoff".to_string(),
+            start_time: 1_672_567_200_000,
+            end_time: Some(1_672_572_600_000),
+            ..Default::default()
+        };
+        state.db.create_entry(auth.user.id, entry).unwrap().unwrap();
+        let request = PolicyRequest {
+            older_than_days: 30,
+            action: RetentionAction::Delete,
+        };
+        let _ = put_default_policy(State(state.clone()), auth.clone(), ValidJson(request))
+            .await
+            .unwrap();
+
+        let preview = preview(State(state.clone()), auth.clone()).await.unwrap();
+        assert!(preview.dry_run);
+        assert_eq!(preview.deleted, 1);
+        assert!(state.db.find_entry(auth.user.id, "e1").unwrap().is_some());
+
+        let run = apply(State(state.clone()), auth.clone()).await.unwrap();
+        assert_eq!(run.deleted, 1);
+        assert!(state.db.find_entry(auth.user.id, "e1").unwrap().is_none());
+        let runs = list_runs(
+            State(state.clone()),
+            auth.clone(),
+            ValidQuery(Page::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(runs.len(), 1);
+
+        let missing = delete_project_policy(State(state), auth, Path("p1".to_string())).await;
+        assert!(matches!(missing, Err(ApiError::NotFound(_))));
+    }
+}
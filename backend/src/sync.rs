@@ -0,0 +1,337 @@
+// backend/src/sync.rs
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::db::{SyncDelta, WorkspaceId};
+use crate::error::{join_path, ApiError, FieldError, ValidJson};
+use crate::merge::Conflict;
+use crate::model::{Category, Dataset, Deletions, Project, Records, TimeEntry, Validate};
+use crate::workspaces;
+use crate::AppState;
+
+/// Records the client changed or deleted since its last sync.
+///
+/// A registered device can leave out `last_synced_at` and the server resumes
+/// from the cursor it stored for the device. Otherwise it is the cursor the
+/// server handed out on the previous sync (0 for a client that never synced);
+/// a device that lost a response can send its last cursor to replay it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SyncRequest {
+    #[serde(default)]
+    pub last_synced_at: Option<i64>,
+    pub time_entries: Records<TimeEntry>,
+    pub projects: Records<Project>,
+    pub categories: Records<Category>,
+    #[serde(default)]
+    pub deleted: Deletions,
+}
+
+impl Validate for SyncRequest {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.last_synced_at.is_some_and(|cursor| cursor < 0) {
+            errors.push(FieldError::new(
+                join_path(path, "last_synced_at"),
+                "must not be negative",
+            ));
+        }
+        self.time_entries
+            .validate(&join_path(path, "time_entries"), errors);
+        self.projects.validate(&join_path(path, "projects"), errors);
+        self.categories
+            .validate(&join_path(path, "categories"), errors);
+        self.deleted.validate(&join_path(path, "deleted"), errors);
+    }
+}
+
+/// Records changed by other clients since the request's cursor, plus the ids
+/// of records deleted in that window and any fields that had to be merged.
+///
+/// When `full_sync` is set the cursor was too old to know about every
+/// deletion: the records are the complete dataset and replace the client's.
+#[derive(Debug, Serialize)]
+pub struct SyncResponse {
+    pub last_synced_at: i64,
+    pub time_entries: Records<TimeEntry>,
+    pub projects: Records<Project>,
+    pub categories: Records<Category>,
+    pub deleted: Deletions,
+    pub conflicts: Vec<Conflict>,
+    pub full_sync: bool,
+}
+
+impl From<SyncDelta> for SyncResponse {
+    fn from(delta: SyncDelta) -> Self {
+        Self {
+            last_synced_at: delta.cursor,
+            time_entries: delta.changes.time_entries,
+            projects: delta.changes.projects,
+            categories: delta.changes.categories,
+            deleted: delta.deleted,
+            conflicts: delta.conflicts,
+            full_sync: delta.full_sync,
+        }
+    }
+}
+
+/// Get the last synced data for the user
+///
+/// Returns a full snapshot, for clients bootstrapping an empty store.
+pub async fn get_sync(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<SyncResponse>, ApiError> {
+    let snapshot = state.db.snapshot(auth.user.id)?;
+    if let Some(device) = auth.device {
+        state.db.record_device_sync(device, snapshot.cursor)?;
+    }
+
+    Ok(Json(SyncResponse::from(snapshot)))
+}
+
+/// Update the last synced data for the user
+///
+/// Stores the client's changes and deletions and answers with whatever
+/// changed elsewhere since its cursor.
+pub async fn post_sync(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(payload): ValidJson<SyncRequest>,
+) -> Result<Json<SyncResponse>, ApiError> {
+    let since = match (payload.last_synced_at, auth.device) {
+        (Some(cursor), _) => cursor,
+        (None, Some(device)) => state
+            .db
+            .find_device(auth.user.id, device)?
+            .map_or(0, |device| device.sync_cursor),
+        (None, None) => {
+            return Err(ApiError::Validation(vec![FieldError::new(
+                "last_synced_at",
+                "is required for a token without a device",
+            )]))
+        }
+    };
+
+    let changes = Dataset {
+        time_entries: payload.time_entries,
+        projects: payload.projects,
+        categories: payload.categories,
+    };
+    let delta = auth
+        .db(&state)
+        .apply_sync(auth.user.id, &changes, &payload.deleted, since)?;
+    if let Some(device) = auth.device {
+        state.db.record_device_sync(device, delta.cursor)?;
+    }
+
+    Ok(Json(SyncResponse::from(delta)))
+}
+
+/// Get the shared projects and categories of a workspace and the caller's
+/// entries in it
+///
+/// Devices keep their stored cursor for their account's own sync, so
+/// workspace syncs always send `last_synced_at`.
+pub async fn get_workspace_sync(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<WorkspaceId>,
+) -> Result<Json<SyncResponse>, ApiError> {
+    let membership = workspaces::membership(&state, &auth, id)?;
+    let snapshot = state.db.snapshot_scope(membership.sync_scope())?;
+    Ok(Json(SyncResponse::from(snapshot)))
+}
+
+/// Sync with a workspace
+///
+/// Members can change only their own entries, and only owners and admins the
+/// shared projects and categories. Other changes are not stored; the
+/// response carries the stored records so the client reverts them.
+pub async fn post_workspace_sync(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<WorkspaceId>,
+    ValidJson(payload): ValidJson<SyncRequest>,
+) -> Result<Json<SyncResponse>, ApiError> {
+    let membership = workspaces::membership(&state, &auth, id)?;
+    let since = payload.last_synced_at.ok_or_else(|| {
+        ApiError::Validation(vec![FieldError::new(
+            "last_synced_at",
+            "is required for a workspace sync",
+        )])
+    })?;
+
+    let changes = Dataset {
+        time_entries: payload.time_entries,
+        projects: payload.projects,
+        categories: payload.categories,
+    };
+    let delta = auth.db(&state).apply_sync_scope(
+        membership.sync_scope(),
+        &changes,
+        &payload.deleted,
+        since,
+    )?;
+    Ok(Json(SyncResponse::from(delta)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn device(state: &AppState, auth: &AuthUser, name: &str) -> AuthUser {
+        let device = state.db.create_device(auth.user.id, name).unwrap();
+        AuthUser {
+            device: Some(device.id),
+            ..auth.clone()
+        }
+    }
+
+    fn request(projects: Records<Project>, last_synced_at: Option<i64>) -> SyncRequest {
+        SyncRequest {
+            last_synced_at,
+            time_entries: Records::new(),
+            projects,
+            categories: Records::new(),
+            deleted: Deletions::default(),
+        }
+    }
+
+    fn project(id: &str, name: &str) -> Records<Project> {
+        let mut projects = Records::new();
+        projects.insert(
+            id.to_string(),
+            Project {
+                id: id.to_string(),
+                name: name.to_string(),
+                color: "#3b82f6".to_string(),
+                ..Default::default()
+            },
+        );
+        projects
+    }
+
+    #[tokio::test]
+    async fn test_post_sync_persists_for_get_sync() {
+        let state = AppState::for_tests();
+        let user = AuthUser::for_tests(&state, "ada");
+        let request = request(project("p1", "Default Project"), Some(0));
+
+        let response = post_sync(State(state.clone()), user.clone(), ValidJson(request))
+            .await
+            .unwrap();
+        assert!(response.projects.is_empty());
+
+        let response = get_sync(State(state), user).await.unwrap();
+        assert_eq!(response.projects["p1"].name, "Default Project");
+        assert_eq!(response.projects["p1"].version, 1);
+        assert!(response.time_entries.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_devices_resume_from_their_own_cursor() {
+        let state = AppState::for_tests();
+        let user = AuthUser::for_tests(&state, "ada");
+        let laptop = device(&state, &user, "Laptop");
+        let phone = device(&state, &user, "Phone");
+
+        let _ = post_sync(
+            State(state.clone()),
+            laptop,
+            ValidJson(request(project("p1", "Work"), None)),
+        )
+        .await
+        .unwrap();
+
+        let first = post_sync(
+            State(state.clone()),
+            phone.clone(),
+            ValidJson(request(Records::new(), None)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(first.projects["p1"].name, "Work");
+
+        let second = post_sync(
+            State(state.clone()),
+            phone.clone(),
+            ValidJson(request(Records::new(), None)),
+        )
+        .await
+        .unwrap();
+        assert!(second.projects.is_empty());
+
+        let stored = state
+            .db
+            .find_device(user.user.id, phone.device.unwrap())
+            .unwrap()
+            .unwrap();
+        assert_eq!(stored.sync_cursor, second.last_synced_at);
+    }
+
+    #[tokio::test]
+    async fn test_workspace_members_share_projects_but_not_entries() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let grace = AuthUser::for_tests(&state, "grace");
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap().id;
+        state
+            .db
+            .add_member(workspace, "grace", crate::db::Role::Member)
+            .unwrap()
+            .unwrap();
+
+        let mut shared = request(project("p1", "Website"), Some(0));
+        shared.time_entries.insert(
+            "e1".to_string(),
+            TimeEntry {
+                id: "e1".to_string(),
+                description: "Kickoff".to_string(),
+                start_time: 1_000,
+                end_time: Some(2_000),
+                project_id: Some("p1".to_string()),
+                ..Default::default()
+            },
+        );
+        let _ = post_workspace_sync(
+            State(state.clone()),
+            ada.clone(),
+            Path(workspace),
+            ValidJson(shared),
+        )
+        .await
+        .unwrap();
+
+        // A member's rename is refused and answered with the stored copy.
+        let rename = request(project("p1", "Renamed"), Some(0));
+        let response = post_workspace_sync(
+            State(state.clone()),
+            grace.clone(),
+            Path(workspace),
+            ValidJson(rename),
+        )
+        .await
+        .unwrap();
+        assert_eq!(response.projects["p1"].name, "Website");
+        assert!(response.time_entries.is_empty());
+
+        // The account's own store is separate from the workspace.
+        let personal = get_sync(State(state), ada).await.unwrap();
+        assert!(personal.projects.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_is_required_without_a_device() {
+        let state = AppState::for_tests();
+        let result = post_sync(
+            State(state.clone()),
+            AuthUser::for_tests(&state, "ada"),
+            ValidJson(request(Records::new(), None)),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+    }
+}
@@ -0,0 +1,411 @@
+// backend/src/targets.rs
+//! Progress towards each category's weekly target, and streaks of weeks that
+//! met it.
+//!
+//! Weeks are the user's: in their time zone, starting on their week-start
+//! day. Running entries count up to now, and an entry crossing into a new
+//! week counts towards both. The hours are summed by the database over the
+//! whole history, so streaks reach back past the reported weeks.
+//!
+//! Progress in a workspace is towards its shared categories' targets, over
+//! the caller's own entries in it, or the whole team's for owners and admins.
+use axum::{extract::State, Json};
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::auth::AuthUser;
+use crate::db::{Db, UserId, WorkspaceId};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::{Category, Validate};
+use crate::periods::{self, Calendar, Period};
+use crate::workspaces;
+use crate::AppState;
+
+const HOUR_MS: f64 = 60.0 * 60.0 * 1000.0;
+const MAX_WEEKS: usize = 104;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct TargetsQuery {
+    /// How many weeks to report, the current one included.
+    pub weeks: usize,
+    /// IANA name of the zone whose weeks are reported, if not the user's own.
+    pub timezone: Option<String>,
+    /// Track a workspace's categories instead of the caller's own.
+    pub workspace: Option<WorkspaceId>,
+}
+
+impl Default for TargetsQuery {
+    fn default() -> Self {
+        Self {
+            weeks: 4,
+            timezone: None,
+            workspace: None,
+        }
+    }
+}
+
+impl Validate for TargetsQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if self.weeks == 0 || self.weeks > MAX_WEEKS {
+            errors.push(FieldError::new(
+                join_path(path, "weeks"),
+                format!("must be between 1 and {MAX_WEEKS}"),
+            ));
+        }
+        if let Some(timezone) = &self.timezone {
+            periods::validate_timezone(timezone, &join_path(path, "timezone"), errors);
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TargetProgress {
+    pub category_id: String,
+    pub name: String,
+    pub color: String,
+    pub weekly_target_hours: f64,
+    /// Newest first; the first is the current week.
+    pub weeks: Vec<WeekProgress>,
+    pub streak: Streak,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct WeekProgress {
+    /// ISO week such as `2023-W01`, or the start date if weeks start on
+    /// another day than Monday.
+    pub week: String,
+    pub start: i64,
+    pub end: i64,
+    pub logged_hours: f64,
+    pub percent_of_target: f64,
+    pub met: bool,
+    /// For the current week: the total at its end if time keeps being logged
+    /// at the rate so far.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub projected_hours: Option<f64>,
+}
+
+/// Consecutive weeks that met the target.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct Streak {
+    /// Ends with the last finished week, plus the current week once it has
+    /// met the target.
+    pub current: usize,
+    pub longest: usize,
+}
+
+/// Weekly target progress and streaks for every category with a target
+pub async fn progress(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<TargetsQuery>,
+) -> Result<Json<Vec<TargetProgress>>, ApiError> {
+    let calendar = Calendar::for_user(&auth.user, query.timezone.as_deref());
+    let now = chrono::Utc::now().timestamp_millis();
+    let (categories, stores) = match query.workspace {
+        Some(workspace) => {
+            let membership = workspaces::membership(&state, &auth, workspace)?;
+            let stores = workspaces::report_stores(&state, &auth, workspace, &membership)?;
+            let stores: Vec<_> = stores.iter().map(|member| member.store).collect();
+            (state.db.list_categories(membership.shared_store)?, stores)
+        }
+        None => (state.db.list_categories(auth.user.id)?, vec![auth.user.id]),
+    };
+    let logged = logged_per_week(&state.db, &stores, calendar, now)?;
+    Ok(Json(target_progress(
+        &categories,
+        &logged,
+        calendar,
+        now,
+        query.weeks,
+    )))
+}
+
+/// Milliseconds logged per category and week across the entry `stores`, in
+/// every week from the one holding the first categorized entry up to the
+/// current one.
+fn logged_per_week(
+    db: &Db,
+    stores: &[UserId],
+    calendar: Calendar,
+    now: i64,
+) -> rusqlite::Result<HashMap<(String, NaiveDate), i64>> {
+    let current_week = calendar.period_start(Period::Week, calendar.local_date(now));
+    let mut first_start: Option<i64> = None;
+    for &store in stores {
+        if let Some(start) = db.first_categorized_start(store)? {
+            first_start = Some(first_start.map_or(start, |first| first.min(start)));
+        }
+    }
+    let mut week = match first_start {
+        Some(start) => calendar
+            .period_start(Period::Week, calendar.local_date(start))
+            .min(current_week),
+        None => current_week,
+    };
+    let mut weeks = Vec::new();
+    let mut bounds = Vec::new();
+    while week <= current_week {
+        let next = Period::Week.next(week);
+        weeks.push(week);
+        bounds.push((calendar.start_of_day(week), calendar.start_of_day(next)));
+        week = next;
+    }
+    let mut logged = HashMap::new();
+    for &store in stores {
+        for (category, week, ms) in db.logged_per_category(store, &bounds, now)? {
+            *logged.entry((category, weeks[week])).or_default() += ms;
+        }
+    }
+    Ok(logged)
+}
+
+fn target_progress(
+    categories: &[Category],
+    logged: &HashMap<(String, NaiveDate), i64>,
+    calendar: Calendar,
+    now: i64,
+    weeks: usize,
+) -> Vec<TargetProgress> {
+    let current_week = calendar.period_start(Period::Week, calendar.local_date(now));
+
+    let mut progress = Vec::new();
+    for category in categories {
+        let Some(target_hours) = category.weekly_target_hours.filter(|&hours| hours > 0.0) else {
+            continue;
+        };
+        let first_week = logged
+            .keys()
+            .filter(|(id, _)| *id == category.id)
+            .map(|&(_, week)| week)
+            .min()
+            .unwrap_or(current_week)
+            .min(current_week);
+
+        // Every week from the first one logged up to the current one,
+        // oldest first.
+        let mut history = Vec::new();
+        let mut week = first_week;
+        while week <= current_week {
+            let next = Period::Week.next(week);
+            let logged_ms = logged
+                .get(&(category.id.clone(), week))
+                .copied()
+                .unwrap_or(0);
+            let logged_hours = logged_ms as f64 / HOUR_MS;
+            let (start, end) = (calendar.start_of_day(week), calendar.start_of_day(next));
+            let projected_hours = (week == current_week).then(|| {
+                let elapsed = (now - start).max(1) as f64;
+                logged_hours * (end - start) as f64 / elapsed
+            });
+            history.push(WeekProgress {
+                week: calendar.period_key(Period::Week, week),
+                start,
+                end,
+                logged_hours,
+                percent_of_target: logged_hours / target_hours * 100.0,
+                met: logged_hours >= target_hours,
+                projected_hours,
+            });
+            week = next;
+        }
+
+        let streak = streak(&history);
+        history.reverse();
+        history.truncate(weeks);
+        progress.push(TargetProgress {
+            category_id: category.id.clone(),
+            name: category.name.clone(),
+            color: category.color.clone(),
+            weekly_target_hours: target_hours,
+            weeks: history,
+            streak,
+        });
+    }
+    progress
+}
+
+/// Streaks in `history`, oldest week first and the current week last. The
+/// current week only breaks a streak once it is over.
+fn streak(history: &[WeekProgress]) -> Streak {
+    let mut longest = 0;
+    let mut run = 0;
+    for week in history {
+        run = if week.met { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let counted = match history.split_last() {
+        Some((current, finished)) if !current.met => finished,
+        _ => history,
+    };
+    Streak {
+        current: counted.iter().rev().take_while(|week| week.met).count(),
+        longest,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{Dataset, Deletions, TimeEntry};
+
+    const HOUR: i64 = 60 * 60 * 1000;
+    const WEEK: i64 = 7 * 24 * HOUR;
+    /// 2023-01-02T00:00:00Z, the Monday starting ISO week 2023-W01.
+    const MONDAY: i64 = 1_672_617_600_000;
+    const USER: UserId = 1;
+
+    fn category(target: Option<f64>) -> Category {
+        Category {
+            id: "c1".to_string(),
+            name: "Work".to_string(),
+            color: "#10b981".to_string(),
+            weekly_target_hours: target,
+            ..Default::default()
+        }
+    }
+
+    fn entry(id: &str, start_time: i64, hours: i64) -> TimeEntry {
+        TimeEntry {
+            id: id.to_string(),
+            start_time,
+            end_time: Some(start_time + hours * HOUR),
+            category_id: Some("c1".to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// What the database sums up for `entries`.
+    fn logged(entries: &[TimeEntry], now: i64) -> HashMap<(String, NaiveDate), i64> {
+        let db = Db::open_in_memory().unwrap();
+        let mut changes = Dataset::default();
+        for entry in entries {
+            changes.time_entries.insert(entry.id.clone(), entry.clone());
+        }
+        db.apply_sync(USER, &changes, &Deletions::default(), 0)
+            .unwrap();
+        logged_per_week(&db, &[USER], Calendar::default(), now).unwrap()
+    }
+
+    #[test]
+    fn test_current_week_is_projected() {
+        let entries = [entry("e1", MONDAY, 4)];
+        // A day and a half into the week.
+        let now = MONDAY + 36 * HOUR;
+        let progress = target_progress(
+            &[category(Some(10.0))],
+            &logged(&entries, now),
+            Calendar::default(),
+            now,
+            4,
+        );
+
+        let current = &progress[0].weeks[0];
+        assert_eq!(current.week, "2023-W01");
+        assert_eq!(current.logged_hours, 4.0);
+        assert_eq!(current.percent_of_target, 40.0);
+        assert_eq!(current.projected_hours, Some(4.0 * 168.0 / 36.0));
+        assert!(!current.met);
+    }
+
+    #[test]
+    fn test_streaks_count_met_weeks() {
+        let entries = [
+            entry("w1", MONDAY, 10),
+            entry("w3", MONDAY + 2 * WEEK, 10),
+            entry("w4", MONDAY + 3 * WEEK, 10),
+            entry("w5", MONDAY + 4 * WEEK, 2),
+        ];
+        let now = MONDAY + 4 * WEEK + 3 * HOUR;
+        let progress = target_progress(
+            &[category(Some(10.0))],
+            &logged(&entries, now),
+            Calendar::default(),
+            now,
+            2,
+        );
+
+        // The unfinished current week does not break the streak yet, and
+        // the streak reaches back past the two reported weeks.
+        assert_eq!(
+            progress[0].streak,
+            Streak {
+                current: 2,
+                longest: 2
+            }
+        );
+        let weeks: Vec<_> = progress[0].weeks.iter().map(|w| w.week.as_str()).collect();
+        assert_eq!(weeks, ["2023-W05", "2023-W04"]);
+        assert_eq!(progress[0].weeks[1].projected_hours, None);
+    }
+
+    #[test]
+    fn test_categories_without_target_are_left_out() {
+        let entries = [entry("e1", MONDAY, 1)];
+        let categories = [category(None), category(Some(0.0))];
+        assert!(target_progress(
+            &categories,
+            &logged(&entries, MONDAY + HOUR),
+            Calendar::default(),
+            MONDAY + HOUR,
+            4
+        )
+        .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_workspace_targets_cover_the_team_for_managers() {
+        let state = AppState::for_tests();
+        let [ada, grace, linus] =
+            ["ada", "grace", "linus"].map(|name| AuthUser::for_tests(&state, name));
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap().id;
+        state
+            .db
+            .add_member(workspace, "grace", crate::db::Role::Member)
+            .unwrap()
+            .unwrap();
+        let start = chrono::Utc::now().timestamp_millis() - 3 * HOUR;
+        for (auth, hours) in [(&ada, 1), (&grace, 2)] {
+            let scope = state
+                .db
+                .membership(workspace, auth.user.id)
+                .unwrap()
+                .unwrap()
+                .sync_scope();
+            let mut changes = Dataset::default();
+            if auth.user.id == ada.user.id {
+                changes
+                    .categories
+                    .insert("c1".to_string(), category(Some(10.0)));
+            }
+            changes
+                .time_entries
+                .insert("e1".to_string(), entry("e1", start, hours));
+            state
+                .db
+                .apply_sync_scope(scope, &changes, &Default::default(), 0)
+                .unwrap();
+        }
+
+        let query = || TargetsQuery {
+            workspace: Some(workspace),
+            ..TargetsQuery::default()
+        };
+        // Summed over weeks, in case the entries cross into this one.
+        let logged = |progress: &[TargetProgress]| -> f64 {
+            progress[0].weeks.iter().map(|week| week.logged_hours).sum()
+        };
+        let team = progress(State(state.clone()), ada, ValidQuery(query()))
+            .await
+            .unwrap();
+        assert!((logged(&team) - 3.0).abs() < 1e-9);
+        let own = progress(State(state.clone()), grace, ValidQuery(query()))
+            .await
+            .unwrap();
+        assert!((logged(&own) - 2.0).abs() < 1e-9);
+        let result = progress(State(state), linus, ValidQuery(query())).await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+}
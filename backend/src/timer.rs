@@ -0,0 +1,144 @@
+// backend/src/timer.rs
+//! The running timer, owned by the server so that at most one entry per user
+//! is ever running, whichever device started it.
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::{self, AuthUser};
+use crate::db::TimerChange;
+use crate::error::{join_path, ApiError, FieldError, ValidJson};
+use crate::model::{TimeEntry, Validate};
+use crate::AppState;
+
+/// The entry to start. The server sets its start time; the id is generated
+/// unless the client wants to pick it.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct StartTimer {
+    pub id: Option<String>,
+    pub description: String,
+    pub project_id: Option<String>,
+    pub category_id: Option<String>,
+}
+
+impl Validate for StartTimer {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        let ids = [
+            ("id", &self.id),
+            ("projectId", &self.project_id),
+            ("categoryId", &self.category_id),
+        ];
+        for (field, id) in ids {
+            if id.as_ref().is_some_and(|id| id.is_empty()) {
+                errors.push(FieldError::new(join_path(path, field), "must not be empty"));
+            }
+        }
+    }
+}
+
+/// The running entry after a timer call, and any entries the call stopped.
+#[derive(Debug, Serialize)]
+pub struct TimerResponse {
+    pub running: Option<TimeEntry>,
+    pub stopped: Vec<TimeEntry>,
+}
+
+impl From<TimerChange> for TimerResponse {
+    fn from(change: TimerChange) -> Self {
+        Self {
+            running: change.running,
+            stopped: change.stopped,
+        }
+    }
+}
+
+/// Start a timer, stopping the one already running
+pub async fn start_timer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(request): ValidJson<StartTimer>,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let entry = TimeEntry {
+        id: request.id.unwrap_or_else(|| auth::random_hex(16)),
+        description: request.description,
+        project_id: request.project_id,
+        category_id: request.category_id,
+        ..Default::default()
+    };
+    let change = auth
+        .db(&state)
+        .start_timer(auth.user.id, entry)?
+        .map_err(|rejection| ApiError::rejected("time entry", rejection))?;
+    Ok(Json(TimerResponse::from(change)))
+}
+
+/// Stop the running timer, if any
+pub async fn stop_timer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let change = auth.db(&state).stop_timer(auth.user.id)?;
+    Ok(Json(TimerResponse::from(change)))
+}
+
+/// Get the running timer
+pub async fn current_timer(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<TimerResponse>, ApiError> {
+    let running = state.db.running_entry(auth.user.id)?;
+    Ok(Json(TimerResponse {
+        running,
+        stopped: Vec::new(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_start_stop_and_current() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let start = || StartTimer {
+            description: "Standup".to_string(),
+            ..StartTimer::default()
+        };
+
+        let first = start_timer(State(state.clone()), auth.clone(), ValidJson(start()))
+            .await
+            .unwrap();
+        let first_id = first.running.as_ref().unwrap().id.clone();
+        assert_eq!(first_id.len(), 32);
+
+        let second = start_timer(State(state.clone()), auth.clone(), ValidJson(start()))
+            .await
+            .unwrap();
+        assert_eq!(second.stopped[0].id, first_id);
+
+        let current = current_timer(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(current.running, second.running);
+
+        let stopped = stop_timer(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        assert_eq!(stopped.stopped.len(), 1);
+        let current = current_timer(State(state), auth).await.unwrap();
+        assert_eq!(current.running, None);
+    }
+
+    #[test]
+    fn test_empty_ids_are_rejected() {
+        let request = StartTimer {
+            id: Some(String::new()),
+            project_id: Some(String::new()),
+            ..StartTimer::default()
+        };
+        let mut errors = Vec::new();
+        request.validate("", &mut errors);
+        assert_eq!(errors.len(), 2);
+    }
+}
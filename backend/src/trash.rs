@@ -0,0 +1,516 @@
+// backend/src/trash.rs
+//! REST endpoints for the trash of deleted records.
+//!
+//! Deletions from any client land here first, so an accidental prune can be
+//! undone; a background task purges records after `retention.trash_days`.
+//!
+//! A workspace's shared projects and categories have a trash of their own,
+//! which its owners and admins manage under `/workspaces/{id}/trash`. So do
+//! each member's entries in it, under `/workspaces/{id}/members/{user_id}/trash`:
+//! members manage their own, owners and admins everyone's.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::db::{RecordKind, Rejection, Restored, TrashedRecord, UserId, WorkspaceId};
+use crate::error::{join_path, ApiError, FieldError, ValidQuery};
+use crate::model::Validate;
+use crate::workspaces;
+use crate::AppState;
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 1000;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct ListTrash {
+    pub kind: Option<RecordKind>,
+    pub limit: i64,
+    pub offset: i64,
+}
+
+impl Default for ListTrash {
+    fn default() -> Self {
+        Self {
+            kind: None,
+            limit: DEFAULT_PAGE_SIZE,
+            offset: 0,
+        }
+    }
+}
+
+impl Validate for ListTrash {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if !(1..=MAX_PAGE_SIZE).contains(&self.limit) {
+            errors.push(FieldError::new(
+                join_path(path, "limit"),
+                format!("must be between 1 and {MAX_PAGE_SIZE}"),
+            ));
+        }
+        if self.offset < 0 {
+            errors.push(FieldError::new(
+                join_path(path, "offset"),
+                "must not be negative",
+            ));
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct EmptiedTrash {
+    pub purged: usize,
+}
+
+/// List deleted records, most recently deleted first
+pub async fn list_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ListTrash>,
+) -> Result<Json<Vec<TrashedRecord>>, ApiError> {
+    let records = state
+        .db
+        .list_trash(auth.user.id, query.kind, query.limit, query.offset)?;
+    Ok(Json(records))
+}
+
+/// Restore one deleted record
+pub async fn restore(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((kind, id)): Path<(RecordKind, String)>,
+) -> Result<Json<Restored>, ApiError> {
+    restore_in(&state, &auth, auth.user.id, kind, &id).map(Json)
+}
+
+/// Restore everything the most recent deletion removed
+pub async fn undo(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Restored>, ApiError> {
+    undo_in(&state, &auth, auth.user.id).map(Json)
+}
+
+/// Delete one record from the trash for good
+pub async fn purge(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((kind, id)): Path<(RecordKind, String)>,
+) -> Result<StatusCode, ApiError> {
+    purge_in(&state, auth.user.id, kind, &id)
+}
+
+/// Delete everything in the trash for good
+pub async fn empty(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<EmptiedTrash>, ApiError> {
+    let purged = state.db.empty_trash(auth.user.id)?;
+    Ok(Json(EmptiedTrash { purged }))
+}
+
+/// List a workspace's deleted projects and categories
+pub async fn list_workspace_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace): Path<WorkspaceId>,
+    ValidQuery(query): ValidQuery<ListTrash>,
+) -> Result<Json<Vec<TrashedRecord>>, ApiError> {
+    let store = shared_store(&state, &auth, workspace)?;
+    let records = state
+        .db
+        .list_trash(store, query.kind, query.limit, query.offset)?;
+    Ok(Json(records))
+}
+
+/// Restore one of a workspace's deleted projects or categories
+pub async fn restore_in_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, kind, id)): Path<(WorkspaceId, RecordKind, String)>,
+) -> Result<Json<Restored>, ApiError> {
+    let store = shared_store(&state, &auth, workspace)?;
+    restore_in(&state, &auth, store, kind, &id).map(Json)
+}
+
+/// Restore what the most recent deletion in a workspace removed
+pub async fn undo_in_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace): Path<WorkspaceId>,
+) -> Result<Json<Restored>, ApiError> {
+    let store = shared_store(&state, &auth, workspace)?;
+    undo_in(&state, &auth, store).map(Json)
+}
+
+/// Delete one of a workspace's records from its trash for good
+pub async fn purge_in_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, kind, id)): Path<(WorkspaceId, RecordKind, String)>,
+) -> Result<StatusCode, ApiError> {
+    let store = shared_store(&state, &auth, workspace)?;
+    purge_in(&state, store, kind, &id)
+}
+
+/// Delete everything in a workspace's trash for good
+pub async fn empty_workspace_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(workspace): Path<WorkspaceId>,
+) -> Result<Json<EmptiedTrash>, ApiError> {
+    let store = shared_store(&state, &auth, workspace)?;
+    let purged = state.db.empty_trash(store)?;
+    Ok(Json(EmptiedTrash { purged }))
+}
+
+/// List a member's deleted entries in a workspace
+pub async fn list_member_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member)): Path<(WorkspaceId, UserId)>,
+    ValidQuery(query): ValidQuery<ListTrash>,
+) -> Result<Json<Vec<TrashedRecord>>, ApiError> {
+    let (store, _) = member_trash(&state, &auth, workspace, member, false)?;
+    let records = state
+        .db
+        .list_trash(store, query.kind, query.limit, query.offset)?;
+    Ok(Json(records))
+}
+
+/// Restore one of a member's deleted entries in a workspace
+pub async fn restore_for_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member, kind, id)): Path<(WorkspaceId, UserId, RecordKind, String)>,
+) -> Result<Json<Restored>, ApiError> {
+    let (store, shared) = member_trash(&state, &auth, workspace, member, true)?;
+    auth.db(&state)
+        .restore_from_trash_in(store, shared, kind, &id)?
+        .map(Json)
+        .map_err(|rejection| ApiError::rejected(resource(kind), rejection))
+}
+
+/// Restore what the most recent deletion of a member's entries in a
+/// workspace removed
+pub async fn undo_for_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member)): Path<(WorkspaceId, UserId)>,
+) -> Result<Json<Restored>, ApiError> {
+    let (store, shared) = member_trash(&state, &auth, workspace, member, true)?;
+    auth.db(&state)
+        .undo_last_delete_in(store, shared)?
+        .map(Json)
+        .map_err(undo_rejected)
+}
+
+/// Delete one of a member's entries in a workspace from the trash for good
+pub async fn purge_for_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member, kind, id)): Path<(WorkspaceId, UserId, RecordKind, String)>,
+) -> Result<StatusCode, ApiError> {
+    let (store, _) = member_trash(&state, &auth, workspace, member, true)?;
+    purge_in(&state, store, kind, &id)
+}
+
+/// Delete all of a member's entries in a workspace from the trash for good
+pub async fn empty_member_trash(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((workspace, member)): Path<(WorkspaceId, UserId)>,
+) -> Result<Json<EmptiedTrash>, ApiError> {
+    let (store, _) = member_trash(&state, &auth, workspace, member, true)?;
+    let purged = state.db.empty_trash(store)?;
+    Ok(Json(EmptiedTrash { purged }))
+}
+
+/// The store of `workspace`'s shared records, if the caller manages it.
+fn shared_store(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+) -> Result<UserId, ApiError> {
+    let membership = workspaces::manager(
+        state,
+        auth,
+        workspace,
+        "only owners and admins can manage the workspace's trash",
+    )?;
+    Ok(membership.shared_store)
+}
+
+/// The store of `member`'s entries in `workspace` and that of the shared
+/// records they refer to. Changing the trash takes a role that can log
+/// entries.
+fn member_trash(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+    member: UserId,
+    write: bool,
+) -> Result<(UserId, UserId), ApiError> {
+    let (membership, store) = workspaces::member_entries(
+        state,
+        auth,
+        workspace,
+        member,
+        "only owners and admins can manage other members' trash",
+    )?;
+    if write && !membership.role.can_log_entries() {
+        return Err(ApiError::Forbidden("viewers cannot change entries"));
+    }
+    Ok((store, membership.shared_store))
+}
+
+fn restore_in(
+    state: &AppState,
+    auth: &AuthUser,
+    store: UserId,
+    kind: RecordKind,
+    id: &str,
+) -> Result<Restored, ApiError> {
+    auth.db(state)
+        .restore_from_trash(store, kind, id)?
+        .map_err(|rejection| ApiError::rejected(resource(kind), rejection))
+}
+
+fn undo_in(state: &AppState, auth: &AuthUser, store: UserId) -> Result<Restored, ApiError> {
+    auth.db(state)
+        .undo_last_delete(store)?
+        .map_err(undo_rejected)
+}
+
+fn undo_rejected(rejection: Rejection) -> ApiError {
+    match rejection {
+        Rejection::NotFound => ApiError::NotFound("deletion to undo"),
+        rejection => ApiError::rejected("record", rejection),
+    }
+}
+
+fn purge_in(
+    state: &AppState,
+    store: UserId,
+    kind: RecordKind,
+    id: &str,
+) -> Result<StatusCode, ApiError> {
+    if state.db.purge_from_trash(store, kind, id)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::NotFound(resource(kind)))
+    }
+}
+
+fn resource(kind: RecordKind) -> &'static str {
+    match kind {
+        RecordKind::TimeEntries => "time entry",
+        RecordKind::Projects => "project",
+        RecordKind::Categories => "category",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Role;
+    use crate::model::{Dataset, Deletions, Project, TimeEntry};
+
+    #[tokio::test]
+    async fn test_delete_then_undo() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let result = undo(State(state.clone()), auth.clone()).await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+
+        let entry = TimeEntry {
+            id: "e1".to_string(),
+            description: "Review".to_string(),
+            start_time: 1_000,
+            end_time: Some(2_000),
+            ..Default::default()
+        };
+        state.db.create_entry(auth.user.id, entry).unwrap().unwrap();
+        assert!(state.db.delete_entry(auth.user.id, "e1").unwrap());
+
+        let trash = list_trash(
+            State(state.clone()),
+            auth.clone(),
+            ValidQuery(ListTrash::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(trash[0].kind, RecordKind::TimeEntries);
+
+        let restored = undo(State(state.clone()), auth.clone()).await.unwrap();
+        assert_eq!(restored.time_entries.len(), 1);
+        assert!(state.db.find_entry(auth.user.id, "e1").unwrap().is_some());
+
+        let result = purge(
+            State(state),
+            auth,
+            Path((RecordKind::TimeEntries, "e1".to_string())),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_managers_restore_shared_records() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let grace = AuthUser::for_tests(&state, "grace");
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap().id;
+        state
+            .db
+            .add_member(workspace, "grace", Role::Member)
+            .unwrap()
+            .unwrap();
+        let store = state
+            .db
+            .membership(workspace, ada.user.id)
+            .unwrap()
+            .unwrap()
+            .shared_store;
+
+        let project = Project {
+            id: "p1".to_string(),
+            name: "Client".to_string(),
+            color: "#3b82f6".to_string(),
+            ..Default::default()
+        };
+        state.db.create_project(store, project).unwrap().unwrap();
+        state.db.delete_project(store, "p1", None).unwrap().unwrap();
+
+        let result = list_workspace_trash(
+            State(state.clone()),
+            grace.clone(),
+            Path(workspace),
+            ValidQuery(ListTrash::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        let result = undo_in_workspace(State(state.clone()), grace, Path(workspace)).await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+
+        let trash = list_workspace_trash(
+            State(state.clone()),
+            ada.clone(),
+            Path(workspace),
+            ValidQuery(ListTrash::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(trash[0].kind, RecordKind::Projects);
+        assert!(state
+            .db
+            .list_trash(ada.user.id, None, 10, 0)
+            .unwrap()
+            .is_empty());
+
+        let restored = restore_in_workspace(
+            State(state.clone()),
+            ada,
+            Path((workspace, RecordKind::Projects, "p1".to_string())),
+        )
+        .await
+        .unwrap();
+        assert_eq!(restored.projects.len(), 1);
+        assert!(state.db.find_project(store, "p1").unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_members_restore_their_workspace_entries() {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let grace = AuthUser::for_tests(&state, "grace");
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap().id;
+        state
+            .db
+            .add_member(workspace, "grace", Role::Member)
+            .unwrap()
+            .unwrap();
+        let membership = state
+            .db
+            .membership(workspace, grace.user.id)
+            .unwrap()
+            .unwrap();
+
+        let project = Project {
+            id: "p1".to_string(),
+            name: "Client".to_string(),
+            color: "#3b82f6".to_string(),
+            ..Default::default()
+        };
+        state
+            .db
+            .create_project(membership.shared_store, project)
+            .unwrap()
+            .unwrap();
+        let mut changes = Dataset::default();
+        changes.time_entries.insert(
+            "e1".to_string(),
+            TimeEntry {
+                id: "e1".to_string(),
+                description: "Review".to_string(),
+                start_time: 1_000,
+                end_time: Some(2_000),
+                project_id: Some("p1".to_string()),
+                ..Default::default()
+            },
+        );
+        let scope = membership.sync_scope();
+        state
+            .db
+            .apply_sync_scope(scope, &changes, &Deletions::default(), 0)
+            .unwrap();
+        let deleted = Deletions {
+            time_entries: vec!["e1".to_string()],
+            ..Deletions::default()
+        };
+        state
+            .db
+            .apply_sync_scope(scope, &Dataset::default(), &deleted, 0)
+            .unwrap();
+
+        let result = list_member_trash(
+            State(state.clone()),
+            grace.clone(),
+            Path((workspace, ada.user.id)),
+            ValidQuery(ListTrash::default()),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+        let trash = list_member_trash(
+            State(state.clone()),
+            ada,
+            Path((workspace, grace.user.id)),
+            ValidQuery(ListTrash::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(trash[0].id, "e1");
+
+        let restored = restore_for_member(
+            State(state.clone()),
+            grace.clone(),
+            Path((
+                workspace,
+                grace.user.id,
+                RecordKind::TimeEntries,
+                "e1".to_string(),
+            )),
+        )
+        .await
+        .unwrap();
+        assert_eq!(restored.time_entries.len(), 1);
+        assert!(state
+            .db
+            .find_entry(membership.entry_store, "e1")
+            .unwrap()
+            .is_some());
+    }
+}
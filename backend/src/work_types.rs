@@ -0,0 +1,117 @@
+// backend/src/work_types.rs
+//! REST endpoints for a user's work-type rules and reclassifying entries.
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::auth::AuthUser;
+use crate::classify::{Classifier, RuleSet};
+use crate::db::{Db, UserId};
+use crate::error::{join_path, ApiError, FieldError, ValidJson, ValidQuery};
+use crate::model::Validate;
+use crate::AppState;
+
+/// Which entries to classify again; by default all of them.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct ReclassifyQuery {
+    /// Only entries starting at or after this time.
+    pub from: Option<i64>,
+    /// Only entries starting before this time.
+    pub to: Option<i64>,
+}
+
+impl Validate for ReclassifyQuery {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if to < from {
+                errors.push(FieldError::new(
+                    join_path(path, "to"),
+                    "must not be before from",
+                ));
+            }
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct Reclassified {
+    pub reclassified_entries: usize,
+}
+
+/// The user's rules, or the defaults while they have none of their own.
+pub fn rules_for(db: &Db, user: UserId) -> rusqlite::Result<RuleSet> {
+    Ok(db.work_type_rules(user)?.unwrap_or_default())
+}
+
+/// Get the work-type rules
+pub async fn get_rules(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<RuleSet>, ApiError> {
+    Ok(Json(rules_for(&state.db, auth.user.id)?))
+}
+
+/// Replace the work-type rules
+///
+/// Entries classified earlier keep their work type until reclassified.
+pub async fn put_rules(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(rules): ValidJson<RuleSet>,
+) -> Result<Json<RuleSet>, ApiError> {
+    state.db.set_work_type_rules(auth.user.id, &rules)?;
+    Ok(Json(rules))
+}
+
+/// Go back to the default work-type rules
+pub async fn reset_rules(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<StatusCode, ApiError> {
+    state.db.reset_work_type_rules(auth.user.id)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Classify entries again with the current rules
+pub async fn reclassify(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidQuery(query): ValidQuery<ReclassifyQuery>,
+) -> Result<Json<Reclassified>, ApiError> {
+    let user = auth.user.id;
+    let classifier = Classifier::new(&rules_for(&state.db, user)?);
+    let reclassified_entries =
+        state
+            .db
+            .reclassify_entries(user, &classifier, query.from, query.to)?;
+    Ok(Json(Reclassified {
+        reclassified_entries,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rules_default_until_replaced() {
+        let state = AppState::for_tests();
+        let auth = AuthUser::for_tests(&state, "ada");
+        let defaults = get_rules(State(state.clone()), auth.clone()).await.unwrap();
+        assert_eq!(defaults.0, RuleSet::default());
+
+        let mut rules = RuleSet::default();
+        rules.work_types.truncate(1);
+        let _ = put_rules(State(state.clone()), auth.clone(), ValidJson(rules.clone()))
+            .await
+            .unwrap();
+        let stored = get_rules(State(state.clone()), auth.clone()).await.unwrap();
+        assert_eq!(stored.0, rules);
+
+        reset_rules(State(state.clone()), auth.clone())
+            .await
+            .unwrap();
+        let reset = get_rules(State(state), auth).await.unwrap();
+        assert_eq!(reset.0, RuleSet::default());
+    }
+}
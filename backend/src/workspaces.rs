@@ -0,0 +1,392 @@
+// backend/src/workspaces.rs
+//! REST endpoints for workspaces and their members.
+//!
+//! A workspace holds projects and categories its members share; each member
+//! logs their own entries against them. Owners and admins manage the shared
+//! records and the members, and see the whole team's time in reports.
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+
+use crate::auth::AuthUser;
+use crate::db::{Member, MemberStore, Membership, Rejection, Role, UserId, Workspace, WorkspaceId};
+use crate::error::{ApiError, FieldError, ValidJson};
+use crate::model::{nullable, require_name, validate_rate, Validate};
+use crate::AppState;
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewWorkspace {
+    pub name: String,
+}
+
+impl Validate for NewWorkspace {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        require_name(&self.name, path, errors);
+    }
+}
+
+/// Fields to change on a workspace; absent fields are kept. `hourly_rate`
+/// and `currency` can be set to null to clear the default rate.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WorkspacePatch {
+    pub name: Option<String>,
+    #[serde(deserialize_with = "nullable")]
+    pub hourly_rate: Option<Option<f64>>,
+    #[serde(deserialize_with = "nullable")]
+    pub currency: Option<Option<String>>,
+}
+
+impl Validate for WorkspacePatch {
+    fn validate(&self, path: &str, errors: &mut Vec<FieldError>) {
+        if let Some(name) = &self.name {
+            require_name(name, path, errors);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewMember {
+    pub username: String,
+    pub role: Role,
+}
+
+impl Validate for NewMember {
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemberPatch {
+    pub role: Role,
+}
+
+impl Validate for MemberPatch {
+    fn validate(&self, _path: &str, _errors: &mut Vec<FieldError>) {}
+}
+
+const MANAGE_MEMBERS: &str = "only owners and admins can manage members";
+
+/// The caller's membership of `workspace`. Workspaces they do not belong to
+/// are reported as missing.
+pub fn membership(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+) -> Result<Membership, ApiError> {
+    state
+        .db
+        .membership(workspace, auth.user.id)?
+        .ok_or(ApiError::NotFound("workspace"))
+}
+
+/// The caller's membership of `workspace`, if they are an owner or admin.
+/// Otherwise the request is refused with `forbidden`.
+pub fn manager(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+    forbidden: &'static str,
+) -> Result<Membership, ApiError> {
+    let membership = membership(state, auth, workspace)?;
+    if !membership.role.is_manager() {
+        return Err(ApiError::Forbidden(forbidden));
+    }
+    Ok(membership)
+}
+
+/// The caller's membership of `workspace` and the store of `member`'s
+/// entries in it. Members reach their own entries; owners and admins reach
+/// everyone's, including those of members who left. Otherwise the request
+/// is refused with `forbidden`.
+pub fn member_entries(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+    member: UserId,
+    forbidden: &'static str,
+) -> Result<(Membership, UserId), ApiError> {
+    let membership = membership(state, auth, workspace)?;
+    if member == auth.user.id {
+        return Ok((membership, membership.entry_store));
+    }
+    if !membership.role.is_manager() {
+        return Err(ApiError::Forbidden(forbidden));
+    }
+    let store = state
+        .db
+        .member_stores(workspace)?
+        .into_iter()
+        .find(|store| store.user_id == member)
+        .ok_or(ApiError::NotFound("member"))?;
+    Ok((membership, store.store))
+}
+
+/// The entry stores a report over `workspace` covers: every member's for
+/// owners and admins, the caller's own otherwise.
+pub fn report_stores(
+    state: &AppState,
+    auth: &AuthUser,
+    workspace: WorkspaceId,
+    membership: &Membership,
+) -> Result<Vec<MemberStore>, ApiError> {
+    if membership.role.is_manager() {
+        Ok(state.db.member_stores(workspace)?)
+    } else {
+        Ok(vec![MemberStore {
+            user_id: auth.user.id,
+            username: auth.user.username.clone(),
+            store: membership.entry_store,
+        }])
+    }
+}
+
+/// Only owners can make or unmake owners.
+fn check_owner_change(membership: &Membership, roles: &[Role]) -> Result<(), ApiError> {
+    if roles.contains(&Role::Owner) && membership.role != Role::Owner {
+        return Err(ApiError::Forbidden(
+            "only owners can appoint or remove owners",
+        ));
+    }
+    Ok(())
+}
+
+/// List the caller's workspaces
+pub async fn list_workspaces(
+    State(state): State<AppState>,
+    auth: AuthUser,
+) -> Result<Json<Vec<Workspace>>, ApiError> {
+    Ok(Json(state.db.list_workspaces(auth.user.id)?))
+}
+
+/// Create a workspace owned by the caller
+pub async fn create_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    ValidJson(request): ValidJson<NewWorkspace>,
+) -> Result<(StatusCode, Json<Workspace>), ApiError> {
+    let workspace = state
+        .db
+        .create_workspace(auth.user.id, request.name.trim())?;
+    Ok((StatusCode::CREATED, Json(workspace)))
+}
+
+/// Rename a workspace or change its default rate
+pub async fn update_workspace(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<WorkspaceId>,
+    ValidJson(patch): ValidJson<WorkspacePatch>,
+) -> Result<Json<Workspace>, ApiError> {
+    manager(
+        &state,
+        &auth,
+        id,
+        "only owners and admins can change the workspace",
+    )?;
+    let current = state
+        .db
+        .workspace(id, auth.user.id)?
+        .ok_or(ApiError::NotFound("workspace"))?;
+    let name = patch
+        .name
+        .as_deref()
+        .map_or(current.name.as_str(), str::trim);
+    let hourly_rate = patch.hourly_rate.unwrap_or(current.hourly_rate);
+    let currency = patch.currency.unwrap_or(current.currency);
+
+    let mut errors = Vec::new();
+    validate_rate(
+        hourly_rate,
+        currency.as_deref(),
+        ("hourly_rate", "currency"),
+        &mut errors,
+    );
+    if !errors.is_empty() {
+        return Err(ApiError::Validation(errors));
+    }
+    let workspace = state
+        .db
+        .update_workspace(id, auth.user.id, name, hourly_rate, currency.as_deref())?
+        .ok_or(ApiError::NotFound("workspace"))?;
+    Ok(Json(workspace))
+}
+
+/// List a workspace's members
+pub async fn list_members(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<WorkspaceId>,
+) -> Result<Json<Vec<Member>>, ApiError> {
+    membership(&state, &auth, id)?;
+    Ok(Json(state.db.list_members(id)?))
+}
+
+/// Add an account to a workspace
+pub async fn add_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path(id): Path<WorkspaceId>,
+    ValidJson(request): ValidJson<NewMember>,
+) -> Result<(StatusCode, Json<Member>), ApiError> {
+    let caller = manager(&state, &auth, id, MANAGE_MEMBERS)?;
+    check_owner_change(&caller, &[request.role])?;
+    let member = state
+        .db
+        .add_member(id, request.username.trim(), request.role)?
+        .map_err(|rejection| match rejection {
+            Rejection::NotFound => ApiError::NotFound("user"),
+            Rejection::IdTaken => ApiError::Conflict("the account is a member already".to_string()),
+            rejection => ApiError::rejected("member", rejection),
+        })?;
+    Ok((StatusCode::CREATED, Json(member)))
+}
+
+/// Change a member's role
+pub async fn update_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, user)): Path<(WorkspaceId, UserId)>,
+    ValidJson(patch): ValidJson<MemberPatch>,
+) -> Result<Json<Member>, ApiError> {
+    let caller = manager(&state, &auth, id, MANAGE_MEMBERS)?;
+    let current = state
+        .db
+        .membership(id, user)?
+        .ok_or(ApiError::NotFound("member"))?;
+    check_owner_change(&caller, &[current.role, patch.role])?;
+    let member = state
+        .db
+        .set_member_role(id, user, patch.role)?
+        .map_err(|rejection| ApiError::rejected("member", rejection))?;
+    Ok(Json(member))
+}
+
+/// Remove a member, or leave the workspace
+pub async fn remove_member(
+    State(state): State<AppState>,
+    auth: AuthUser,
+    Path((id, user)): Path<(WorkspaceId, UserId)>,
+) -> Result<StatusCode, ApiError> {
+    if user != auth.user.id {
+        let caller = manager(&state, &auth, id, MANAGE_MEMBERS)?;
+        let current = state
+            .db
+            .membership(id, user)?
+            .ok_or(ApiError::NotFound("member"))?;
+        check_owner_change(&caller, &[current.role])?;
+    }
+    state
+        .db
+        .remove_member(id, user)?
+        .map_err(|rejection| ApiError::rejected("member", rejection))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup() -> (AppState, AuthUser, AuthUser, WorkspaceId) {
+        let state = AppState::for_tests();
+        let ada = AuthUser::for_tests(&state, "ada");
+        let grace = AuthUser::for_tests(&state, "grace");
+        let workspace = state.db.create_workspace(ada.user.id, "Acme").unwrap();
+        (state, ada, grace, workspace.id)
+    }
+
+    #[tokio::test]
+    async fn test_only_managers_add_members() {
+        let (state, ada, grace, workspace) = setup();
+        let request = NewMember {
+            username: "grace".to_string(),
+            role: Role::Member,
+        };
+        let (_, member) = add_member(
+            State(state.clone()),
+            ada.clone(),
+            Path(workspace),
+            ValidJson(request),
+        )
+        .await
+        .unwrap();
+        assert_eq!(member.role, Role::Member);
+
+        let request = NewMember {
+            username: "ada".to_string(),
+            role: Role::Viewer,
+        };
+        let result = add_member(
+            State(state.clone()),
+            grace.clone(),
+            Path(workspace),
+            ValidJson(request),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+
+        // Members can leave on their own.
+        let left = remove_member(
+            State(state.clone()),
+            grace.clone(),
+            Path((workspace, grace.user.id)),
+        )
+        .await
+        .unwrap();
+        assert_eq!(left, StatusCode::NO_CONTENT);
+        let result = list_members(State(state), grace, Path(workspace)).await;
+        assert!(matches!(result, Err(ApiError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_admins_cannot_appoint_owners() {
+        let (state, ada, grace, workspace) = setup();
+        state
+            .db
+            .add_member(workspace, "grace", Role::Admin)
+            .unwrap()
+            .unwrap();
+        let patch = MemberPatch { role: Role::Viewer };
+        let result = update_member(
+            State(state.clone()),
+            grace,
+            Path((workspace, ada.user.id)),
+            ValidJson(patch),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Forbidden(_))));
+    }
+
+    #[tokio::test]
+    async fn test_default_rate_needs_a_currency() {
+        let (state, ada, _, workspace) = setup();
+        let patch = WorkspacePatch {
+            hourly_rate: Some(Some(90.0)),
+            ..WorkspacePatch::default()
+        };
+        let result = update_workspace(
+            State(state.clone()),
+            ada.clone(),
+            Path(workspace),
+            ValidJson(patch),
+        )
+        .await;
+        assert!(matches!(result, Err(ApiError::Validation(_))));
+
+        let patch = WorkspacePatch {
+            hourly_rate: Some(Some(90.0)),
+            currency: Some(Some("EUR".to_string())),
+            ..WorkspacePatch::default()
+        };
+        let updated = update_workspace(State(state), ada, Path(workspace), ValidJson(patch))
+            .await
+            .unwrap();
+        assert_eq!(updated.name, "Acme");
+        assert_eq!(updated.hourly_rate, Some(90.0));
+    }
+}
@@ -14,6 +14,7 @@ export interface Project {
   id: string;
   name: string;
   color: string;
+  archived?: boolean;
 }
 
 export interface Category {